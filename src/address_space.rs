@@ -1,9 +1,14 @@
 //! This module provides the [`AddressSpace`] struct which provides an abstraction over a virtual
 //! address space and provides methods to introspect and manage the virtual address space.
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use core::convert::TryFrom;
 use core::marker::PhantomData;
 use core::ops::Range;
-use crate::PageFormat;
+use crate::{FormatError, PageFormat};
+use crate::format::MAX_LEVELS;
 use crate::walkers::*;
 
 /// The [`AddressSpace`] struct expects a type implementing this trait in order to map the page
@@ -18,6 +23,14 @@ pub trait PageTableMapper<Error> {
     /// An `Error` constant indicating that a function has not been implemented.
     const NOT_IMPLEMENTED: Error;
 
+    /// Returns the `Error` indicating that the page at the given virtual address was not present.
+    /// The walkers use this function rather than [`PageTableMapper::PAGE_NOT_PRESENT`], such that
+    /// the faulting virtual address can be captured, e.g. using [`crate::WalkError`]. The default
+    /// implementation simply returns [`PageTableMapper::PAGE_NOT_PRESENT`].
+    fn page_not_present(_virt: usize) -> Error {
+        Self::PAGE_NOT_PRESENT
+    }
+
     /// Reads the PTE at the given physical address.
     fn read_pte(&self, pte_size: usize, phys_addr: u64) -> Result<u64, Error> {
         match pte_size {
@@ -35,6 +48,19 @@ pub trait PageTableMapper<Error> {
         }
     }
 
+    /// Reads consecutive PTEs starting at the given physical address into the given buffer, and
+    /// returns the number of PTEs read. This is used by [`PageFormat::walk`] to read the PTEs of a
+    /// page table in batches rather than one by one, which is useful when every access to the page
+    /// tables is costly, e.g. for a remote target. The default implementation simply invokes
+    /// [`PageTableMapper::read_pte`] for every PTE.
+    fn read_ptes(&self, pte_size: usize, phys_addr: u64, ptes: &mut [u64]) -> Result<usize, Error> {
+        for (i, pte) in ptes.iter_mut().enumerate() {
+            *pte = self.read_pte(pte_size, phys_addr + (i * pte_size) as u64)?;
+        }
+
+        Ok(ptes.len())
+    }
+
     /// Writes the PTE to the given physical address.
     fn write_pte(&mut self, pte_size: usize, phys_addr: u64, value: u64) -> Result<(), Error> {
         match pte_size {
@@ -62,20 +88,149 @@ pub trait PageTableMapper<Error> {
         Err(Self::NOT_IMPLEMENTED)
     }
 
-    /// Allocates a physical page.
+    /// Allocates a physical page. The page does not have to be zeroed, as the walkers invoke
+    /// [`PageTableMapper::zero_page`] before using a newly allocated page as a page table.
     fn alloc_page(&mut self) -> Result<u64, Error> {
         Err(Self::NOT_IMPLEMENTED)
     }
 
+    /// Allocates a physically contiguous huge page of the given size that is aligned to its size.
+    /// Returns `None` if no such huge page could be allocated, in which case the walkers fall back
+    /// to allocating page tables and smaller pages. Huge pages are freed using
+    /// [`PageTableMapper::free_page`] like any other page. The default implementation always
+    /// returns `None`.
+    fn alloc_huge_page(&mut self, _size: usize) -> Result<Option<u64>, Error> {
+        Ok(None)
+    }
+
+    /// Maps the page table at the given physical address with the given number of 64-bit PTEs, and
+    /// returns the PTEs as a slice. This allows scanning a whole page table at once, which is far
+    /// more efficient than reading the PTEs one by one, e.g. for [`crate::walkers::PteRemover`] to
+    /// check whether a page table is empty. The PTEs are in the byte order of the page tables. The
+    /// slice must hold at least the given number of PTEs. Returns `None` if the mapper does not
+    /// support mapping page tables, in which case the PTEs are read using
+    /// [`PageTableMapper::read_ptes`] instead. Any error is passed on to the caller. The default
+    /// implementation always returns `None`.
+    fn map_table(&self, _phys_addr: u64, _entries: usize) -> Result<Option<&[u64]>, Error> {
+        Ok(None)
+    }
+
+    /// Zeroes the given number of bytes of the page at the given physical address, which is used
+    /// to clear a newly allocated page table before it is linked into the page table hierarchy.
+    /// The default implementation writes zeroed PTEs of the given PTE size using
+    /// [`PageTableMapper::write_pte`]. Mappers that can zero pages more efficiently, or that
+    /// already return zeroed pages from [`PageTableMapper::alloc_page`], may want to override
+    /// this function.
+    fn zero_page(&mut self, pte_size: usize, phys_addr: u64, size: usize) -> Result<(), Error> {
+        for offset in (0..size).step_by(pte_size) {
+            self.write_pte(pte_size, phys_addr + offset as u64, 0)?;
+        }
+
+        Ok(())
+    }
+
     /// Frees a physical page.
     fn free_page(&mut self, _pte: u64) {
     }
 }
 
-/// Abstracts a virtual address space.
-pub struct AddressSpace<'a, Mapper, Error>
+/// Walks the given virtual address range using the given walker that fills PTE holes. With the
+/// `alloc` feature enabled, the PTE holes filled by the walker are recorded, such that they can be
+/// rolled back if the walk fails. Upon rollback, the filled PTEs are cleared in reverse order and
+/// the page tables they refer to are freed. The pages they refer to are only freed if `free_pages`
+/// is set, i.e. when the walker allocated them.
+#[cfg(feature = "alloc")]
+fn walk_transaction<Walker, Mapper, Error>(
+    format: &PageFormat,
+    root: u64,
+    mapper: &mut Mapper,
+    range: Range<usize>,
+    walker: Walker,
+    free_pages: bool,
+) -> Result<(), Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    let mut transaction = PteTransaction {
+        walker,
+        journal: Vec::new(),
+        format,
+        error: PhantomData,
+        mapper: PhantomData,
+    };
+
+    let result = format.walk_mut(root, range, &mut transaction, mapper);
+
+    if result.is_err() {
+        for (index, virt_addr, pte) in transaction.journal.into_iter().rev() {
+            // Clear the PTE at the recorded page level. The rollback is best-effort, as the
+            // original error is more relevant to the caller.
+            let mut cleared = false;
+            let mut walker = PteMutator {
+                f: |pte_type: crate::PteType, _range: Range<usize>, entry: &mut u64| {
+                    let level = &format.levels[index];
+
+                    // Check that the PTE still refers to the page or page table that has been
+                    // allocated, as the flags may have been changed, e.g. the accessed bit.
+                    if pte_type.level() == index &&
+                        level.is_present(*entry) &&
+                        format.pte_to_phys(*entry) == format.pte_to_phys(pte) {
+                        *entry = level.empty_pte();
+                        cleared = true;
+                    }
+                },
+                tables: true,
+                error: PhantomData,
+                mapper: PhantomData,
+            };
+
+            let result = format.walk_mut(root, virt_addr..virt_addr + 1, &mut walker, mapper);
+
+            // Only free the page or page table if the PTE referring to it has actually been
+            // cleared. Otherwise, it is still linked into the page table hierarchy.
+            if result.is_err() || !cleared {
+                continue;
+            }
+
+            let level = &format.levels[index];
+
+            if free_pages || (index != 0 && !level.is_huge_page(pte)) {
+                mapper.free_page(format.pte_to_phys(pte));
+            }
+        }
+    }
+
+    result
+}
+
+/// Walks the given virtual address range using the given walker that fills PTE holes. Without the
+/// `alloc` feature, the PTE holes filled by the walker cannot be recorded, and are therefore not
+/// rolled back if the walk fails.
+#[cfg(not(feature = "alloc"))]
+fn walk_transaction<Walker, Mapper, Error>(
+    format: &PageFormat,
+    root: u64,
+    mapper: &mut Mapper,
+    range: Range<usize>,
+    mut walker: Walker,
+    _free_pages: bool,
+) -> Result<(), Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    format.walk_mut(root, range, &mut walker, mapper)
+}
+
+/// Abstracts a virtual address space. By default, the address space borrows the type
+/// implementing [`PageTableMapper`] mutably, but it can also own it (see
+/// [`AddressSpace::new_owned`]), as the address space accepts any type that can be borrowed as
+/// the mapper.
+pub struct AddressSpace<'a, Mapper, Error, M = &'a mut Mapper>
 where
     Mapper: PageTableMapper<Error>,
+    M: BorrowMut<Mapper>,
 {
     /// The page table format describing the page table hierarchy for this virtual address space.
     format: PageFormat<'a>,
@@ -83,11 +238,14 @@ where
     /// The root address of the page table hierarchy.
     root: u64,
 
-    /// The type implementing PageTableMapper.
-    mapper: &'a mut Mapper,
+    /// The type implementing PageTableMapper, or a reference to it.
+    mapper: M,
+
+    /// A marker for Mapper.
+    marker: PhantomData<Mapper>,
 
     /// A marker for Error.
-    error: core::marker::PhantomData<Error>,
+    error: PhantomData<Error>,
 }
 
 impl<'a, Mapper, Error> AddressSpace<'a, Mapper, Error>
@@ -102,24 +260,269 @@ where
             format,
             mapper,
             root,
+            marker: PhantomData,
             error: PhantomData,
         }
     }
 
+    /// Creates a new address space like [`AddressSpace::new`], but validates the page format using
+    /// [`PageFormat::validate`] and the pointer to the root of the page table hierarchy first. The
+    /// root has to be aligned to the smallest page size and must not have any bits set outside of
+    /// the physical address bits that the page format can encode in a PTE.
+    pub fn try_new(format: PageFormat<'a>, mapper: &'a mut Mapper, root: u64) -> Result<Self, FormatError> {
+        format.validate()?;
+
+        if root & (format.min_page_size() as u64 - 1) != 0 {
+            return Err(FormatError::UnalignedRoot);
+        }
+
+        if format.pte_to_phys(format.phys_to_pte(root)) != root {
+            return Err(FormatError::RootOutsidePhysicalMask);
+        }
+
+        Ok(Self::new(format, mapper, root))
+    }
+}
+
+impl<'a, Mapper, Error> AddressSpace<'a, Mapper, Error, Mapper>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Creates a new address space for the given page table format descripting the page table
+    /// hierarchy, the page table mapper and the pointer to the root of the page table hierarchy.
+    /// Unlike [`AddressSpace::new`], the address space takes ownership of the page table mapper,
+    /// such that the address space can be stored or moved around together with its mapper.
+    pub fn new_owned(format: PageFormat<'a>, mapper: Mapper, root: u64) -> Self {
+        Self {
+            format,
+            mapper,
+            root,
+            marker: PhantomData,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<'a, Mapper, Error, M> AddressSpace<'a, Mapper, Error, M>
+where
+    Mapper: PageTableMapper<Error>,
+    M: BorrowMut<Mapper>,
+{
+    /// Consumes the address space and returns the page table mapper, or the reference to it.
+    pub fn into_mapper(self) -> M {
+        self.mapper
+    }
+
+    /// Returns a read-only view of the address space that borrows the page table mapper
+    /// immutably. See [`ReadOnlyAddressSpace`] for more details.
+    pub fn read_only(&self) -> ReadOnlyAddressSpace<'_, Mapper, Error> {
+        ReadOnlyAddressSpace::new(self.format.clone(), self.mapper.borrow(), self.root)
+    }
+
+    /// Returns the physical address of the root page table of the page table hierarchy, e.g. to
+    /// load it into the hardware register that points to the active page tables.
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Sets the physical address of the root page table of the page table hierarchy, such that
+    /// subsequent operations use the page table hierarchy at the given root instead. This allows
+    /// reusing the address space for several page table hierarchies that share the same mapper,
+    /// e.g. those of different processes.
+    pub fn set_root(&mut self, root: u64) {
+        self.root = root;
+    }
+
+    /// Returns the page table format describing the page table hierarchy.
+    pub fn format(&self) -> &PageFormat<'a> {
+        &self.format
+    }
+
     /// Reads the PTE for the given the virtual address if the virtual address is valid.
     pub fn read_pte(&self, virt_addr: usize) -> Result<u64, Error> {
-        let mut walker = PteReader {
-            pte: None,
+        self.read_pte_at(virt_addr).map(|(pte, _)| pte)
+    }
+
+    /// Reads the PTE for the given virtual address if the virtual address is valid, and returns
+    /// the PTE together with the index of the page level at which the PTE was found. The page
+    /// level can be used to determine the page size, e.g. to tell huge pages apart.
+    pub fn read_pte_at(&self, virt_addr: usize) -> Result<(u64, usize), Error> {
+        self.read_only().read_pte_at(virt_addr)
+    }
+
+    /// Translates the given virtual address to the physical address it maps to. Returns `None` if
+    /// the virtual address is not backed by a page that is present.
+    pub fn translate(&self, virt_addr: usize) -> Result<Option<u64>, Error> {
+        self.read_only().translate(virt_addr)
+    }
+
+    /// Checks whether the given virtual address is backed by a page that is present, including
+    /// huge pages. Unlike [`AddressSpace::read_pte`], this function returns `false` rather than an
+    /// error if the virtual address is not mapped, such that errors are reserved for failures of
+    /// the mapper.
+    pub fn is_mapped(&self, virt_addr: usize) -> Result<bool, Error> {
+        self.read_only().is_mapped(virt_addr)
+    }
+
+    /// Translates the given virtual address and returns the physical addresses of all the page
+    /// tables visited along the way. See [`ReadOnlyAddressSpace::translate_path`].
+    pub fn translate_path(&self, virt_addr: usize) -> Result<([u64; MAX_LEVELS], usize), Error> {
+        self.read_only().translate_path(virt_addr)
+    }
+
+    /// Returns an iterator over the mappings of the pages that are present within the given
+    /// virtual address range in ascending order of their virtual addresses. Each page results in
+    /// a separate [`Mapping`].
+    #[cfg(feature = "alloc")]
+    pub fn iter_mappings(&self, range: Range<usize>) -> Result<impl Iterator<Item = Mapping>, Error> {
+        let mut walker = MappingCollector {
+            mappings: Vec::new(),
+            coalesce: false,
+            format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk(self.root, virt_addr..virt_addr + 1, &mut walker, self.mapper)?;
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
 
-        match walker.pte {
-            Some(pte) => Ok(pte),
-            _ => Err(Mapper::PTE_NOT_FOUND),
-        }
+        Ok(walker.mappings.into_iter())
+    }
+
+    /// Returns an iterator over the mappings of the pages that are present within the given
+    /// virtual address range in ascending order of their virtual addresses. Unlike
+    /// [`AddressSpace::iter_mappings`], consecutive pages are merged into a single [`Mapping`] as
+    /// long as they are virtually and physically contiguous, and share the same page size and
+    /// flags.
+    #[cfg(feature = "alloc")]
+    pub fn iter_mappings_coalesced(&self, range: Range<usize>) -> Result<impl Iterator<Item = Mapping>, Error> {
+        let mut walker = MappingCollector {
+            mappings: Vec::new(),
+            coalesce: true,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
+
+        Ok(walker.mappings.into_iter())
+    }
+
+    /// Returns an iterator over the virtual address ranges of the pages that are present within
+    /// the given virtual address range, but that do not have any of the bits in the given global
+    /// mask set, in ascending order. These are the pages that need to be flushed from the TLB on a
+    /// context switch. The global mask is architecture-specific, e.g.
+    /// [`crate::arch::x86_64::PAGE_GLOBAL`] on x86-64.
+    #[cfg(feature = "alloc")]
+    pub fn iter_non_global(&self, range: Range<usize>, global_mask: u64) -> Result<impl Iterator<Item = Range<usize>>, Error> {
+        let mappings = self.iter_mappings(range)?;
+
+        Ok(mappings
+            .filter(move |mapping| mapping.flags & global_mask == 0)
+            .map(|mapping| mapping.virt))
+    }
+
+    /// Determines the smallest virtual address range that contains all the pages that are present
+    /// within the given virtual address range, i.e. the range from the start of the first page
+    /// that is present to the end of the last page that is present, clamped to the given range.
+    /// Returns `None` if no pages are present.
+    pub fn mapped_extent(&self, range: Range<usize>) -> Result<Option<Range<usize>>, Error> {
+        let mut walker = ExtentWalker {
+            extent: None,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
+
+        Ok(walker.extent)
+    }
+
+    /// Applies the given closure to every PTE within the given range in the virtual address space,
+    /// i.e. both the PTEs of pages and the PTEs of page tables, in the order in which they are
+    /// walked. The closure is given the [`crate::PteType`], which includes the page level, the
+    /// virtual address range and the PTE. This allows inspecting the PTEs without implementing
+    /// [`crate::PageWalker`].
+    pub fn for_each_pte<F>(&self, range: Range<usize>, f: F) -> Result<(), Error>
+    where
+        F: FnMut(crate::PteType, Range<usize>, u64),
+    {
+        let mut walker = PteVisitor {
+            f,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
+
+        Ok(())
+    }
+
+    /// Walks the given virtual address range using the given walker, which allows running a custom
+    /// [`crate::PageWalker`] without having to supply the root and the mapper. See
+    /// [`PageFormat::walk`].
+    pub fn walk<Walker>(&self, range: Range<usize>, walker: &mut Walker) -> Result<(), Error>
+    where
+        Walker: crate::PageWalker<Mapper, Error>,
+    {
+        self.format.walk(self.root, range, walker, self.mapper.borrow())
+    }
+
+    /// Counts the pages that are present within the given range in the virtual address space per
+    /// page level, such that the returned array contains the number of pages of the page size of
+    /// each page level, e.g. to quantify how much of the range is covered by huge pages. The
+    /// number of bytes covered per page level can be derived by multiplying the counts by
+    /// [`crate::PageLevel::page_size`].
+    pub fn page_size_histogram(&self, range: Range<usize>) -> Result<[usize; MAX_LEVELS], Error> {
+        let mut histogram = [0; MAX_LEVELS];
+        let format = &self.format;
+
+        self.for_each_pte(range, |pte_type, _range, pte| {
+            if let crate::PteType::Page(level, _) = pte_type {
+                if format.levels[level].is_present(pte) {
+                    histogram[level] += 1;
+                }
+            }
+        })?;
+
+        Ok(histogram)
+    }
+
+    /// Counts the number of page tables that would have to be allocated to map all the pages
+    /// within the given virtual address range, e.g. using [`AddressSpace::map_range`], without
+    /// modifying the page tables. This allows environments with constrained allocators to reserve
+    /// the page tables up front.
+    pub fn count_required_tables(&self, range: Range<usize>) -> Result<usize, Error> {
+        let mut walker = TableCounter {
+            count: 0,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
+
+        Ok(walker.count)
+    }
+
+    /// Counts the bytes consumed by the page tables backing the mappings within the given virtual
+    /// address range, including the root page table. Every page table is counted once, even if
+    /// it is shared by multiple pages within the range. This can be used to quantify the cost of
+    /// the page tables for a sparse layout compared to a dense layout.
+    pub fn table_overhead_bytes(&self, range: Range<usize>) -> Result<usize, Error> {
+        let root = self.format.levels[self.format.levels.len() - 1].entries() * self.format.pte_size;
+
+        let mut walker = TableSizeCounter {
+            bytes: root,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
+
+        Ok(walker.bytes)
     }
 
     /// Writes the PTE for the given virtual address if the virtual address is valid.
@@ -130,38 +533,315 @@ where
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, virt_addr..virt_addr + 1, &mut walker, self.mapper)?;
+        self.format.walk_mut(self.root, virt_addr..virt_addr + 1, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Applies the given closure to the PTE of every page within the given range in the virtual
+    /// address space, including pages that are not present. The closure is given the
+    /// [`crate::PteType`], the virtual address range and a mutable reference to the PTE, which it
+    /// may leave unchanged. This allows modifying the PTEs without implementing
+    /// [`crate::PageWalkerMut`].
+    pub fn for_each_pte_mut<F>(&mut self, range: Range<usize>, f: F) -> Result<(), Error>
+    where
+        F: FnMut(crate::PteType, Range<usize>, &mut u64),
+    {
+        let mut walker = PteMutator {
+            f,
+            tables: false,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
 
         Ok(())
     }
 
+    /// Walks the given virtual address range using the given walker, which allows running a custom
+    /// [`crate::PageWalkerMut`] without having to supply the root and the mapper. See
+    /// [`PageFormat::walk_mut`].
+    pub fn walk_mut<Walker>(&mut self, range: Range<usize>, walker: &mut Walker) -> Result<(), Error>
+    where
+        Walker: crate::PageWalkerMut<Mapper, Error>,
+    {
+        self.format.walk_mut(self.root, range, walker, self.mapper.borrow_mut())
+    }
+
     /// Allocates pages and the underlying page tables for a given range in the virtual address
-    /// space. The pages are protected using the given mask.
+    /// space. The pages are protected using the given mask. Pages that are already present,
+    /// including huge pages, are left intact, such that only the holes in the given range get
+    /// filled. With the `alloc` feature enabled, the pages and page tables allocated by this
+    /// function are freed if the allocation fails midway, such that the address space is left
+    /// unchanged.
     pub fn allocate_range(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
-        let mut walker = PteAllocator {
+        let walker = PteAllocator {
             mask: Some(mask),
+            huge_pages: false,
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), range, walker, true)
+    }
 
-        Ok(())
+    /// Allocates pages and the underlying page tables for a given range in the virtual address
+    /// space like [`AddressSpace::allocate_range`], but invokes the given closure to supply the
+    /// physical page for each page that has to be allocated, e.g. to allocate the pages from a
+    /// specific NUMA node or from device memory. The closure is given the virtual address of the
+    /// page. The page tables are still allocated using [`PageTableMapper::alloc_page`]. With the
+    /// `alloc` feature enabled, the mappings and page tables set up by this function are removed if
+    /// the allocation fails midway. However, the pages supplied by the closure are not freed, as
+    /// they are owned by the caller.
+    pub fn allocate_range_with<F>(&mut self, range: Range<usize>, mask: u64, f: F) -> Result<(), Error>
+    where
+        F: FnMut(usize) -> Result<u64, Error>,
+    {
+        let walker = PteFrameAllocator {
+            allocator: PteAllocator {
+                mask: None,
+                huge_pages: false,
+                format: &self.format,
+                error: PhantomData,
+                mapper: PhantomData,
+            },
+            mask,
+            f,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), range, walker, false)
+    }
+
+    /// Allocates pages and the underlying page tables for a given range in the virtual address
+    /// space like [`AddressSpace::allocate_range`], but allocates huge pages using
+    /// [`PageTableMapper::alloc_huge_page`] wherever the page level supports them and the virtual
+    /// address range covers the whole huge page. The remaining parts of the range, as well as the
+    /// huge pages that could not be allocated, fall back to smaller pages.
+    pub fn allocate_range_huge(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
+        let walker = PteAllocator {
+            mask: Some(mask),
+            huge_pages: true,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), range, walker, true)
     }
 
     /// Maps the given range in the virtual address space range to the given physical address
     /// offset and mask. Allocates the underlying page tables if they are missing. This is useful
-    /// for memory-mapped I/O.
+    /// for memory-mapped I/O. With the `alloc` feature enabled, the mappings and page tables set up
+    /// by this function are removed if the mapping fails midway, such that the address space is
+    /// left unchanged.
     pub fn map_range(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
-        let mut walker = PteMapper {
+        let phys_base = self.format.pte_to_phys(mask);
+
+        self.map_range_to(range, phys_base, mask & !self.format.address_mask())
+    }
+
+    /// Maps the given range in the virtual address space to the contiguous physical address range
+    /// starting at the given physical base address, and protects the pages using the given mask.
+    /// That is, each page in the virtual address range is mapped to the physical base address
+    /// plus the offset of that page within the virtual address range. Allocates the underlying
+    /// page tables if they are missing. This is useful for memory-mapped I/O.
+    pub fn map_range_to(&mut self, virt: Range<usize>, phys_base: u64, mask: u64) -> Result<(), Error> {
+        let walker = PteMapper {
+            virt_base: virt.start,
+            phys_base,
             mask,
+            flags: None,
+            huge_pages: false,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), virt, walker, false)
+    }
+
+    /// Maps the given range in the virtual address space to the contiguous physical address range
+    /// starting at the given physical base address like [`AddressSpace::map_range_to`], but
+    /// determines the mask of each page by invoking the given closure with the virtual address and
+    /// the page level of that page. This allows mapping a range where the protection flags vary
+    /// per page, e.g. to map a guard page in the middle of the range.
+    pub fn map_range_with<F>(&mut self, virt: Range<usize>, phys_base: u64, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize) -> u64,
+    {
+        let walker = PteMapper {
+            virt_base: virt.start,
+            phys_base,
+            mask: 0,
+            flags: Some(&mut f),
+            huge_pages: false,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), virt, walker, false)
+    }
+
+    /// Maps the given range in the virtual address space to the contiguous physical address range
+    /// starting at the given physical base address like [`AddressSpace::map_range_to`], but uses
+    /// huge pages wherever the page level supports them and both the virtual address range and the
+    /// physical address range are aligned to the huge page size. The remaining parts of the range
+    /// fall back to smaller pages.
+    pub fn map_range_huge(&mut self, virt: Range<usize>, phys_base: u64, mask: u64) -> Result<(), Error> {
+        let walker = PteMapper {
+            virt_base: virt.start,
+            phys_base,
+            mask,
+            flags: None,
+            huge_pages: true,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), virt, walker, false)
+    }
+
+    /// Populates the page for the given virtual address on demand, e.g. to resolve a page fault
+    /// in a pager. This allocates the underlying page tables if they are missing, and invokes the
+    /// given closure to supply the physical address and the flags of the page, which is then
+    /// mapped at the leaf page level. Returns the PTE of the page. If the page is already present,
+    /// the closure is not invoked and the existing PTE is returned instead. With the `alloc`
+    /// feature enabled, the page tables allocated by this function are freed again if the closure
+    /// returns an error.
+    pub fn populate<F>(&mut self, virt_addr: usize, f: F) -> Result<u64, Error>
+    where
+        F: FnOnce() -> Result<(u64, u64), Error>,
+    {
+        let walker = PtePopulator {
+            allocator: PteAllocator {
+                mask: None,
+                huge_pages: false,
+                format: &self.format,
+                error: PhantomData,
+                mapper: PhantomData,
+            },
+            f: Some(f),
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), virt_addr..virt_addr + 1, walker, false)?;
+
+        self.read_pte(virt_addr)
+    }
+
+    /// Maps the given physical address range such that each virtual address is equal to its
+    /// physical address, and protects the pages using the given mask. This uses huge pages
+    /// wherever the range is suitably aligned like [`AddressSpace::map_range_huge`]. This is
+    /// useful to set up an identity map, e.g. for the early boot code of a kernel. Returns
+    /// [`PageTableMapper::PTE_NOT_FOUND`] if the physical address range does not fit in the
+    /// virtual address space.
+    pub fn identity_map(&mut self, phys_range: Range<u64>, mask: u64) -> Result<(), Error> {
+        let virt_base = usize::try_from(phys_range.start).map_err(|_| Mapper::PTE_NOT_FOUND)?;
+
+        self.offset_map(virt_base, phys_range, mask)
+    }
+
+    /// Maps the given physical address range to the virtual address range starting at the given
+    /// virtual base address, and protects the pages using the given mask. That is, each virtual
+    /// address is the physical address plus a fixed offset. This uses huge pages wherever the
+    /// ranges are suitably aligned like [`AddressSpace::map_range_huge`]. This is useful to set up
+    /// the direct map of physical memory of a kernel, e.g. at `PAGE_OFFSET` on Linux. Returns
+    /// [`PageTableMapper::PTE_NOT_FOUND`] if the physical address range is inverted, or if the
+    /// virtual address range would extend beyond the end of the virtual address space.
+    pub fn offset_map(&mut self, virt_base: usize, phys_range: Range<u64>, mask: u64) -> Result<(), Error> {
+        let size = phys_range.end.checked_sub(phys_range.start)
+            .and_then(|size| usize::try_from(size).ok())
+            .ok_or(Mapper::PTE_NOT_FOUND)?;
+        let virt_end = virt_base.checked_add(size).ok_or(Mapper::PTE_NOT_FOUND)?;
+
+        self.map_range_huge(virt_base..virt_end, phys_range.start, mask)
+    }
+
+    /// Changes the physical backing of the given range in the virtual address space, such that
+    /// each page is remapped to the given physical base address plus the offset of that page
+    /// within the virtual address range, while preserving the flags of each page. Huge pages are
+    /// remapped as a whole, and should therefore be covered by a suitably aligned physical address
+    /// range. Returns [`PageTableMapper::PAGE_NOT_PRESENT`] if any part of the range is not
+    /// mapped, as this function only remaps existing mappings. This function first checks that
+    /// the whole range is mapped, such that the page tables are left untouched upon failure.
+    pub fn remap_range(&mut self, range: Range<usize>, new_phys_base: u64) -> Result<(), Error> {
+        let mut walker = HoleChecker {
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range.clone(), &mut walker, self.mapper.borrow())?;
+
+        let mut walker = PteRemapper {
+            virt_base: range.start,
+            phys_base: new_phys_base,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Splits the huge page that maps the given virtual address into pages of the next page
+    /// level that map the same physical address range with the same protection flags. Huge pages
+    /// are split recursively until the given virtual address is mapped by a page of the smallest
+    /// page size. This function does nothing if the virtual address is already mapped by a page
+    /// of the smallest page size, and returns [`PageTableMapper::PAGE_NOT_PRESENT`] if the virtual
+    /// address is not mapped.
+    pub fn split_huge_page(&mut self, virt_addr: usize) -> Result<(), Error> {
+        let mut walker = PteSplitter {
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, virt_addr..virt_addr + 1, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Tries to merge the pages of the leaf page table covering the given virtual address into a
+    /// single huge page, and frees the leaf page table upon success. The pages can only be merged
+    /// if the page level supports huge pages, and if all the pages are present, physically
+    /// contiguous, aligned to the huge page size and share the same protection flags. Returns
+    /// `true` if the pages have been merged and `false` otherwise.
+    pub fn try_merge_huge_page(&mut self, virt_addr: usize) -> Result<bool, Error> {
+        let mut walker = PteMerger {
+            merged: false,
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        self.format.walk_mut(self.root, virt_addr..virt_addr + 1, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(walker.merged)
+    }
+
+    /// Clones the page table hierarchy for the given range in the virtual address space into the
+    /// page table hierarchy at the given destination root using the given destination page table
+    /// mapper. Page tables that are missing in the destination page table hierarchy are allocated,
+    /// whereas the PTEs of the pages are copied verbatim. As a result, both page table hierarchies
+    /// map the virtual address range to the same physical pages using the same page sizes, which
+    /// is useful to implement `fork()`.
+    pub fn clone_into(&self, dest_root: u64, dest_mapper: &mut Mapper, range: Range<usize>) -> Result<(), Error> {
+        let mut tables = [0; MAX_LEVELS];
+        tables[self.format.levels.len() - 1] = dest_root;
+
+        let mut walker = PteCloner {
+            tables,
+            dest_mapper,
+            format: &self.format,
+            error: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper.borrow())?;
 
         Ok(())
     }
@@ -172,12 +852,136 @@ where
     pub fn protect_range(&mut self, range: Range<usize>, mask: (u64, u64)) -> Result<(), Error> {
         let mut walker = PteProtector {
             mask,
+            sticky: 0,
+            tables: false,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Changes the protection flags of the given range in the virtual address space like
+    /// [`AddressSpace::protect_range`], but never clears the given sticky bits, e.g. the accessed
+    /// and dirty bits. This allows clearing a broad mask without losing the working set
+    /// information of the pages.
+    pub fn protect_range_sticky(&mut self, range: Range<usize>, mask: (u64, u64), sticky: u64) -> Result<(), Error> {
+        let mut walker = PteProtector {
+            mask,
+            sticky,
+            tables: false,
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Changes the protection flags of the given range in the virtual address space like
+    /// [`AddressSpace::protect_range`], and returns the virtual address ranges of the pages of
+    /// which the PTEs actually changed in ascending order. Pages that already have the requested
+    /// protection flags are not reported, such that the caller only has to invalidate the TLB and
+    /// instruction cache entries of the returned ranges.
+    #[cfg(feature = "alloc")]
+    pub fn protect_range_report(&mut self, range: Range<usize>, mask: (u64, u64)) -> Result<Vec<Range<usize>>, Error> {
+        let mut walker = PteChangeRecorder {
+            walker: PteProtector {
+                mask,
+                sticky: 0,
+                tables: false,
+                format: &self.format,
+                error: PhantomData,
+                mapper: PhantomData,
+            },
+            changed: Vec::new(),
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(walker.changed)
+    }
+
+    /// Changes the protection flags of the given range in the virtual address space like
+    /// [`AddressSpace::protect_range`]. In addition, the bits of the second mask that are part of
+    /// the page table mask of a page level are also set in the PTEs of the page tables mapping the
+    /// pages. On architectures where the effective permissions are the most restrictive
+    /// permissions along the page table walk, this ensures that granting a permission is actually
+    /// effective. Note that the bits are never cleared in the PTEs of the page tables, as that
+    /// would affect the other pages mapped by these page tables.
+    pub fn protect_range_with_tables(&mut self, range: Range<usize>, mask: (u64, u64)) -> Result<(), Error> {
+        let mut walker = PteProtector {
+            mask,
+            sticky: 0,
+            tables: true,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Changes the protection flags of the given range in the virtual address space like
+    /// [`AddressSpace::protect_range`], but returns [`PageTableMapper::PAGE_NOT_PRESENT`] if any
+    /// part of the range is not mapped. This function is all-or-nothing: it first checks that the
+    /// whole range is mapped, and only then changes the protection flags, such that the page
+    /// tables are left untouched upon failure.
+    pub fn protect_range_strict(&mut self, range: Range<usize>, mask: (u64, u64)) -> Result<(), Error> {
+        let mut walker = HoleChecker {
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range.clone(), &mut walker, self.mapper.borrow())?;
+
+        self.protect_range(range, mask)
+    }
+
+    /// Marks the writable pages of the given range in the virtual address space as copy-on-write.
+    /// That is, for every page that is present and has all the bits in the write mask set, the
+    /// bits in the write mask are cleared and the bits in the copy-on-write mask are set. The
+    /// copy-on-write mask should consist of bits that are free for software use, such that a write
+    /// fault can later be identified as a copy-on-write fault. Read-only pages are left untouched.
+    pub fn mark_cow(&mut self, range: Range<usize>, write_mask: u64, cow_mask: u64) -> Result<(), Error> {
+        let mut walker = PteCowMarker {
+            write_mask,
+            cow_mask,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Propagates the permission bits selected by the given mask from the pages in the given range
+    /// of the virtual address space to the page tables mapping them. That is, the union of the
+    /// permission bits of the PTEs within a page table is set in the PTE pointing to that page
+    /// table. On architectures where the effective permissions are the most restrictive
+    /// permissions along the page table walk, this ensures that a page table does not restrict the
+    /// permissions of the pages it maps, e.g. a user page mapped by a supervisor page table.
+    pub fn propagate_permissions(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
+        let mut walker = PteConsistencyFixer {
+            mask,
+            bits: [0; MAX_LEVELS],
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
 
         Ok(())
     }
@@ -185,68 +989,413 @@ where
     /// Frees the pages for the given range in the virtual address space. If the underlying page
     /// tables have been cleared, then this function also free the underlying page tables.
     pub fn free_range(&mut self, range: Range<usize>) -> Result<(), Error> {
-        let flags = PteRemovalFlags::all();
+        let flags = PteRemovalFlags::FREE_PAGES | PteRemovalFlags::FREE_PAGE_TABLES;
 
         let mut walker = PteRemover {
             flags,
+            cleared: [0; MAX_LEVELS],
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Frees the pages for the given range in the virtual address space like
+    /// [`AddressSpace::free_range`], but zeroes the contents of the pages before freeing them, such
+    /// that the contents do not leak to the next user of the pages.
+    pub fn free_range_zeroed(&mut self, range: Range<usize>) -> Result<(), Error> {
+        let flags = PteRemovalFlags::FREE_PAGES | PteRemovalFlags::FREE_PAGE_TABLES |
+            PteRemovalFlags::ZERO_PAGES;
+
+        let mut walker = PteRemover {
+            flags,
+            cleared: [0; MAX_LEVELS],
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Frees the pages for the given range in the virtual address space, but keeps the underlying
+    /// page tables allocated, such that the range can be remapped quickly.
+    pub fn free_range_keep_tables(&mut self, range: Range<usize>) -> Result<(), Error> {
+        let flags = PteRemovalFlags::FREE_PAGES;
+
+        let mut walker = PteRemover {
+            flags,
+            cleared: [0; MAX_LEVELS],
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
 
         Ok(())
     }
 
     /// Unmaps the pages for the given range in the virtual address space without freeing the
-    /// underlying pages. This is useful for memory-mapped I/O.
+    /// underlying pages. The underlying page tables are kept allocated. This is useful for
+    /// memory-mapped I/O.
     pub fn unmap_range(&mut self, range: Range<usize>) -> Result<(), Error> {
         let flags = PteRemovalFlags::empty();
 
         let mut walker = PteRemover {
             flags,
+            cleared: [0; MAX_LEVELS],
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Unmaps the pages for the given range in the virtual address space like
+    /// [`AddressSpace::unmap_range`], and returns the physical addresses of the pages that were
+    /// mapped in ascending order of their virtual addresses. The pages are not freed, such that the
+    /// caller can defer freeing them, e.g. until after a grace period or a TLB shootdown.
+    #[cfg(feature = "alloc")]
+    pub fn unmap_range_report(&mut self, range: Range<usize>) -> Result<Vec<u64>, Error> {
+        let format = &self.format;
+        let mut frames = Vec::new();
+
+        let recorder = PteMutator {
+            f: |pte_type: crate::PteType, _range, pte: &mut u64| {
+                if pte_type.is_page() && format.levels[pte_type.level()].is_present(*pte) {
+                    frames.push(format.pte_to_phys(*pte));
+                }
+            },
+            tables: false,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        let remover = PteRemover {
+            flags: PteRemovalFlags::empty(),
+            cleared: [0; MAX_LEVELS],
+            format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        // The recorder runs first, such that it observes the PTEs before they are cleared.
+        let mut walker = Tee::new(recorder, remover);
+
+        format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
+
+        Ok(frames)
+    }
+
+    /// Swaps out the pages for the given range in the virtual address space. For every page that
+    /// is present, including huge pages, the given closure is invoked with the virtual address of
+    /// the page and its PTE, and returns the swap entry to store in the PTE instead, e.g. to encode
+    /// the swap slot that holds the contents of the page. The swap entry is marked as not present
+    /// using [`PageLevel::clear_present`], such that the page faults upon access and the swap
+    /// entry is reported as a [`crate::HoleKind::NonZero`] PTE hole.
+    ///
+    /// The pages are not freed, as the caller may still have to write back their contents.
+    /// Instead, the physical address of each page can be derived from the PTE given to the closure
+    /// using [`PageFormat::pte_to_phys`], such that the caller can reuse the pages afterwards. To
+    /// swap a page back in, use [`AddressSpace::populate`] with a closure that reads the swap
+    /// entry using [`AddressSpace::read_pte`] and returns the physical address and the flags of
+    /// the page that the contents have been read into.
+    ///
+    /// [`PageLevel::clear_present`]: crate::level::PageLevel::clear_present
+    pub fn swap_out<F>(&mut self, range: Range<usize>, mut encode: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, u64) -> u64,
+    {
+        let format = &self.format;
+
+        let mut walker = PteMutator {
+            f: |pte_type: crate::PteType, range: Range<usize>, pte: &mut u64| {
+                let level = &format.levels[pte_type.level()];
+
+                if pte_type.is_page() && level.is_present(*pte) {
+                    *pte = level.clear_present(encode(range.start, *pte));
+                }
+            },
+            tables: false,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())?;
 
         Ok(())
     }
 
-    /// Copies bytes starting at the given address into the given buffer.
-    pub fn copy_from(&mut self, data: &mut [u8], address: usize) -> Result<(), Error> {
+    /// Copies bytes starting at the given address into the given buffer. Upon failure, this
+    /// function returns the error together with the virtual address at which the copy faulted,
+    /// i.e. the address of the first byte that could not be copied. If the copy faults on a page
+    /// that is not present, this is the base address of that page, unless the copy started in
+    /// the middle of that page, in which case it is the given address.
+    pub fn copy_from(&mut self, data: &mut [u8], address: usize) -> Result<(), (Error, usize)> {
+        self.read_only().copy_from(data, address)
+    }
+
+    /// Compares the bytes starting at the given address against the given buffer. Returns `None`
+    /// if all the bytes match, or the offset of the first byte that differs otherwise. Returns an
+    /// error if the range is not fully mapped before a difference has been found.
+    pub fn verify(&self, address: usize, expected: &[u8]) -> Result<Option<usize>, Error> {
+        self.read_only().verify(address, expected)
+    }
+
+    /// Copies bytes from the given buffer to the given address. Upon failure, this function
+    /// returns the error together with the virtual address at which the copy faulted like
+    /// [`AddressSpace::copy_from`].
+    pub fn copy_to(&mut self, address: usize, data: &[u8]) -> Result<(), (Error, usize)> {
         let range = address..address + data.len();
 
-        let mut walker = CopyFromWalker {
+        let mut walker = CopyToWalker {
             offset: 0,
             data,
+            pending: None,
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk(self.root, range, &mut walker, self.mapper)?;
+        let mapper = self.mapper.borrow_mut();
+
+        self.format.walk_mut(self.root, range, &mut walker, mapper)
+            .and_then(|_| walker.flush(mapper))
+            .map_err(|e| (e, address + walker.offset))?;
 
         Ok(())
     }
 
-    /// Copies bytes from the given buffer to the given address.
-    pub fn copy_to(&mut self, address: usize, data: &[u8]) -> Result<(), Error> {
+    /// Copies bytes from the given buffer to the given address like [`AddressSpace::copy_to`], but
+    /// allocates the pages and the underlying page tables for any part of the range that is not
+    /// mapped, and protects the newly allocated pages using the given mask. The part of a newly
+    /// allocated page that is not covered by the buffer is zeroed. This is useful to populate a
+    /// fresh address space, e.g. from an ELF image, in a single pass. With the `alloc` feature
+    /// enabled, the allocated pages and page tables are freed again if the copy fails.
+    pub fn copy_to_alloc(&mut self, address: usize, data: &[u8], mask: u64) -> Result<(), Error> {
         let range = address..address + data.len();
 
-        let mut walker = CopyToWalker {
+        let walker = CopyToAllocWalker {
+            walker: CopyToWalker {
+                offset: 0,
+                data,
+                pending: None,
+                format: &self.format,
+                error: PhantomData,
+                mapper: PhantomData,
+            },
+            allocator: PteAllocator {
+                mask: None,
+                huge_pages: false,
+                format: &self.format,
+                error: PhantomData,
+                mapper: PhantomData,
+            },
+            mask,
+        };
+
+        walk_transaction(&self.format, self.root, self.mapper.borrow_mut(), range, walker, true)
+    }
+
+    /// Fills the given virtual address range with the given byte, e.g. to zero or poison a mapped
+    /// region. Like [`AddressSpace::copy_to`], this returns an error if the range is not fully
+    /// mapped.
+    pub fn fill(&mut self, range: Range<usize>, byte: u8) -> Result<(), Error> {
+        let mut walker = FillWalker {
+            byte,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk_mut(self.root, range, &mut walker, self.mapper.borrow_mut())
+    }
+}
+
+/// A read-only view of a virtual address space that borrows the type implementing
+/// [`PageTableMapper`] immutably, and therefore only offers the methods that do not modify the
+/// page tables. Unlike [`AddressSpace`], the view can be shared between threads if the mapper
+/// implements [`Sync`], such that multiple read-only walks can run concurrently.
+pub struct ReadOnlyAddressSpace<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The page table format describing the page table hierarchy for this virtual address space.
+    format: PageFormat<'a>,
+
+    /// The root address of the page table hierarchy.
+    root: u64,
+
+    /// A reference to the type implementing PageTableMapper.
+    mapper: &'a Mapper,
+
+    /// A marker for Error.
+    error: PhantomData<Error>,
+}
+
+impl<'a, Mapper, Error> ReadOnlyAddressSpace<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Creates a new read-only view of the address space for the given page table format
+    /// describing the page table hierarchy, the page table mapper and the pointer to the root of
+    /// the page table hierarchy.
+    pub fn new(format: PageFormat<'a>, mapper: &'a Mapper, root: u64) -> Self {
+        Self {
+            format,
+            mapper,
+            root,
+            error: PhantomData,
+        }
+    }
+
+    /// Returns the physical address of the root page table of the page table hierarchy.
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Returns the page table format describing the page table hierarchy.
+    pub fn format(&self) -> &PageFormat<'a> {
+        &self.format
+    }
+
+    /// Walks the given virtual address range using the given walker. See
+    /// [`AddressSpace::walk`].
+    pub fn walk<Walker>(&self, range: Range<usize>, walker: &mut Walker) -> Result<(), Error>
+    where
+        Walker: crate::PageWalker<Mapper, Error>,
+    {
+        self.format.walk(self.root, range, walker, self.mapper)
+    }
+
+    /// Reads the PTE for the given the virtual address if the virtual address is valid.
+    pub fn read_pte(&self, virt_addr: usize) -> Result<u64, Error> {
+        self.read_pte_at(virt_addr).map(|(pte, _)| pte)
+    }
+
+    /// Reads the PTE for the given virtual address if the virtual address is valid, and returns
+    /// the PTE together with the index of the page level at which the PTE was found.
+    pub fn read_pte_at(&self, virt_addr: usize) -> Result<(u64, usize), Error> {
+        match self.format.resolve(self.root, virt_addr, self.mapper)? {
+            Some(entry) => Ok(entry),
+            _ => Err(Mapper::PTE_NOT_FOUND),
+        }
+    }
+
+    /// Translates the given virtual address to the physical address it maps to. Returns `None` if
+    /// the virtual address is not backed by a page that is present.
+    pub fn translate(&self, virt_addr: usize) -> Result<Option<u64>, Error> {
+        let (pte, level) = match self.format.resolve(self.root, virt_addr, self.mapper)? {
+            Some(entry) => entry,
+            _ => return Ok(None),
+        };
+
+        if !self.format.levels[level].is_present(pte) {
+            return Ok(None);
+        }
+
+        let offset = self.format.page_offset(virt_addr, level) as u64;
+
+        Ok(Some(self.format.pte_to_phys(pte) + offset))
+    }
+
+    /// Translates the given virtual address like [`ReadOnlyAddressSpace::translate`], but returns
+    /// the physical addresses of all the page tables visited along the way together with the
+    /// number of page tables visited. The first entries are the physical addresses of the page
+    /// tables starting at the root page table down to the page table holding the PTE of the page,
+    /// or the page table holding the PTE hole at which the translation stopped. If the virtual
+    /// address is backed by a page that is present, the entry following the page tables is the
+    /// physical address of the page frame. The remaining entries are zero. This is useful to
+    /// diagnose page table corruption.
+    pub fn translate_path(&self, virt_addr: usize) -> Result<([u64; MAX_LEVELS], usize), Error> {
+        let mut path = [0; MAX_LEVELS];
+        let mut count = 0;
+        let mut phys_addr = self.root;
+
+        for (index, level) in self.format.levels.iter().enumerate().rev() {
+            path[count] = phys_addr;
+            count += 1;
+
+            let offset = (level.pte_index(virt_addr) * self.format.pte_size) as u64;
+            let pte = self.format.read_pte(self.mapper, phys_addr + offset)?;
+
+            if !level.is_present(pte) {
+                break;
+            }
+
+            // The PTE refers to a page if we are at the leaf page table or if the PTE refers to a
+            // huge page.
+            if index == 0 || level.is_huge_page(pte) {
+                if count < MAX_LEVELS {
+                    path[count] = self.format.pte_to_phys(pte);
+                }
+
+                break;
+            }
+
+            phys_addr = self.format.pte_to_phys(pte);
+        }
+
+        Ok((path, count))
+    }
+
+    /// Checks whether the given virtual address is backed by a page that is present, including
+    /// huge pages.
+    pub fn is_mapped(&self, virt_addr: usize) -> Result<bool, Error> {
+        Ok(match self.format.resolve(self.root, virt_addr, self.mapper)? {
+            Some((pte, level)) => self.format.levels[level].is_present(pte),
+            _ => false,
+        })
+    }
+
+    /// Copies bytes starting at the given address into the given buffer. Upon failure, this
+    /// function returns the error together with the virtual address at which the copy faulted
+    /// like [`AddressSpace::copy_from`].
+    pub fn copy_from(&self, data: &mut [u8], address: usize) -> Result<(), (Error, usize)> {
+        let range = address..address + data.len();
+
+        let mut walker = CopyFromWalker {
             offset: 0,
             data,
+            pending: None,
             format: &self.format,
             error: PhantomData,
             mapper: PhantomData,
         };
 
-        self.format.walk_mut(self.root, range, &mut walker, self.mapper)?;
+        self.format.walk(self.root, range, &mut walker, self.mapper)
+            .and_then(|_| walker.flush(self.mapper))
+            .map_err(|e| (e, address + walker.offset))?;
 
         Ok(())
     }
+
+    /// Compares the bytes starting at the given address against the given buffer like
+    /// [`AddressSpace::verify`].
+    pub fn verify(&self, address: usize, expected: &[u8]) -> Result<Option<usize>, Error> {
+        let range = address..address + expected.len();
+
+        let mut walker = VerifyWalker {
+            offset: 0,
+            expected,
+            mismatch: None,
+            format: &self.format,
+            error: PhantomData,
+            mapper: PhantomData,
+        };
+
+        self.format.walk(self.root, range, &mut walker, self.mapper)?;
+
+        Ok(walker.mismatch)
+    }
 }
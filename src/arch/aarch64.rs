@@ -1,8 +1,122 @@
 //! This module provides the page table formats available for the AArch64 architecture.
-use lazy_static::lazy_static;
-use crate::{PageFormat, PageLevel};
+use bitflags::bitflags;
+use crate::{Endian, PageFormat, PageLevel};
 
-static PAGE_LEVELS_4K: &'static [PageLevel] = &[
+/// The page is accessible from EL0 (`AP[1]`).
+pub const PAGE_AP_USER:      u64 = 1 << 6;
+/// The page is read-only (`AP[2]`).
+pub const PAGE_AP_READ_ONLY: u64 = 1 << 7;
+/// The page is not executable at EL1 (PXN).
+pub const PAGE_PXN:          u64 = 1 << 53;
+/// The page is not executable at EL0 (UXN).
+pub const PAGE_UXN:          u64 = 1 << 54;
+/// The mask of the `AP[2:1]`, UXN and PXN bits of a stage 1 descriptor.
+pub const PAGE_AP_MASK:      u64 = PAGE_AP_USER | PAGE_AP_READ_ONLY | PAGE_PXN | PAGE_UXN;
+
+bitflags! {
+    /// The flags of a stage 1 descriptor as typed flags, such that masks can be composed safely,
+    /// e.g. `AArch64PteFlags::ACCESS | AArch64PteFlags::AP_USER`. Use [`AArch64PteFlags::bits`] to
+    /// get the mask for functions such as [`crate::AddressSpace::map_range`] and
+    /// [`crate::AddressSpace::protect_range`].
+    pub struct AArch64PteFlags: u64 {
+        /// The descriptor is valid.
+        const VALID         = 1 << 0;
+        /// The descriptor is a table descriptor, or a page descriptor at the leaf page level.
+        /// Without this bit, a valid descriptor at an intermediate page level is a block
+        /// descriptor.
+        const TABLE         = 1 << 1;
+        /// The page is accessible from EL0 (`AP[1]`).
+        const AP_USER       = PAGE_AP_USER;
+        /// The page is read-only (`AP[2]`).
+        const AP_READ_ONLY  = PAGE_AP_READ_ONLY;
+        /// The page is outer shareable (`SH[1:0]` = 0b10).
+        const OUTER_SHARED  = 0b10 << 8;
+        /// The page is inner shareable (`SH[1:0]` = 0b11).
+        const INNER_SHARED  = 0b11 << 8;
+        /// The page has been accessed (AF).
+        const ACCESS        = 1 << 10;
+        /// The page is not global, i.e. its TLB entry is tagged with the ASID (nG).
+        const NOT_GLOBAL    = 1 << 11;
+        /// The page is not executable at EL1 (PXN).
+        const PXN           = PAGE_PXN;
+        /// The page is not executable at EL0 (UXN).
+        const UXN           = PAGE_UXN;
+    }
+}
+
+/// The access permissions of a page as encoded by the `AP[2:1]`, UXN and PXN bits of a stage 1
+/// descriptor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Permissions {
+    /// The page is readable from EL0. Note that the page is always readable from EL1.
+    pub readable: bool,
+    /// The page is writable. If the page is readable from EL0, then it is also writable from EL0.
+    pub writable: bool,
+    /// The page is executable at EL0.
+    pub user_exec: bool,
+    /// The page is executable at EL1.
+    pub kernel_exec: bool,
+}
+
+/// Decodes the `AP[2:1]`, UXN and PXN bits of the given stage 1 descriptor into [`Permissions`].
+pub fn decode_ap(pte: u64) -> Permissions {
+    Permissions {
+        readable: pte & PAGE_AP_USER != 0,
+        writable: pte & PAGE_AP_READ_ONLY == 0,
+        user_exec: pte & PAGE_UXN == 0,
+        kernel_exec: pte & PAGE_PXN == 0,
+    }
+}
+
+/// Encodes the given [`Permissions`] into the `AP[2:1]`, UXN and PXN bits of a stage 1 descriptor.
+/// The resulting bits can be used as the mask for [`crate::AddressSpace::protect_range`] together
+/// with [`PAGE_AP_MASK`] to clear the existing access permissions.
+pub fn encode_ap(permissions: Permissions) -> u64 {
+    let mut pte = 0;
+
+    if permissions.readable {
+        pte |= PAGE_AP_USER;
+    }
+
+    if !permissions.writable {
+        pte |= PAGE_AP_READ_ONLY;
+    }
+
+    if !permissions.user_exec {
+        pte |= PAGE_UXN;
+    }
+
+    if !permissions.kernel_exec {
+        pte |= PAGE_PXN;
+    }
+
+    pte
+}
+
+/// The PTE bits that hold the physical address bits 51:50 for 52-bit physical addresses with a
+/// page size of 4K or 16K as introduced by `FEAT_LPA2`, i.e. the PTE bits 9:8, together with the
+/// number of bits to shift them to the left. This can be used as [`PageFormat::physical_high`],
+/// where the physical mask covers the physical address bits 49:12.
+pub const PHYSICAL_HIGH_LPA_4K: (u64, u32) = (0b11 << 8, 42);
+
+/// The PTE bits that hold the physical address bits 51:48 for 52-bit physical addresses with a
+/// page size of 64K as introduced by `FEAT_LPA`, i.e. the PTE bits 15:12, together with the number
+/// of bits to shift them to the left. This can be used as [`PageFormat::physical_high`], e.g. for
+/// the page format returned by [`format_for_txsz`], where the physical mask covers the physical
+/// address bits 47:16.
+pub const PHYSICAL_HIGH_LPA_64K: (u64, u32) = (0b1111 << 12, 36);
+
+/// The names of the page levels of AArch64, from the leaf page level up to the root page level,
+/// where the lookup levels are numbered starting from the root page level of a four-level page
+/// table layout.
+const LEVEL_NAMES: &[&str] = &["L3", "L2", "L1", "L0"];
+
+// The descriptor type is encoded in bits 1:0. At the leaf page level, 0b11 is a page descriptor.
+// At the intermediate page levels, 0b11 is a table descriptor and 0b01 is a block descriptor, i.e.
+// a huge page. Since the present bit is also part of the huge page check, a descriptor is only
+// considered a huge page if bit 0 is set and bit 1 is clear, whereas an invalid descriptor with
+// bit 0 clear is never considered a huge page.
+const PAGE_LEVELS_4K: &[PageLevel] = &[
     PageLevel {
         shift_bits: 12,
         va_bits: 9,
@@ -33,102 +147,264 @@ static PAGE_LEVELS_4K: &'static [PageLevel] = &[
     },
 ];
 
-lazy_static! {
-    /// A page table layout for AArch64 consisting of three page levels with 64-bit PTEs and a page
-    /// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
-    /// address to index into the page table. Furthermore, it supports 2M huge pages and 1G huge
-    /// pages. Finally, while the number of physical address bits supported is CPU-specific, the
-    /// maximum is 52 bits. This format is commonly used instead of `PAGE_FORMAT_4K_L4` to reduce
-    /// the depth of the page table walk to improve the performance of virtual address translation.
-    pub static ref PAGE_FORMAT_4K_L3: PageFormat<'static> = PageFormat {
-        levels: &PAGE_LEVELS_4K[0..3],
-        physical_mask: 0x000f_ffff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// A page table layout for AArch64 consisting of three page levels with 64-bit PTEs and a page
+/// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
+/// address to index into the page table. Furthermore, it supports 2M huge pages and 1G huge
+/// pages. Finally, while the number of physical address bits supported is CPU-specific, the
+/// maximum is 52 bits. This format is commonly used instead of `PAGE_FORMAT_4K_L4` to reduce
+/// the depth of the page table walk to improve the performance of virtual address translation.
+pub const PAGE_FORMAT_4K_L3: PageFormat<'static> = PageFormat {
+    levels: PAGE_LEVELS_4K.split_at(3).0,
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES.split_at(3).0),
+    endian: Endian::Native,
+};
 
-    /// A page table layout for AArch64 consisting of four page levels with 64-bit PTEs and a page
-    /// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
-    /// address to index into the page table. Furthermore, it supports 2M huge pages and 1G huge
-    /// pages. Finally, while the number of physical address bits supported is CPU-specific, the
-    /// maximum is 52 bits.
-    pub static ref PAGE_FORMAT_4K_L4: PageFormat<'static> = PageFormat {
-        levels: &PAGE_LEVELS_4K[0..3],
-        physical_mask: 0x000f_ffff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// A page table layout for AArch64 consisting of four page levels with 64-bit PTEs and a page
+/// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
+/// address to index into the page table. Furthermore, it supports 2M huge pages and 1G huge
+/// pages. Finally, while the number of physical address bits supported is CPU-specific, the
+/// maximum is 52 bits.
+pub const PAGE_FORMAT_4K_L4: PageFormat<'static> = PageFormat {
+    levels: PAGE_LEVELS_4K,
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES),
+    endian: Endian::Native,
+};
 
-    /// A page table layout for AArch64 consisting of four page levels with 64-bit PTEs and a page
-    /// size of 16K. Therefore, each page table has 2048 entries and uses 11 bits of the virtual
-    /// address to index into the page table, except for the root page table. The root page table
-    /// instead only consists of two entries and only uses 1 bit of the virtual address to index
-    /// into this page table. Finally, while the number of physical address bits supported is
-    /// CPU-specific, the maximum is 52 bits. This page table format is rather exotic.
-    pub static ref PAGE_FORMAT_16K: PageFormat<'static> = PageFormat {
-        levels: &[
-            PageLevel {
-                shift_bits: 12,
-                va_bits: 11,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 23,
-                va_bits: 11,
-                present_bit: (1 << 0, 1 << 0),
-                huge_page_bit: (1 << 1, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 34,
-                va_bits: 11,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 45,
-                va_bits: 1,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
+/// A page table layout for AArch64 consisting of four page levels with 64-bit PTEs and a page
+/// size of 4K like `PAGE_FORMAT_4K_L4`, but with 52-bit physical addresses as introduced by
+/// `FEAT_LPA2`. As the PTE bits 51:50 are not available for the physical address, the physical
+/// address bits 51:50 are instead stored in the PTE bits 9:8. See [`PageFormat::pte_to_phys`]
+/// and [`PageFormat::phys_to_pte`].
+pub const PAGE_FORMAT_4K_L4_LPA: PageFormat<'static> = PageFormat {
+    levels: PAGE_LEVELS_4K,
+    physical_mask: 0x0003_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: Some(PHYSICAL_HIGH_LPA_4K),
+    names: Some(LEVEL_NAMES),
+    endian: Endian::Native,
+};
+
+/// A page table layout for AArch64 consisting of four page levels with 64-bit PTEs and a page
+/// size of 16K. Therefore, each page table has 2048 entries and uses 11 bits of the virtual
+/// address to index into the page table, except for the root page table. The root page table
+/// instead only consists of two entries and only uses 1 bit of the virtual address to index
+/// into this page table. Finally, while the number of physical address bits supported is
+/// CPU-specific, the maximum is 52 bits. This page table format is rather exotic.
+pub const PAGE_FORMAT_16K: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 11,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 23,
+            va_bits: 11,
+            present_bit: (1 << 0, 1 << 0),
+            huge_page_bit: (1 << 1, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 34,
+            va_bits: 11,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 45,
+            va_bits: 1,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+    ],
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES),
+    endian: Endian::Native,
+};
+
+/// A page table layout for AArch64 consisting of three page levels with 64-bit PTEs and a page
+/// size of 64K. Therefore, each page table has 8192 entries and uses 13 bits of the virtual
+/// address to index into the page table, except for the root page table. The root page table
+/// instead only consists of 64 entries and only uses 6 bit of the virtual address to index
+/// into this page table. Finally, while the number of physical address bits supported is
+/// CPU-specific, the maximum is 52 bits. This page table format is rather exotic.
+pub const PAGE_FORMAT_64K: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 13,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 25,
+            va_bits: 13,
+            present_bit: (1 << 0, 1 << 0),
+            huge_page_bit: (1 << 1, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 38,
+            va_bits: 6,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+    ],
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES.split_at(3).0),
+    endian: Endian::Native,
+};
+
+/// The default page format is a four-level page table hierarchy with 4K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K_L4;
+
+/// The translation granule as configured by the `TG0` and `TG1` fields of `TCR_ELx`, i.e. the size
+/// of the pages and page tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granule {
+    /// 4K pages, where each page table has 512 entries.
+    Size4K,
+    /// 16K pages, where each page table has 2048 entries.
+    Size16K,
+    /// 64K pages, where each page table has 8192 entries.
+    Size64K,
+}
+
+impl Granule {
+    /// Returns the number of bits of the page offset.
+    pub const fn page_shift(self) -> usize {
+        match self {
+            Granule::Size4K => 12,
+            Granule::Size16K => 14,
+            Granule::Size64K => 16,
+        }
+    }
+
+    /// Returns the number of bits of the virtual address that are used to index into a full page
+    /// table.
+    pub const fn level_bits(self) -> usize {
+        self.page_shift() - 3
+    }
+
+    /// Returns whether block descriptors, i.e. huge pages, are supported at the page level with
+    /// the given index, where index zero is the leaf page level. 4K granules support 2M and 1G
+    /// blocks, whereas 16K and 64K granules only support 32M and 512M blocks respectively.
+    const fn supports_blocks(self, index: usize) -> bool {
+        match self {
+            Granule::Size4K => index == 1 || index == 2,
+            _ => index == 1,
+        }
+    }
+}
+
+/// The smallest `TxSZ` value supported by [`format_for_txsz`], i.e. a 48-bit virtual address space.
+pub const MIN_TXSZ: u8 = 16;
+/// The largest `TxSZ` value supported by [`format_for_txsz`], i.e. a 25-bit virtual address space.
+pub const MAX_TXSZ: u8 = 39;
+
+/// The number of `TxSZ` values supported by [`format_for_txsz`].
+const TXSZ_COUNT: usize = (MAX_TXSZ - MIN_TXSZ + 1) as usize;
+
+/// A placeholder for the unused page levels in the tables below.
+const UNUSED_LEVEL: PageLevel = PageLevel {
+    shift_bits: 0,
+    va_bits: 0,
+    present_bit: (0, 0),
+    huge_page_bit: (0, 0),
+    page_table_mask: 0,
+};
+
+/// Returns the number of page levels and the number of bits of the virtual address used to index
+/// into the root page table for the given `TxSZ` value and granule.
+const fn txsz_layout(txsz: u8, granule: Granule) -> (usize, usize) {
+    let bits = 64 - txsz as usize - granule.page_shift();
+    let count = bits.div_ceil(granule.level_bits());
+
+    (count, bits - (count - 1) * granule.level_bits())
+}
+
+/// Builds the page levels for every supported `TxSZ` value for the given granule, such that
+/// [`format_for_txsz`] can hand out static slices. The descriptor types are encoded as described
+/// for `PAGE_LEVELS_4K`.
+const fn txsz_levels(granule: Granule) -> [[PageLevel; 4]; TXSZ_COUNT] {
+    const UNUSED: [PageLevel; 4] = [UNUSED_LEVEL; 4];
+    let mut table = [UNUSED; TXSZ_COUNT];
+    let mut txsz = MIN_TXSZ;
+
+    while txsz <= MAX_TXSZ {
+        let (count, root_bits) = txsz_layout(txsz, granule);
+        let mut index = 0;
+
+        while index < count {
+            let (present_bit, huge_page_bit) = match index {
+                0 => ((1 << 0 | 1 << 1, 1 << 0 | 1 << 1), (0, 0)),
+                _ if granule.supports_blocks(index) => ((1 << 0, 1 << 0), (1 << 1, 0)),
+                _ => ((1 << 0 | 1 << 1, 1 << 0 | 1 << 1), (0, 0)),
+            };
+
+            table[(txsz - MIN_TXSZ) as usize][index] = PageLevel {
+                shift_bits: granule.page_shift() + index * granule.level_bits(),
+                va_bits: if index + 1 == count { root_bits } else { granule.level_bits() },
+                present_bit,
+                huge_page_bit,
                 page_table_mask: 0,
-            },
-        ],
-        physical_mask: 0x000f_ffff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
+            };
+
+            index += 1;
+        }
+
+        txsz += 1;
+    }
+
+    table
+}
+
+static TXSZ_LEVELS_4K: [[PageLevel; 4]; TXSZ_COUNT] = txsz_levels(Granule::Size4K);
+static TXSZ_LEVELS_16K: [[PageLevel; 4]; TXSZ_COUNT] = txsz_levels(Granule::Size16K);
+static TXSZ_LEVELS_64K: [[PageLevel; 4]; TXSZ_COUNT] = txsz_levels(Granule::Size64K);
+
+/// Returns the page table layout for the given `TxSZ` value, i.e. the `T0SZ` or `T1SZ` field of
+/// `TCR_ELx`, and the given granule. The size of the virtual address space is `64 - txsz` bits,
+/// which determines the number of page levels, i.e. the initial lookup level, as well as the
+/// number of bits used to index into the root page table. For instance, with 4K pages a `TxSZ` of
+/// 25 results in a 39-bit virtual address space with three page levels, whereas a `TxSZ` of 16
+/// results in a 48-bit virtual address space with four page levels. Returns `None` if `txsz` is
+/// outside of the range from [`MIN_TXSZ`] to [`MAX_TXSZ`].
+pub fn format_for_txsz(txsz: u8, granule: Granule) -> Option<PageFormat<'static>> {
+    if !(MIN_TXSZ..=MAX_TXSZ).contains(&txsz) {
+        return None;
+    }
+
+    let table = match granule {
+        Granule::Size4K => &TXSZ_LEVELS_4K,
+        Granule::Size16K => &TXSZ_LEVELS_16K,
+        Granule::Size64K => &TXSZ_LEVELS_64K,
     };
 
-    /// A page table layout for AArch64 consisting of three page levels with 64-bit PTEs and a page
-    /// size of 64K. Therefore, each page table has 8192 entries and uses 13 bits of the virtual
-    /// address to index into the page table, except for the root page table. The root page table
-    /// instead only consists of 64 entries and only uses 6 bit of the virtual address to index
-    /// into this page table. Finally, while the number of physical address bits supported is
-    /// CPU-specific, the maximum is 52 bits. This page table format is rather exotic.
-    pub static ref PAGE_FORMAT_64K: PageFormat<'static> = PageFormat {
-        levels: &[
-            PageLevel {
-                shift_bits: 12,
-                va_bits: 13,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 25,
-                va_bits: 13,
-                present_bit: (1 << 0, 1 << 0),
-                huge_page_bit: (1 << 1, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 38,
-                va_bits: 6,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-        ],
+    let (count, _) = txsz_layout(txsz, granule);
+
+    Some(PageFormat {
+        levels: &table[(txsz - MIN_TXSZ) as usize][..count],
         physical_mask: 0x000f_ffff_ffff_f000,
         pte_size: core::mem::size_of::<u64>(),
-    };
+        physical_high: None,
+        names: Some(&LEVEL_NAMES[..count]),
+        endian: Endian::Native,
+    })
 }
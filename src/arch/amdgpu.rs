@@ -0,0 +1,72 @@
+//! This module provides the page table formats available for the GPU virtual memory (GPUVM) of AMD
+//! GPUs.
+//!
+//! The PTEs of GPUVM have a fragment field that indicates that the PTE is part of a larger
+//! physically contiguous and suitably aligned range of pages, which allows the GPU to cache the
+//! whole fragment in a single TLB entry. The page formats in this module do not model fragments,
+//! and treat each PTE as a single page instead. As the fragment field only serves as a hint, this
+//! does not affect the translation of virtual addresses.
+use crate::{Endian, PageFormat, PageLevel};
+
+/// The page is valid, i.e. present.
+pub const PAGE_VALID:      u64 = 1 << 0;
+/// The page is located in system memory rather than video memory.
+pub const PAGE_SYSTEM:     u64 = 1 << 1;
+/// The page is snooped by the CPU caches.
+pub const PAGE_SNOOPED:    u64 = 1 << 2;
+/// The page is executable.
+pub const PAGE_EXECUTABLE: u64 = 1 << 4;
+/// The page is readable.
+pub const PAGE_READABLE:   u64 = 1 << 5;
+/// The page is writeable.
+pub const PAGE_WRITEABLE:  u64 = 1 << 6;
+/// The mask of the fragment field, which is currently not modeled by the page formats.
+pub const PAGE_FRAGMENT:   u64 = 0x1f << 7;
+/// The page directory entry (PDE) refers to a page rather than a page table, i.e. a huge page.
+pub const PAGE_PDE_PTE:    u64 = 1 << 54;
+
+/// A page table layout for GPUVM as used by GFX9 and later consisting of four page levels
+/// with 64-bit PTEs and a page size of 4K. Therefore, each page table has 512 entries and uses
+/// 9 bits of the virtual address to index into the page table, resulting in a 48-bit virtual
+/// address space. Furthermore, it supports 2M and 1G huge pages, for which the PDE is marked
+/// as a PTE. Finally, up to 48 bits of physical address are supported.
+pub const PAGE_FORMAT_GFX9: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 9,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 21,
+            va_bits: 9,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (PAGE_PDE_PTE, PAGE_PDE_PTE),
+            page_table_mask: PAGE_VALID,
+        },
+        PageLevel {
+            shift_bits: 30,
+            va_bits: 9,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (PAGE_PDE_PTE, PAGE_PDE_PTE),
+            page_table_mask: PAGE_VALID,
+        },
+        PageLevel {
+            shift_bits: 39,
+            va_bits: 9,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: PAGE_VALID,
+        },
+    ],
+    physical_mask: 0x0000_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// The default page format is a four-level page table hierarchy with 4K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_GFX9;
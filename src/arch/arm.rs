@@ -1,67 +1,115 @@
 //! This module provides the page table formats available for the ARMv7-A architecture.
-use lazy_static::lazy_static;
-use crate::{PageFormat, PageLevel};
+use crate::{Endian, PageFormat, PageLevel};
 
-lazy_static! {
-    /// A page table layout for ARMv7-A consisting of two page levels with 32-bit PTEs and a page
-    /// size of 4K. The leaf page table has 256 entries and uses 8 bits of the virtual address to
-    /// index into the page table, whereas the root page table has 4096 entries and uses 12 bits of
-    /// the virtual address to index into the page table. Furthermore, it supports 1M huge pages.
-    pub static ref PAGE_FORMAT_4K: PageFormat<'static> = PageFormat {
-        levels: &[
-            PageLevel {
-                shift_bits: 12,
-                va_bits: 8,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 20,
-                va_bits: 12,
-                present_bit: (1 << 0, 1 << 0),
-                huge_page_bit: (1 << 1, 0),
-                page_table_mask: 0,
-            },
-        ],
-        physical_mask: 0xffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// The first-level descriptor refers to a section or supersection rather than a page table.
+pub const PAGE_SECTION:      u64 = 1 << 1;
+/// The section descriptor refers to a 16M supersection rather than a 1M section.
+pub const PAGE_SUPERSECTION: u64 = 1 << 18;
 
-    /// A page table layout for ARMv7-A consisting of three page levels with 64-bit PTEs, through
-    /// the Long Physical Address Extension (LPAE) feature, and a page size of 4K. Therefore, each
-    /// page table has 512 entries and uses 9 bits of the virtual address to index into the page
-    /// table, except for the root page table. The root page table has four entries and uses 2 bits
-    /// of the virtual address to index into the page table. Furthermore, it supports 2M huge
-    /// pages and 1G huge pages.
-    pub static ref PAGE_FORMAT_4K_PAE: PageFormat<'static> = PageFormat {
-        levels: &[
-            PageLevel {
-                shift_bits: 12,
-                va_bits: 9,
-                present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
-                huge_page_bit: (0, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 21,
-                va_bits: 9,
-                present_bit: (1 << 0, 1 << 0),
-                huge_page_bit: (1 << 1, 0),
-                page_table_mask: 0,
-            },
-            PageLevel {
-                shift_bits: 30,
-                va_bits: 2,
-                present_bit: (1 << 0, 1 << 0),
-                huge_page_bit: (1 << 1, 0),
-                page_table_mask: 0,
-            },
-        ],
-        physical_mask: 0x0000_00ff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// The size of a supersection.
+pub const SUPERSECTION_SIZE: usize = 16 << 20;
+/// The number of consecutive first-level descriptors that describe a single supersection.
+pub const SUPERSECTION_ENTRIES: usize = 16;
 
-    /// The default page format is a two-level page table hierarchy with 4K pages.
-    pub static ref DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K.clone();
+/// Checks whether the given first-level descriptor of the short-descriptor format refers to a
+/// 16M supersection. A supersection is described by [`SUPERSECTION_ENTRIES`] consecutive
+/// first-level descriptors that are identical, such that any virtual address within the
+/// supersection resolves to one of these replicated descriptors.
+pub fn is_supersection(pte: u64) -> bool {
+    pte & 0b11 == PAGE_SECTION && pte & PAGE_SUPERSECTION != 0
 }
+
+/// Resolves the given virtual address within a supersection to its physical address, given one of
+/// the replicated first-level descriptors describing the supersection. The replicated descriptors
+/// all hold the physical base address of the 16M supersection rather than the physical address of
+/// the 1M region that they cover, so the offset is taken from the lower 24 bits of the virtual
+/// address. This also takes the extended physical address bits 32 to 39 into account.
+pub fn supersection_phys_addr(pte: u64, virt_addr: usize) -> u64 {
+    let base = pte & 0xff00_0000;
+    let extended = ((pte >> 20) & 0xf) << 32 | ((pte >> 5) & 0xf) << 36;
+
+    extended | base | (virt_addr & (SUPERSECTION_SIZE - 1)) as u64
+}
+
+/// Creates the [`SUPERSECTION_ENTRIES`] replicated first-level descriptors for a supersection that
+/// maps the given physical base address, which must be aligned to [`SUPERSECTION_SIZE`], using
+/// the given flags. The descriptors should be written to the consecutive first-level entries
+/// starting at the entry for the 16M-aligned virtual address.
+pub fn make_supersection_ptes(phys_addr: u64, flags: u64) -> [u64; SUPERSECTION_ENTRIES] {
+    let base = phys_addr & 0xff00_0000;
+    let extended = ((phys_addr >> 32) & 0xf) << 20 | ((phys_addr >> 36) & 0xf) << 5;
+    let mask = !(0xff00_0000 | 0xf << 20 | 0xf << 5 | 0b11);
+
+    [extended | base | (flags & mask) | PAGE_SUPERSECTION | PAGE_SECTION; SUPERSECTION_ENTRIES]
+}
+
+/// A page table layout for ARMv7-A consisting of two page levels with 32-bit PTEs and a page
+/// size of 4K. The leaf page table has 256 entries and uses 8 bits of the virtual address to
+/// index into the page table, whereas the root page table has 4096 entries and uses 12 bits of
+/// the virtual address to index into the page table. Furthermore, it supports 1M huge pages.
+/// Supersections are not modelled by this format, as they are described by 16 replicated
+/// first-level descriptors. Instead, a walker can use [`is_supersection`] and
+/// [`supersection_phys_addr`] to resolve a virtual address within a supersection.
+pub const PAGE_FORMAT_4K: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 8,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 20,
+            va_bits: 12,
+            present_bit: (1 << 0, 1 << 0),
+            huge_page_bit: (1 << 1, 0),
+            page_table_mask: 0,
+        },
+    ],
+    physical_mask: 0xffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// A page table layout for ARMv7-A consisting of three page levels with 64-bit PTEs, through
+/// the Long Physical Address Extension (LPAE) feature, and a page size of 4K. Therefore, each
+/// page table has 512 entries and uses 9 bits of the virtual address to index into the page
+/// table, except for the root page table. The root page table has four entries and uses 2 bits
+/// of the virtual address to index into the page table. Furthermore, it supports 2M huge
+/// pages and 1G huge pages.
+pub const PAGE_FORMAT_4K_PAE: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 9,
+            present_bit: (1 << 0 | 1 << 1, 1 << 0 | 1 << 1),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 21,
+            va_bits: 9,
+            present_bit: (1 << 0, 1 << 0),
+            huge_page_bit: (1 << 1, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 30,
+            va_bits: 2,
+            present_bit: (1 << 0, 1 << 0),
+            huge_page_bit: (1 << 1, 0),
+            page_table_mask: 0,
+        },
+    ],
+    physical_mask: 0x0000_00ff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// The default page format is a two-level page table hierarchy with 4K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K;
@@ -0,0 +1,52 @@
+//! This module provides the page table formats available for the MIPS32 architecture.
+//!
+//! Unlike most other architectures, MIPS does not have a hardware page table walker. Instead, the
+//! TLB is managed in software, and the TLB refill handler of the operating system is responsible
+//! for walking the page tables and for loading the EntryLo registers. As a result, the layout of
+//! the page tables is defined by the operating system rather than the architecture. The page
+//! formats in this module model the layout commonly used by operating systems, where each PTE
+//! stores the physical address of the page in place and the low bits mirror the flags of the
+//! EntryLo registers, such that the Valid (V) bit is used as the present bit.
+//!
+//! Furthermore, every TLB entry maps a pair of consecutive virtual pages, where the even page is
+//! loaded into EntryLo0 and the odd page into EntryLo1. The page formats in this module do not
+//! model these pairs, and instead treat each PTE as a single page. It is up to the TLB refill
+//! handler to load the PTEs of the even and odd page into the same TLB entry.
+use crate::{Endian, PageFormat, PageLevel};
+
+/// The page is global, i.e. the ASID is ignored when matching the TLB entry.
+pub const PAGE_GLOBAL: u64 = 1 << 0;
+/// The page is valid, i.e. present.
+pub const PAGE_VALID:  u64 = 1 << 1;
+/// The page is dirty, i.e. writeable.
+pub const PAGE_DIRTY:  u64 = 1 << 2;
+
+/// A page table layout for MIPS32 consisting of two page levels with 32-bit PTEs and a page
+/// size of 4K. Therefore, each page table has 1024 entries and uses 10 bits of the virtual
+/// address to index into the page table. Huge pages are not supported.
+pub const PAGE_FORMAT_4K: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 12,
+            va_bits: 10,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 22,
+            va_bits: 10,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: PAGE_VALID,
+        },
+    ],
+    physical_mask: 0xffff_f000,
+    pte_size: core::mem::size_of::<u32>(),
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// The default page format is a two-level page table hierarchy with 4K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K;
@@ -1,6 +1,59 @@
-//! This module provides architecture-specific page formats.
+//! This module provides architecture-specific page formats, and the [`default_format`] function
+//! to select the default page format of an architecture given by [`Arch`].
+
+use crate::PageFormat;
 
 pub mod aarch64;
+pub mod amdgpu;
 pub mod arm;
+pub mod mips;
+pub mod sparc;
 pub mod x86;
 pub mod x86_64;
+
+/// The architectures for which this crate provides page formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Arch {
+    /// 32-bit x86, see [`x86`].
+    X86,
+    /// x86-64, see [`x86_64`].
+    X86_64,
+    /// AArch64, see [`aarch64`].
+    Aarch64,
+    /// 32-bit ARM, see [`arm`].
+    Arm,
+    /// MIPS, see [`mips`].
+    Mips,
+    /// SPARC, see [`sparc`].
+    Sparc,
+    /// AMD GPUs, see [`amdgpu`].
+    AmdGpu,
+}
+
+impl Arch {
+    /// All the architectures for which this crate provides page formats.
+    pub const ALL: [Arch; 7] = [
+        Arch::X86,
+        Arch::X86_64,
+        Arch::Aarch64,
+        Arch::Arm,
+        Arch::Mips,
+        Arch::Sparc,
+        Arch::AmdGpu,
+    ];
+}
+
+/// Returns the default page format of the given architecture, i.e. the `DEFAULT_PAGE_FORMAT` of
+/// the corresponding module. This allows tools to select the page format by architecture without
+/// having to refer to the individual modules.
+pub const fn default_format(arch: Arch) -> &'static PageFormat<'static> {
+    match arch {
+        Arch::X86 => &x86::DEFAULT_PAGE_FORMAT,
+        Arch::X86_64 => &x86_64::DEFAULT_PAGE_FORMAT,
+        Arch::Aarch64 => &aarch64::DEFAULT_PAGE_FORMAT,
+        Arch::Arm => &arm::DEFAULT_PAGE_FORMAT,
+        Arch::Mips => &mips::DEFAULT_PAGE_FORMAT,
+        Arch::Sparc => &sparc::DEFAULT_PAGE_FORMAT,
+        Arch::AmdGpu => &amdgpu::DEFAULT_PAGE_FORMAT,
+    }
+}
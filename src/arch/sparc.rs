@@ -0,0 +1,85 @@
+//! This module provides the page table formats available for the SPARC V9 architecture with the
+//! sun4v hypervisor interface.
+//!
+//! Like MIPS, SPARC does not have a hardware page table walker. Instead, translations are loaded
+//! into the TLB from a Translation Storage Buffer (TSB), which is a cache of Translation Table
+//! Entries (TTEs) that is filled by the TLB miss handler of the operating system. As a result, the
+//! layout of the page tables is defined by the operating system rather than the architecture. The
+//! page formats in this module model a simplified layout where each PTE is a sun4v TTE data word,
+//! such that the TLB miss handler can load the PTE into the TSB as is. The Valid (V) bit is used as
+//! the present bit, and the page size is encoded in the size field of the TTE.
+//!
+//! Since the size field of a TTE cannot tell a huge page apart from a PTE referring to a page
+//! table, a bit that is free for software use marks huge pages, similar to what operating systems
+//! do.
+use crate::{Endian, PageFormat, PageLevel};
+
+/// The TTE maps an 8K page.
+pub const PAGE_SZ8K:      u64 = 0;
+/// The TTE maps a 64K page.
+pub const PAGE_SZ64K:     u64 = 1;
+/// The TTE maps a 512K page.
+pub const PAGE_SZ512K:    u64 = 2;
+/// The TTE maps a 4M page.
+pub const PAGE_SZ4M:      u64 = 3;
+/// The mask of the size field of the TTE.
+pub const PAGE_SZ_MASK:   u64 = 0x7;
+/// The page is writeable.
+pub const PAGE_WRITE:     u64 = 1 << 6;
+/// The page is executable.
+pub const PAGE_EXEC:      u64 = 1 << 7;
+/// The page is privileged, i.e. only accessible in supervisor mode.
+pub const PAGE_PRIV:      u64 = 1 << 8;
+/// The page is cacheable in the virtually indexed cache.
+pub const PAGE_CV:        u64 = 1 << 9;
+/// The page is cacheable in the physically indexed cache.
+pub const PAGE_CP:        u64 = 1 << 10;
+/// The page is a huge page. This bit is free for software use.
+pub const PAGE_HUGE:      u64 = 1 << 56;
+/// The page is valid, i.e. present.
+pub const PAGE_VALID:     u64 = 1 << 63;
+
+/// A page table layout for SPARC V9 (sun4v) consisting of three page levels with 64-bit PTEs
+/// and a page size of 8K. The leaf page table has 512 entries and uses 9 bits of the virtual
+/// address to index into the page table, whereas the other page tables have 1024 entries and
+/// use 10 bits of the virtual address to index into the page table. Furthermore, it supports
+/// 4M huge pages at the second page level, which should have their size field set to
+/// [`PAGE_SZ4M`]. The 64K and 512K page sizes supported by the TTE are not modelled as page
+/// levels.
+pub const PAGE_FORMAT_8K: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel {
+            shift_bits: 13,
+            va_bits: 9,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        },
+        PageLevel {
+            shift_bits: 22,
+            va_bits: 10,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (PAGE_HUGE, PAGE_HUGE),
+            page_table_mask: PAGE_VALID,
+        },
+        PageLevel {
+            shift_bits: 32,
+            va_bits: 10,
+            present_bit: (PAGE_VALID, PAGE_VALID),
+            huge_page_bit: (0, 0),
+            page_table_mask: PAGE_VALID,
+        },
+    ],
+    physical_mask: 0x00ff_ffff_ffff_e000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// The representative page format for SPARC V9 (sun4v), which is the three-level page table
+/// hierarchy with 8K pages and 4M huge pages described by [`PAGE_FORMAT_8K`].
+pub const PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_8K;
+
+/// The default page format is a three-level page table hierarchy with 8K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_8K;
@@ -1,82 +1,159 @@
 //! This module provides the page table formats available for the x86-64 architecture.
-use lazy_static::lazy_static;
-use crate::{PageFormat, PageLevel};
+use bitflags::bitflags;
+use crate::{Endian, PageFormat, PageLevel};
 
 /// The page is present.
-pub const PAGE_PRESENT: u64 = 1 << 0;
+pub const PAGE_PRESENT:  u64 = 1 << 0;
 /// The page is writeable.
-pub const PAGE_WRITE:   u64 = 1 << 1;
+pub const PAGE_WRITE:    u64 = 1 << 1;
 /// The page is accessible in user mode.
-pub const PAGE_USER:    u64 = 1 << 2;
+pub const PAGE_USER:     u64 = 1 << 2;
+/// The page has been accessed.
+pub const PAGE_ACCESSED: u64 = 1 << 5;
+/// The page has been written to.
+pub const PAGE_DIRTY:    u64 = 1 << 6;
 /// The page is a huge page.
-pub const PAGE_HUGE:    u64 = 1 << 7;
+pub const PAGE_HUGE:     u64 = 1 << 7;
+/// The page is global, i.e. its TLB entry is not flushed on a context switch.
+pub const PAGE_GLOBAL:   u64 = 1 << 8;
+/// The page is not executable.
+pub const PAGE_NX:       u64 = 1 << 63;
+/// The mask of the flags that are decoded into [`Permissions`].
+pub const PAGE_FLAGS_MASK: u64 = PAGE_PRESENT | PAGE_WRITE | PAGE_USER | PAGE_ACCESSED |
+    PAGE_DIRTY | PAGE_GLOBAL | PAGE_NX;
 
+bitflags! {
+    /// The flags of a PTE as typed flags, such that masks can be composed safely, e.g.
+    /// `X86PteFlags::WRITE | X86PteFlags::USER`. Use [`X86PteFlags::bits`] to get the mask for
+    /// functions such as [`crate::AddressSpace::map_range`] and
+    /// [`crate::AddressSpace::protect_range`]. The flags are the same as the bare constants in
+    /// this module.
+    pub struct X86PteFlags: u64 {
+        /// The page is present.
+        const PRESENT  = PAGE_PRESENT;
+        /// The page is writeable.
+        const WRITE    = PAGE_WRITE;
+        /// The page is accessible in user mode.
+        const USER     = PAGE_USER;
+        /// The page has been accessed.
+        const ACCESSED = PAGE_ACCESSED;
+        /// The page has been written to.
+        const DIRTY    = PAGE_DIRTY;
+        /// The page is a huge page.
+        const HUGE     = PAGE_HUGE;
+        /// The page is global, i.e. its TLB entry is not flushed on a context switch.
+        const GLOBAL   = PAGE_GLOBAL;
+        /// The page is not executable.
+        const NX       = PAGE_NX;
+    }
+}
+
+/// The flags of a leaf PTE.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Permissions {
+    /// The page is present.
+    pub present: bool,
+    /// The page is writeable.
+    pub write: bool,
+    /// The page is accessible in user mode.
+    pub user: bool,
+    /// The page is not executable.
+    pub nx: bool,
+    /// The page is global.
+    pub global: bool,
+    /// The page has been accessed.
+    pub accessed: bool,
+    /// The page has been written to.
+    pub dirty: bool,
+}
+
+/// Decodes the flags of the given leaf PTE into [`Permissions`].
+pub fn decode_flags(pte: u64) -> Permissions {
+    Permissions {
+        present: pte & PAGE_PRESENT != 0,
+        write: pte & PAGE_WRITE != 0,
+        user: pte & PAGE_USER != 0,
+        nx: pte & PAGE_NX != 0,
+        global: pte & PAGE_GLOBAL != 0,
+        accessed: pte & PAGE_ACCESSED != 0,
+        dirty: pte & PAGE_DIRTY != 0,
+    }
+}
 
-static PAGE_LEVELS_4K: &'static [PageLevel] = &[
-    PageLevel {
-        shift_bits: 12,
-        va_bits: 9,
-        present_bit: (PAGE_PRESENT, PAGE_PRESENT),
-        huge_page_bit: (0, 0),
-        page_table_mask: 0,
-    },
-    PageLevel {
-        shift_bits: 21,
-        va_bits: 9,
-        present_bit: (PAGE_PRESENT, PAGE_PRESENT),
-        huge_page_bit: (PAGE_HUGE, PAGE_HUGE),
-        page_table_mask: PAGE_PRESENT | PAGE_WRITE | PAGE_USER,
-    },
-    PageLevel {
-        shift_bits: 30,
-        va_bits: 9,
-        present_bit: (PAGE_PRESENT, PAGE_PRESENT),
-        huge_page_bit: (PAGE_HUGE, PAGE_HUGE),
-        page_table_mask: PAGE_PRESENT | PAGE_WRITE | PAGE_USER,
-    },
-    PageLevel {
-        shift_bits: 39,
-        va_bits: 9,
-        present_bit: (PAGE_PRESENT, PAGE_PRESENT),
-        huge_page_bit: (0, 0),
-        page_table_mask: PAGE_PRESENT | PAGE_WRITE | PAGE_USER,
-    },
-    PageLevel {
-        shift_bits: 48,
-        va_bits: 9,
-        present_bit: (PAGE_PRESENT, PAGE_PRESENT),
-        huge_page_bit: (0, 0),
-        page_table_mask: PAGE_PRESENT | PAGE_WRITE | PAGE_USER,
-    },
+/// Encodes the given [`Permissions`] into the flags of a leaf PTE. The resulting flags never touch
+/// the physical address bits, and can be used as the mask for
+/// [`crate::AddressSpace::protect_range`] together with [`PAGE_FLAGS_MASK`] to clear the existing
+/// flags.
+pub fn encode_flags(permissions: Permissions) -> u64 {
+    let flags = [
+        (permissions.present, PAGE_PRESENT),
+        (permissions.write, PAGE_WRITE),
+        (permissions.user, PAGE_USER),
+        (permissions.nx, PAGE_NX),
+        (permissions.global, PAGE_GLOBAL),
+        (permissions.accessed, PAGE_ACCESSED),
+        (permissions.dirty, PAGE_DIRTY),
+    ];
+
+    flags.iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |pte, (_, flag)| pte | flag)
+}
+
+/// The names of the page levels of x86-64, from the leaf page level up to the root page level of
+/// the five-level page table layout.
+const LEVEL_NAMES: &[&str] = &["PTE", "PDE", "PDPTE", "PML4E", "PML5E"];
+
+const PAGE_LEVELS_4K: &[PageLevel] = &[
+    PageLevel::new(12, 9)
+        .with_present(PAGE_PRESENT, PAGE_PRESENT),
+    PageLevel::new(21, 9)
+        .with_present(PAGE_PRESENT, PAGE_PRESENT)
+        .with_huge(PAGE_HUGE, PAGE_HUGE)
+        .with_table_mask(PAGE_PRESENT | PAGE_WRITE | PAGE_USER),
+    PageLevel::new(30, 9)
+        .with_present(PAGE_PRESENT, PAGE_PRESENT)
+        .with_huge(PAGE_HUGE, PAGE_HUGE)
+        .with_table_mask(PAGE_PRESENT | PAGE_WRITE | PAGE_USER),
+    PageLevel::new(39, 9)
+        .with_present(PAGE_PRESENT, PAGE_PRESENT)
+        .with_table_mask(PAGE_PRESENT | PAGE_WRITE | PAGE_USER),
+    PageLevel::new(48, 9)
+        .with_present(PAGE_PRESENT, PAGE_PRESENT)
+        .with_table_mask(PAGE_PRESENT | PAGE_WRITE | PAGE_USER),
 ];
 
-lazy_static! {
-    /// A page table layout for x86-64 consisting of four page levels with 64-bit PTEs and a page
-    /// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
-    /// address to index into the page table. Furthermore, it supports 2M huge page and optionally
-    /// 1G huge pages. Finally, while the number of physical address bits supported is
-    /// CPU-specific, the maximum is 52 bits.
-    pub static ref PAGE_FORMAT_4K_L4: PageFormat<'static> = PageFormat {
-        levels: &PAGE_LEVELS_4K[0..4],
-        physical_mask: 0x000f_ffff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// A page table layout for x86-64 consisting of four page levels with 64-bit PTEs and a page
+/// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
+/// address to index into the page table. Furthermore, it supports 2M huge page and optionally
+/// 1G huge pages. Finally, while the number of physical address bits supported is
+/// CPU-specific, the maximum is 52 bits.
+pub const PAGE_FORMAT_4K_L4: PageFormat<'static> = PageFormat {
+    levels: PAGE_LEVELS_4K.split_at(4).0,
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES.split_at(4).0),
+    endian: Endian::Native,
+};
 
-    /// A page table layout for x86-64 consisting of five page levels with 64-bit PTEs and a page
-    /// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
-    /// address to index into the page table. Furthermore, it supports 2M huge page and optionally
-    /// 1G huge pages. Finally, while the number of physical address bits supported is
-    /// CPU-specific, the maximum is 52 bits.
-    pub static ref PAGE_FORMAT_4K_L5: PageFormat<'static> = PageFormat {
-        levels: PAGE_LEVELS_4K,
-        physical_mask: 0x000f_ffff_ffff_f000,
-        pte_size: core::mem::size_of::<u64>(),
-    };
+/// A page table layout for x86-64 consisting of five page levels with 64-bit PTEs and a page
+/// size of 4K. Therefore, each page table has 512 entries and uses 9 bits of the virtual
+/// address to index into the page table. Furthermore, it supports 2M huge page and optionally
+/// 1G huge pages. Finally, while the number of physical address bits supported is
+/// CPU-specific, the maximum is 52 bits.
+pub const PAGE_FORMAT_4K_L5: PageFormat<'static> = PageFormat {
+    levels: PAGE_LEVELS_4K,
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: core::mem::size_of::<u64>(),
+    physical_high: None,
+    names: Some(LEVEL_NAMES),
+    endian: Endian::Native,
+};
 
-    /// The five-level page table layout is also known as LA57 as it expands linear or virtual
-    /// addresses to 57 bits.
-    pub static ref PAGE_FORMAT_LA57: PageFormat<'static> = PAGE_FORMAT_4K_L5.clone();
+/// The five-level page table layout is also known as LA57 as it expands linear or virtual
+/// addresses to 57 bits.
+pub const PAGE_FORMAT_LA57: PageFormat<'static> = PAGE_FORMAT_4K_L5;
 
-    /// The default page format is a four-level page table hierarchy with 4K pages.
-    pub static ref DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K_L4.clone();
-}
+/// The default page format is a four-level page table hierarchy with 4K pages.
+pub const DEFAULT_PAGE_FORMAT: PageFormat<'static> = PAGE_FORMAT_4K_L4;
@@ -0,0 +1,127 @@
+//! This module provides the [`ContiguousPhysMapper`] trait for page table mappers that have the
+//! physical memory available as a single contiguous slice, and the [`ContiguousMapper`] struct
+//! which turns such a type into a [`PageTableMapper`].
+
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+
+/// A simpler alternative to [`PageTableMapper`] for page table mappers that have the physical
+/// memory available as a single contiguous slice, e.g. a flat physical memory on the heap or a
+/// memory-mapped file. The physical address is simply used as the offset into the slice.
+///
+/// Wrap the type in a [`ContiguousMapper`] to get a [`PageTableMapper`] of which
+/// [`PageTableMapper::read_bytes`] and [`PageTableMapper::write_bytes`], and therefore also the
+/// functions to read and write PTEs, are derived from the slices.
+pub trait ContiguousPhysMapper<Error> {
+    /// An `Error` constant indicating that the PTE was not found.
+    const PTE_NOT_FOUND: Error;
+
+    /// An `Error` constant indicating that a page was not present. This is also returned when
+    /// accessing a physical address outside of the slice.
+    const PAGE_NOT_PRESENT: Error;
+
+    /// An `Error` constant indicating that a function has not been implemented.
+    const NOT_IMPLEMENTED: Error;
+
+    /// Returns the physical memory as a slice.
+    fn phys_slice(&self) -> &[u8];
+
+    /// Returns the physical memory as a mutable slice, or `None` if the physical memory is
+    /// read-only. The default implementation returns `None`, in which case writing to the physical
+    /// memory fails with [`ContiguousPhysMapper::NOT_IMPLEMENTED`].
+    fn phys_slice_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
+    /// Allocates a physical page. See [`PageTableMapper::alloc_page`].
+    fn alloc_page(&mut self) -> Result<u64, Error> {
+        Err(Self::NOT_IMPLEMENTED)
+    }
+
+    /// Frees a physical page. See [`PageTableMapper::free_page`].
+    fn free_page(&mut self, _pte: u64) {
+    }
+}
+
+/// The [`ContiguousMapper`] struct wraps any [`ContiguousPhysMapper`] and implements
+/// [`PageTableMapper`] for it by reading and writing the physical memory slice directly.
+pub struct ContiguousMapper<Mapper, Error>
+where
+    Mapper: ContiguousPhysMapper<Error>,
+{
+    /// The inner mapper.
+    pub mapper: Mapper,
+    /// A marker for Error.
+    error: PhantomData<Error>,
+}
+
+impl<Mapper, Error> ContiguousMapper<Mapper, Error>
+where
+    Mapper: ContiguousPhysMapper<Error>,
+{
+    /// Wraps the given mapper.
+    pub fn new(mapper: Mapper) -> Self {
+        Self {
+            mapper,
+            error: PhantomData,
+        }
+    }
+
+    /// Unwraps the inner mapper.
+    pub fn into_inner(self) -> Mapper {
+        self.mapper
+    }
+
+    /// Returns the range of the physical memory slice for the given physical address and size, or
+    /// [`ContiguousPhysMapper::PAGE_NOT_PRESENT`] if the range does not fit in a `usize`.
+    fn range(phys_addr: u64, size: usize) -> Result<Range<usize>, Error> {
+        let start = usize::try_from(phys_addr)
+            .map_err(|_| Mapper::PAGE_NOT_PRESENT)?;
+        let end = start.checked_add(size)
+            .ok_or(Mapper::PAGE_NOT_PRESENT)?;
+
+        Ok(start..end)
+    }
+}
+
+impl<Mapper, Error> PageTableMapper<Error> for ContiguousMapper<Mapper, Error>
+where
+    Mapper: ContiguousPhysMapper<Error>,
+{
+    const PTE_NOT_FOUND: Error = Mapper::PTE_NOT_FOUND;
+    const PAGE_NOT_PRESENT: Error = Mapper::PAGE_NOT_PRESENT;
+    const NOT_IMPLEMENTED: Error = Mapper::NOT_IMPLEMENTED;
+
+    fn read_bytes(&self, bytes: &mut [u8], phys_addr: u64) -> Result<usize, Error> {
+        let range = Self::range(phys_addr, bytes.len())?;
+
+        match self.mapper.phys_slice().get(range) {
+            Some(slice) => bytes.copy_from_slice(slice),
+            _ => return Err(Mapper::PAGE_NOT_PRESENT),
+        }
+
+        Ok(bytes.len())
+    }
+
+    fn write_bytes(&mut self, phys_addr: u64, bytes: &[u8]) -> Result<usize, Error> {
+        let range = Self::range(phys_addr, bytes.len())?;
+        let memory = self.mapper.phys_slice_mut().ok_or(Mapper::NOT_IMPLEMENTED)?;
+
+        match memory.get_mut(range) {
+            Some(slice) => slice.copy_from_slice(bytes),
+            _ => return Err(Mapper::PAGE_NOT_PRESENT),
+        }
+
+        Ok(bytes.len())
+    }
+
+    fn alloc_page(&mut self) -> Result<u64, Error> {
+        self.mapper.alloc_page()
+    }
+
+    fn free_page(&mut self, pte: u64) {
+        self.mapper.free_page(pte)
+    }
+}
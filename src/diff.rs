@@ -0,0 +1,149 @@
+//! This module provides the [`diff()`] function which compares the mappings of two page table
+//! hierarchies for a given virtual address range.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::PageFormat;
+
+/// Describes how the mappings of two page table hierarchies differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// The page is only present in the first page table hierarchy.
+    OnlyInA,
+    /// The page is only present in the second page table hierarchy.
+    OnlyInB,
+    /// The page is present in both page table hierarchies, but the PTEs differ.
+    Different,
+}
+
+/// Describes a virtual address range for which the mappings of two page table hierarchies differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// The virtual address range.
+    pub virt: Range<usize>,
+    /// How the mappings differ.
+    pub kind: DifferenceKind,
+}
+
+/// Describes the PTE found in one of the page table hierarchies.
+enum Entry {
+    /// The PTE is not present or the page table hierarchy has no page table at this level.
+    Absent,
+    /// The PTE refers to a page.
+    Page(u64),
+    /// The PTE refers to another page table at the given physical address.
+    PageTable(u64),
+}
+
+/// Reads the PTE at the given PTE index from the given page table, if any, and classifies it.
+fn read_entry<Mapper, Error>(
+    format: &PageFormat,
+    index: usize,
+    page_table: Option<u64>,
+    pte_index: usize,
+    mapper: &Mapper,
+) -> Result<Entry, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    let level = &format.levels[index];
+
+    let page_table = match page_table {
+        Some(page_table) => page_table,
+        _ => return Ok(Entry::Absent),
+    };
+
+    let offset = (pte_index * format.pte_size) as u64;
+    let pte = format.read_pte(mapper, page_table + offset)?;
+
+    Ok(if !level.is_present(pte) {
+        Entry::Absent
+    } else if index == 0 || level.is_huge_page(pte) {
+        Entry::Page(pte)
+    } else {
+        Entry::PageTable(format.pte_to_phys(pte))
+    })
+}
+
+/// This is a recursive helper function used to traverse both page table hierarchies in lockstep
+/// for a given virtual address range and the physical addresses of the page tables for the current
+/// page table level, if any.
+#[allow(clippy::too_many_arguments)]
+fn do_diff<MapperA, MapperB, Error>(
+    format: &PageFormat,
+    index: usize,
+    table_a: Option<u64>,
+    mapper_a: &MapperA,
+    table_b: Option<u64>,
+    mapper_b: &MapperB,
+    range: Range<usize>,
+    differences: &mut Vec<Difference>,
+) -> Result<(), Error>
+where
+    MapperA: PageTableMapper<Error>,
+    MapperB: PageTableMapper<Error>,
+{
+    if range.is_empty() {
+        return Ok(());
+    }
+
+    for (pte_index, page_range) in format.page_ranges(index, range) {
+        let a = read_entry(format, index, table_a, pte_index, mapper_a)?;
+        let b = read_entry(format, index, table_b, pte_index, mapper_b)?;
+
+        let kind = match (a, b) {
+            (Entry::Absent, Entry::Absent) => continue,
+            (Entry::Page(a), Entry::Page(b)) if a == b => continue,
+            (Entry::Page(_), Entry::Absent) => DifferenceKind::OnlyInA,
+            (Entry::Absent, Entry::Page(_)) => DifferenceKind::OnlyInB,
+            (Entry::PageTable(a), Entry::PageTable(b)) => {
+                do_diff(format, index - 1, Some(a), mapper_a, Some(b), mapper_b, page_range, differences)?;
+                continue;
+            }
+            (Entry::PageTable(a), Entry::Absent) => {
+                do_diff(format, index - 1, Some(a), mapper_a, None, mapper_b, page_range, differences)?;
+                continue;
+            }
+            (Entry::Absent, Entry::PageTable(b)) => {
+                do_diff(format, index - 1, None, mapper_a, Some(b), mapper_b, page_range, differences)?;
+                continue;
+            }
+            // Either both PTEs refer to different pages, or one PTE refers to a huge page while
+            // the other refers to a page table.
+            _ => DifferenceKind::Different,
+        };
+
+        differences.push(Difference {
+            virt: page_range,
+            kind,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares the mappings of two page table hierarchies using the same page format for the given
+/// virtual address range, and returns the virtual address ranges for which the mappings differ in
+/// ascending order. Both page table hierarchies are walked in lockstep. If one page table
+/// hierarchy maps a huge page where the other has a page table, then the whole range of the huge
+/// page is reported as [`DifferenceKind::Different`].
+pub fn diff<MapperA, MapperB, Error>(
+    format: &PageFormat,
+    root_a: u64,
+    mapper_a: &MapperA,
+    root_b: u64,
+    mapper_b: &MapperB,
+    range: Range<usize>,
+) -> Result<Vec<Difference>, Error>
+where
+    MapperA: PageTableMapper<Error>,
+    MapperB: PageTableMapper<Error>,
+{
+    let mut differences = Vec::new();
+    let index = format.levels.len() - 1;
+
+    do_diff(format, index, Some(root_a), mapper_a, Some(root_b), mapper_b, range, &mut differences)?;
+
+    Ok(differences)
+}
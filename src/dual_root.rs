@@ -0,0 +1,271 @@
+//! This module provides the [`DualRootAddressSpace`] struct which abstracts a virtual address
+//! space that is split into a lower and an upper half with a page table hierarchy each, e.g. the
+//! TTBR0 and TTBR1 page table hierarchies on AArch64.
+
+use core::borrow::BorrowMut;
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::{AddressSpace, PageTableMapper, ReadOnlyAddressSpace};
+use crate::PageFormat;
+
+/// The first virtual address of the upper half of the virtual address space, i.e. the virtual
+/// address with only the top bit set.
+const UPPER_HALF: usize = !(usize::MAX >> 1);
+
+/// Abstracts a virtual address space that uses two page table hierarchies, where the top bit of
+/// the virtual address selects the page table hierarchy to walk. The lower half of the virtual
+/// address space is translated by the page table hierarchy at the lower root and the upper half
+/// by the page table hierarchy at the upper root. On AArch64, these are the page table
+/// hierarchies pointed to by TTBR0 and TTBR1 respectively.
+///
+/// The operations pick the appropriate root based on the virtual address. Virtual address ranges
+/// that cross into the upper half are split, such that each part is walked using its own page
+/// table hierarchy. Any other operation of [`AddressSpace`] can be used through
+/// [`DualRootAddressSpace::address_space`].
+pub struct DualRootAddressSpace<'a, Mapper, Error, M = &'a mut Mapper>
+where
+    Mapper: PageTableMapper<Error>,
+    M: BorrowMut<Mapper>,
+{
+    /// The page table format describing the page table hierarchies for this virtual address space.
+    format: PageFormat<'a>,
+
+    /// The root address of the page table hierarchy of the lower half.
+    lower_root: u64,
+
+    /// The root address of the page table hierarchy of the upper half.
+    upper_root: u64,
+
+    /// The type implementing PageTableMapper, or a reference to it.
+    mapper: M,
+
+    /// A marker for Mapper.
+    marker: PhantomData<Mapper>,
+
+    /// A marker for Error.
+    error: PhantomData<Error>,
+}
+
+impl<'a, Mapper, Error> DualRootAddressSpace<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Creates a new address space for the given page table format describing the page table
+    /// hierarchies, the page table mapper and the pointers to the roots of the page table
+    /// hierarchies of the lower and the upper half.
+    pub fn new(format: PageFormat<'a>, mapper: &'a mut Mapper, lower_root: u64, upper_root: u64) -> Self {
+        Self {
+            format,
+            lower_root,
+            upper_root,
+            mapper,
+            marker: PhantomData,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<'a, Mapper, Error> DualRootAddressSpace<'a, Mapper, Error, Mapper>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Creates a new address space like [`DualRootAddressSpace::new`], but takes ownership of the
+    /// page table mapper.
+    pub fn new_owned(format: PageFormat<'a>, mapper: Mapper, lower_root: u64, upper_root: u64) -> Self {
+        Self {
+            format,
+            lower_root,
+            upper_root,
+            mapper,
+            marker: PhantomData,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<'a, Mapper, Error, M> DualRootAddressSpace<'a, Mapper, Error, M>
+where
+    Mapper: PageTableMapper<Error>,
+    M: BorrowMut<Mapper>,
+{
+    /// Consumes the address space and returns the page table mapper, or the reference to it.
+    pub fn into_mapper(self) -> M {
+        self.mapper
+    }
+
+    /// Returns the page table format describing the page table hierarchies.
+    pub fn format(&self) -> &PageFormat<'a> {
+        &self.format
+    }
+
+    /// Returns the physical address of the root page table of the lower half.
+    pub fn lower_root(&self) -> u64 {
+        self.lower_root
+    }
+
+    /// Returns the physical address of the root page table of the upper half.
+    pub fn upper_root(&self) -> u64 {
+        self.upper_root
+    }
+
+    /// Sets the physical address of the root page table of the lower half.
+    pub fn set_lower_root(&mut self, root: u64) {
+        self.lower_root = root;
+    }
+
+    /// Sets the physical address of the root page table of the upper half.
+    pub fn set_upper_root(&mut self, root: u64) {
+        self.upper_root = root;
+    }
+
+    /// Returns the physical address of the root page table that translates the given virtual
+    /// address.
+    pub fn root_for(&self, virt_addr: usize) -> u64 {
+        match virt_addr & UPPER_HALF {
+            0 => self.lower_root,
+            _ => self.upper_root,
+        }
+    }
+
+    /// Returns the [`AddressSpace`] that translates the given virtual address, i.e. the address
+    /// space for the lower or the upper half.
+    pub fn address_space(&mut self, virt_addr: usize) -> AddressSpace<'_, Mapper, Error> {
+        let root = self.root_for(virt_addr);
+
+        AddressSpace::new(self.format.clone(), self.mapper.borrow_mut(), root)
+    }
+
+    /// Returns a read-only view of the address space that translates the given virtual address.
+    /// See [`ReadOnlyAddressSpace`] for more details.
+    pub fn read_only(&self, virt_addr: usize) -> ReadOnlyAddressSpace<'_, Mapper, Error> {
+        ReadOnlyAddressSpace::new(self.format.clone(), self.mapper.borrow(), self.root_for(virt_addr))
+    }
+
+    /// Splits the given virtual address range into the part in the lower half and the part in the
+    /// upper half. Either part may be empty.
+    fn halves(range: Range<usize>) -> [Range<usize>; 2] {
+        let lower = range.start..range.end.min(UPPER_HALF);
+        let upper = range.start.max(UPPER_HALF)..range.end;
+
+        [lower, upper]
+    }
+
+    /// Splits the given virtual address range into the part in the lower half and the part in the
+    /// upper half, and invokes the given closure for each part that is not empty with the
+    /// corresponding address space and the offset of the part within the range.
+    fn for_each_half<F>(&mut self, range: Range<usize>, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut AddressSpace<'_, Mapper, Error>, Range<usize>, usize) -> Result<(), Error>,
+    {
+        for part in Self::halves(range.clone()) {
+            if part.is_empty() {
+                continue;
+            }
+
+            let offset = part.start - range.start;
+
+            f(&mut self.address_space(part.start), part, offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the PTE for the given the virtual address if the virtual address is valid. See
+    /// [`AddressSpace::read_pte`].
+    pub fn read_pte(&self, virt_addr: usize) -> Result<u64, Error> {
+        self.read_only(virt_addr).read_pte(virt_addr)
+    }
+
+    /// Translates the given virtual address into a physical address. See
+    /// [`AddressSpace::translate`].
+    pub fn translate(&self, virt_addr: usize) -> Result<Option<u64>, Error> {
+        self.read_only(virt_addr).translate(virt_addr)
+    }
+
+    /// Checks whether the given virtual address is mapped. See [`AddressSpace::is_mapped`].
+    pub fn is_mapped(&self, virt_addr: usize) -> Result<bool, Error> {
+        self.read_only(virt_addr).is_mapped(virt_addr)
+    }
+
+    /// Writes the PTE for the given virtual address if the virtual address is valid. See
+    /// [`AddressSpace::write_pte`].
+    pub fn write_pte(&mut self, virt_addr: usize, pte: u64) -> Result<(), Error> {
+        self.address_space(virt_addr).write_pte(virt_addr, pte)
+    }
+
+    /// Allocates pages and the underlying page tables for the given range. See
+    /// [`AddressSpace::allocate_range`].
+    pub fn allocate_range(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
+        self.for_each_half(range, |space, range, _| space.allocate_range(range, mask))
+    }
+
+    /// Maps the given range using the given mask. See [`AddressSpace::map_range`]. The physical
+    /// address encoded in the mask is the physical address of the start of the range, such that
+    /// the part in the upper half is mapped to the physical addresses following the part in the
+    /// lower half.
+    pub fn map_range(&mut self, range: Range<usize>, mask: u64) -> Result<(), Error> {
+        let phys_base = self.format.pte_to_phys(mask);
+        let mask = mask & !self.format.address_mask();
+
+        self.map_range_to(range, phys_base, mask)
+    }
+
+    /// Maps the given virtual address range to the physical address range starting at the given
+    /// physical address. See [`AddressSpace::map_range_to`].
+    pub fn map_range_to(&mut self, virt: Range<usize>, phys_base: u64, mask: u64) -> Result<(), Error> {
+        self.for_each_half(virt, |space, range, offset| {
+            space.map_range_to(range, phys_base + offset as u64, mask)
+        })
+    }
+
+    /// Changes the protection flags of the given range. See [`AddressSpace::protect_range`].
+    pub fn protect_range(&mut self, range: Range<usize>, mask: (u64, u64)) -> Result<(), Error> {
+        self.for_each_half(range, |space, range, _| space.protect_range(range, mask))
+    }
+
+    /// Frees the pages and page tables of the given range. See [`AddressSpace::free_range`].
+    pub fn free_range(&mut self, range: Range<usize>) -> Result<(), Error> {
+        self.for_each_half(range, |space, range, _| space.free_range(range))
+    }
+
+    /// Unmaps the given range without freeing the pages. See [`AddressSpace::unmap_range`].
+    pub fn unmap_range(&mut self, range: Range<usize>) -> Result<(), Error> {
+        self.for_each_half(range, |space, range, _| space.unmap_range(range))
+    }
+
+    /// Copies bytes starting at the given address into the given buffer. See
+    /// [`AddressSpace::copy_from`]. A copy that crosses into the upper half is split, such that
+    /// each part is copied using its own page table hierarchy.
+    pub fn copy_from(&self, data: &mut [u8], address: usize) -> Result<(), (Error, usize)> {
+        for part in Self::halves(address..address + data.len()) {
+            if part.is_empty() {
+                continue;
+            }
+
+            let offset = part.start - address;
+            let data = &mut data[offset..offset + part.len()];
+
+            self.read_only(part.start).copy_from(data, part.start)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies bytes from the given buffer to the given address. See [`AddressSpace::copy_to`]. A
+    /// copy that crosses into the upper half is split, such that each part is copied using its own
+    /// page table hierarchy.
+    pub fn copy_to(&mut self, address: usize, data: &[u8]) -> Result<(), (Error, usize)> {
+        for part in Self::halves(address..address + data.len()) {
+            if part.is_empty() {
+                continue;
+            }
+
+            let offset = part.start - address;
+            let data = &data[offset..offset + part.len()];
+
+            self.address_space(part.start).copy_to(part.start, data)?;
+        }
+
+        Ok(())
+    }
+}
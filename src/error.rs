@@ -0,0 +1,74 @@
+//! This module provides the [`WalkError`] enum which can be used as the `Error` type of a
+//! [`PageTableMapper`] to capture the faulting virtual address when a page is not present, and the
+//! [`FormatError`] enum for the errors that can occur when validating the input against a
+//! [`PageFormat`].
+//!
+//! [`PageFormat`]: crate::PageFormat
+
+use crate::address_space::PageTableMapper;
+
+/// The [`WalkError`] enum provides the errors that can occur during a page table walk, where the
+/// errors of the [`PageTableMapper`] itself are wrapped in [`WalkError::Mapper`]. A
+/// [`PageTableMapper`] using this as its `Error` type should use the variants as its error
+/// constants, and should override [`PageTableMapper::page_not_present`] to return
+/// [`WalkError::page_not_present`], such that the faulting virtual address is captured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalkError<E> {
+    /// The PTE was not found.
+    PteNotFound,
+    /// The page at the given virtual address was not present.
+    PageNotPresent {
+        /// The faulting virtual address.
+        virt: usize,
+    },
+    /// The function has not been implemented.
+    NotImplemented,
+    /// An error returned by the mapper.
+    Mapper(E),
+}
+
+impl<E> WalkError<E> {
+    /// Returns [`WalkError::PageNotPresent`] for the given virtual address. This can be used to
+    /// implement [`PageTableMapper::page_not_present`].
+    pub fn page_not_present(virt: usize) -> Self {
+        Self::PageNotPresent { virt }
+    }
+
+    /// Converts an error of a [`PageTableMapper`] that uses error constants into a [`WalkError`],
+    /// where the given virtual address is used as the faulting virtual address if the error is
+    /// [`PageTableMapper::PAGE_NOT_PRESENT`]. Errors that do not match any of the error constants
+    /// are wrapped in [`WalkError::Mapper`]. This allows the existing mappers that use error
+    /// constants to interoperate with [`WalkError`].
+    pub fn from_mapper<Mapper>(error: E, virt: usize) -> Self
+    where
+        Mapper: PageTableMapper<E>,
+        E: PartialEq,
+    {
+        if error == Mapper::PTE_NOT_FOUND {
+            Self::PteNotFound
+        } else if error == Mapper::PAGE_NOT_PRESENT {
+            Self::PageNotPresent { virt }
+        } else if error == Mapper::NOT_IMPLEMENTED {
+            Self::NotImplemented
+        } else {
+            Self::Mapper(error)
+        }
+    }
+}
+
+/// The [`FormatError`] enum provides the errors that can occur when validating a
+/// [`crate::PageFormat`] using [`crate::PageFormat::validate`], or when validating a physical
+/// address against a [`crate::PageFormat`], e.g. in [`crate::AddressSpace::try_new`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The root page table is not aligned to the smallest page size.
+    UnalignedRoot,
+    /// The root page table has bits set outside of the physical mask.
+    RootOutsidePhysicalMask,
+    /// The PTE size is not supported, i.e. it is not a power of two or the PTE does not fit in a
+    /// `u64`.
+    UnsupportedPteSize,
+    /// The PTE bits that hold the physical address overlap with the present bits or the huge page
+    /// bits of one of the page levels.
+    PhysicalMaskOverlapsFlags,
+}
@@ -2,8 +2,45 @@
 //! hierarchy.
 
 use core::ops::Range;
+use crate::error::FormatError;
 use crate::level::PageLevel;
-use crate::walker::PteType;
+use crate::walker::{Descend, PteLocation, PteType};
+
+/// The maximum number of PTEs that [`PageFormat::walk`] reads in a single batch using
+/// [`crate::address_space::PageTableMapper::read_ptes`].
+pub const PTE_BATCH_SIZE: usize = 64;
+
+/// The maximum number of page levels supported by the walkers that keep track of state for every
+/// page level.
+pub const MAX_LEVELS: usize = 8;
+
+/// The byte order of the PTEs in the page tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endian {
+    /// The PTEs are stored in the byte order of the host.
+    #[default]
+    Native,
+    /// The PTEs are stored in little-endian byte order.
+    Little,
+    /// The PTEs are stored in big-endian byte order.
+    Big,
+}
+
+/// The statistics of one or more page table walks as collected by [`PageFormat::walk_with_stats`]
+/// and [`PageFormat::walk_mut_with_stats`], e.g. to tune the ranges that are walked or to detect
+/// pathological walks over sparse page tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WalkStats {
+    /// The number of PTEs read, i.e. the PTEs of both pages and page tables, including PTE holes.
+    pub ptes_read: usize,
+    /// The number of page tables visited, including the root page table.
+    pub tables_visited: usize,
+    /// The number of PTE holes, i.e. PTEs that are not marked as present.
+    pub holes: usize,
+    /// The maximum depth reached, i.e. the maximum number of page tables visited on the way down
+    /// from the root page table, where the root page table itself is at a depth of one.
+    pub max_depth: usize,
+}
 
 /// Describes the page format of the page hierarchy and the mask of bits in the PTE that refer to
 /// the actual physical address and are not used for metadata.
@@ -18,8 +55,27 @@ pub struct PageFormat<'a> {
     /// metadata.
     pub physical_mask: u64,
 
-    /// The size of a page table entry (PTE) in bytes.
+    /// The size of a page table entry (PTE) in bytes. As PTEs are handled as a `u64`, this is
+    /// limited to a power of two of at most 8 bytes. Formats with wider descriptors are not
+    /// supported, which is checked by [`PageFormat::validate`].
     pub pte_size: usize,
+
+    /// The PTE bits that hold the upper physical address bits which are not contiguous with the
+    /// bits in the physical mask, together with the number of bits to shift these PTE bits to the
+    /// left to get the corresponding physical address bits, e.g. for 52-bit physical addresses on
+    /// AArch64. This is `None` if the physical mask covers all the physical address bits. See
+    /// [`PageFormat::pte_to_phys`] and [`PageFormat::phys_to_pte`].
+    pub physical_high: Option<(u64, u32)>,
+
+    /// The names of the page levels used for diagnostics, where the name at index zero is the
+    /// name of the leaf page level. See [`PageFormat::level_name`].
+    pub names: Option<&'a [&'a str]>,
+
+    /// The byte order of the PTEs in the page tables, e.g. to introspect the page tables of a
+    /// big-endian target on a little-endian host. The PTEs are converted to and from the byte
+    /// order of the host when they are read and written during a page table walk, such that the
+    /// walkers always operate on PTEs in the byte order of the host.
+    pub endian: Endian,
 }
 
 impl<'a> PageFormat<'a> {
@@ -29,15 +85,233 @@ impl<'a> PageFormat<'a> {
     pub fn virtual_mask(&self) -> usize {
         self.levels
             .iter()
-            .map(|level| level.mask() | level.page_size() - 1)
+            .map(|level| level.mask() | (level.page_size() - 1))
             .max()
             .unwrap()
     }
 
+    /// Returns the mask of all the PTE bits that hold the physical address, i.e. the physical mask
+    /// together with the PTE bits that hold the upper physical address bits, if any. The remaining
+    /// PTE bits are the flags.
+    pub fn address_mask(&self) -> u64 {
+        match self.physical_high {
+            Some((mask, _)) => self.physical_mask | mask,
+            _ => self.physical_mask,
+        }
+    }
+
+    /// Validates the page format. That is, it checks that the PTE size is a power of two of at
+    /// most 8 bytes, such that a PTE fits in a `u64`. Furthermore, it checks that the PTE bits that
+    /// hold the physical address, as given by [`PageFormat::address_mask`], do not overlap with
+    /// the present bits or the huge page bits of any of the page levels. Otherwise, these flags
+    /// would be interpreted as part of the physical address and vice versa.
+    pub fn validate(&self) -> Result<(), FormatError> {
+        if !self.pte_size.is_power_of_two() || self.pte_size > core::mem::size_of::<u64>() {
+            return Err(FormatError::UnsupportedPteSize);
+        }
+
+        let address_mask = self.address_mask();
+
+        for level in self.levels {
+            if (level.present_bit.0 | level.huge_page_bit.0) & address_mask != 0 {
+                return Err(FormatError::PhysicalMaskOverlapsFlags);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Extracts the physical address from the given PTE, where the upper physical address bits are
+    /// relocated from their PTE bits if the page format uses [`PageFormat::physical_high`].
+    pub fn pte_to_phys(&self, pte: u64) -> u64 {
+        match self.physical_high {
+            Some((mask, shift)) => (pte & self.physical_mask) | ((pte & mask) << shift),
+            _ => pte & self.physical_mask,
+        }
+    }
+
+    /// Encodes the given physical address into the PTE bits that hold the physical address. This
+    /// is the inverse of [`PageFormat::pte_to_phys`], and the physical address bits that cannot be
+    /// encoded are discarded.
+    pub fn phys_to_pte(&self, phys_addr: u64) -> u64 {
+        match self.physical_high {
+            Some((mask, shift)) => (phys_addr & self.physical_mask) | ((phys_addr >> shift) & mask),
+            _ => phys_addr & self.physical_mask,
+        }
+    }
+
+    /// Converts the given PTE between the byte order of the page tables and the byte order of the
+    /// host. As this merely swaps the bytes if the byte orders differ, the same function is used
+    /// for both directions.
+    pub fn swap_pte(&self, pte: u64) -> u64 {
+        let swap = match self.endian {
+            Endian::Native => false,
+            Endian::Little => cfg!(target_endian = "big"),
+            Endian::Big => cfg!(target_endian = "little"),
+        };
+
+        if !swap {
+            return pte;
+        }
+
+        match self.pte_size {
+            2 => (pte as u16).swap_bytes() as u64,
+            4 => (pte as u32).swap_bytes() as u64,
+            _ => pte.swap_bytes(),
+        }
+    }
+
+    /// Reads the PTE at the given physical address using the given mapper, and converts it to the
+    /// byte order of the host.
+    pub fn read_pte<Mapper, Error>(&self, mapper: &Mapper, phys_addr: u64) -> Result<u64, Error>
+    where
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        mapper.read_pte(self.pte_size, phys_addr).map(|pte| self.swap_pte(pte))
+    }
+
+    /// Reads consecutive PTEs starting at the given physical address using the given mapper like
+    /// [`crate::address_space::PageTableMapper::read_ptes`], and converts them to the byte order
+    /// of the host.
+    pub fn read_ptes<Mapper, Error>(&self, mapper: &Mapper, phys_addr: u64, ptes: &mut [u64]) -> Result<usize, Error>
+    where
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        let count = mapper.read_ptes(self.pte_size, phys_addr, ptes)?;
+
+        for pte in &mut ptes[..count] {
+            *pte = self.swap_pte(*pte);
+        }
+
+        Ok(count)
+    }
+
+    /// Converts the given PTE to the byte order of the page tables, and writes it to the given
+    /// physical address using the given mapper.
+    pub fn write_pte<Mapper, Error>(&self, mapper: &mut Mapper, phys_addr: u64, pte: u64) -> Result<(), Error>
+    where
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        mapper.write_pte(self.pte_size, phys_addr, self.swap_pte(pte))
+    }
+
+    /// Returns the page size of the page or page table referred to by a PTE of the given
+    /// [`PteType`], i.e. the page size of the page level at which the PTE is found.
+    ///
+    /// ```
+    /// use page_walker::arch::x86_64::PAGE_FORMAT_4K_L4;
+    /// use page_walker::PteType;
+    ///
+    /// assert_eq!(PAGE_FORMAT_4K_L4.page_size_at(PteType::Page(0, false)), 0x1000);
+    /// assert_eq!(PAGE_FORMAT_4K_L4.page_size_at(PteType::Page(1, true)), 0x200000);
+    /// ```
+    pub fn page_size_at(&self, pte_type: PteType) -> usize {
+        self.levels[pte_type.level()].page_size()
+    }
+
+    /// Returns the name of the page level at the given index, e.g. "PML4E" for the root page level
+    /// of x86-64. If the page format does not name its page levels, this returns "L0", "L1", etc.
+    pub fn level_name(&self, index: usize) -> &str {
+        const DEFAULT_NAMES: [&str; MAX_LEVELS] = ["L0", "L1", "L2", "L3", "L4", "L5", "L6", "L7"];
+
+        self.names
+            .and_then(|names| names.get(index).copied())
+            .or_else(|| DEFAULT_NAMES.get(index).copied())
+            .unwrap_or("L?")
+    }
+
+    /// Returns the smallest page size, i.e. the page size of the leaf page level.
+    pub fn min_page_size(&self) -> usize {
+        self.levels[0].page_size()
+    }
+
+    /// Returns the largest page size, i.e. the page size of the highest page level that supports
+    /// huge pages, or the page size of the leaf page level if there are no such page levels.
+    pub fn max_page_size(&self) -> usize {
+        self.page_sizes()
+            .last()
+            .unwrap_or_else(|| self.min_page_size())
+    }
+
+    /// Returns an iterator over the supported page sizes in ascending order, i.e. the page size of
+    /// the leaf page level followed by the page sizes of the page levels that support huge pages.
+    pub fn page_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.levels
+            .iter()
+            .enumerate()
+            .filter(|(index, level)| *index == 0 || level.supports_huge_pages())
+            .map(|(_, level)| level.page_size())
+    }
+
+    /// Checks whether the given virtual address is aligned to the page size of the given page
+    /// level.
+    pub fn is_page_aligned(&self, addr: usize, index: usize) -> bool {
+        addr & (self.levels[index].page_size() - 1) == 0
+    }
+
+    /// Returns the offset of the given virtual address within the page of the given page level,
+    /// e.g. the offset within a 2M page for the second page level on x86-64. This is the single
+    /// place where the page offset is computed by the walkers and the translation functions.
+    pub fn page_offset(&self, addr: usize, index: usize) -> usize {
+        addr & (self.levels[index].page_size() - 1)
+    }
+
+    /// Clears the newly allocated page table at the given physical address that the PTE at the
+    /// given page level is going to refer to, before the page table is linked into the page table
+    /// hierarchy. The page table is zeroed using
+    /// [`crate::address_space::PageTableMapper::zero_page`], and if the empty PTE of the page
+    /// level below is not zero, as given by [`PageLevel::empty_pte`], every entry is set to the
+    /// empty PTE, such that none of the entries is marked as present.
+    pub fn clear_page_table<Mapper, Error>(&self, mapper: &mut Mapper, index: usize, phys_addr: u64) -> Result<(), Error>
+    where
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        let level = &self.levels[index - 1];
+        let size = level.entries() * self.pte_size;
+
+        mapper.zero_page(self.pte_size, phys_addr, size)?;
+
+        let empty = level.empty_pte();
+
+        if empty != 0 {
+            for offset in (0..size).step_by(self.pte_size) {
+                self.write_pte(mapper, phys_addr + offset as u64, empty)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Constructs a PTE for the given page level that is marked as present, and that refers to the
+    /// given physical address with the given flags. The PTE is marked as present using
+    /// [`PageLevel::set_present`]. At the leaf page level, the PTE refers to a page. At any other
+    /// page level, the PTE refers to a page table, and is therefore ensured to **not** be marked
+    /// as a huge page.
+    pub fn make_pte(&self, index: usize, phys_addr: u64, flags: u64) -> u64 {
+        let level = &self.levels[index];
+        let pte = level.set_present(self.phys_to_pte(phys_addr) | flags);
+
+        match index {
+            0 => pte,
+            _ => (pte & !level.huge_page_bit.0) |
+                ((level.huge_page_bit.0 ^ level.huge_page_bit.1) & level.huge_page_bit.0),
+        }
+    }
+
+    /// Constructs a PTE for the given page level that is marked as present, and that refers to a
+    /// huge page at the given physical address with the given flags. The page level should
+    /// support huge pages.
+    pub fn make_huge_pte(&self, index: usize, phys_addr: u64, flags: u64) -> u64 {
+        let level = &self.levels[index];
+        let pte = level.set_present(self.phys_to_pte(phys_addr) | flags);
+
+        (pte & !level.huge_page_bit.0) | level.huge_page_bit.1
+    }
+
     /// Sign extends a given virtual address by extending the sign bit into the unused upper bits
     /// of the virtual address.
     pub fn sign_extend(&self, address: usize) -> usize {
-        let sign_bit = 1 << self.virtual_mask().trailing_ones() - 1;
+        let sign_bit = 1 << (self.virtual_mask().trailing_ones() - 1);
 
         if address & sign_bit == sign_bit {
             // Invert the virtual mask and mask it with the address to sign extend the address.
@@ -47,10 +321,116 @@ impl<'a> PageFormat<'a> {
         }
     }
 
+    /// Returns the range of non-canonical virtual addresses, i.e. the gap between the top of the
+    /// lower half and the bottom of the upper half of the virtual address space, where the virtual
+    /// addresses are sign extended as in [`PageFormat::sign_extend`]. For instance, this is
+    /// `0x0000_8000_0000_0000..0xffff_8000_0000_0000` for the four-level page table layout on
+    /// x86-64. Returns `None` if the page format covers all the bits of the virtual address, as
+    /// every virtual address is then canonical. This can be used to reject non-canonical virtual
+    /// addresses up front.
+    pub fn canonical_hole(&self) -> Option<Range<usize>> {
+        let virtual_mask = self.virtual_mask();
+
+        if virtual_mask == usize::MAX {
+            return None;
+        }
+
+        Some((virtual_mask >> 1) + 1..!(virtual_mask >> 1))
+    }
+
+    /// Checks whether the given virtual address range lies entirely within canonical address
+    /// space, i.e. whether the range lies entirely within either the lower half or the upper half
+    /// of the virtual address space without touching the non-canonical addresses returned by
+    /// [`PageFormat::canonical_hole`]. An empty range is always considered to be canonical.
+    pub fn is_canonical_range(&self, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+
+        match self.canonical_hole() {
+            Some(hole) => range.end <= hole.start || range.start >= hole.end,
+            _ => true,
+        }
+    }
+
+    /// Returns the index of the last PTE of the page table at the given page level that the range
+    /// covers. If the range extends beyond the virtual addresses that the page table can reach,
+    /// e.g. beyond the four entries of the root page table of x86 PAE, then this is clamped to the
+    /// last entry of the page table, such that the PTE index never exceeds
+    /// [`PageLevel::entries`]. The range must not be empty.
+    fn last_pte_index(&self, index: usize, range: Range<usize>) -> usize {
+        let level = &self.levels[index];
+        let bits = (level.shift_bits + level.va_bits) as u32;
+
+        match (range.end - 1).checked_shr(bits).unwrap_or(0) == range.start.checked_shr(bits).unwrap_or(0) {
+            true => level.pte_index(range.end - 1),
+            _    => level.entries() - 1,
+        }
+    }
+
+    /// Splits up the range by page boundaries for the given page level, such that we have a range
+    /// for each page that is inclusive of the original range. For instance, the range
+    /// 0x0000..0x2000 spans two 4K pages, so this iterator would return 0x0000..0x1000 and
+    /// 0x1000..0x2000. The page ranges are sign extended where appropriate. In addition, this
+    /// iterator returns the PTE index for each page range. The part of the range beyond the reach
+    /// of the page table is out of range and therefore skipped. The range must not be empty.
+    pub(crate) fn page_ranges(&self, index: usize, range: Range<usize>) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+        let level = &self.levels[index];
+
+        (level.pte_index(range.start)..=self.last_pte_index(index, range.clone()))
+            .scan(self.sign_extend(range.start), move |state, pte_index| {
+                let page_range = *state..level.end(*state).min(range.end - 1) + 1;
+                *state = self.sign_extend(level.end(*state).wrapping_add(1));
+
+                Some((pte_index, page_range))
+            })
+    }
+
+    /// Resolves the given virtual address to the PTE of the page that it maps to, by descending
+    /// the page table hierarchy starting at the root page table at the given physical address
+    /// level by level. Unlike [`PageFormat::walk`], this does not split a range into pages, which
+    /// makes it cheaper for single lookups. Returns the PTE together with the index of the page
+    /// level at which the PTE was found, or `None` if the virtual address is not backed by a page
+    /// table at one of the page levels. Note that the returned PTE itself may not be present.
+    pub fn resolve<Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        virt_addr: usize,
+        mapper: &Mapper,
+    ) -> Result<Option<(u64, usize)>, Error>
+    where
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        let mut phys_addr = phys_addr;
+
+        for (index, level) in self.levels.iter().enumerate().rev() {
+            let offset = (level.pte_index(virt_addr) * self.pte_size) as u64;
+            let pte = self.read_pte(mapper, phys_addr + offset)?;
+
+            // The PTE refers to a page if we are at the leaf page table or if the PTE refers to a
+            // huge page.
+            if index == 0 || level.is_huge_page(pte) {
+                return Ok(Some((pte, index)));
+            }
+
+            // There is no page table to descend into.
+            if !level.is_present(pte) {
+                return Ok(None);
+            }
+
+            phys_addr = self.pte_to_phys(pte);
+        }
+
+        Ok(None)
+    }
+
     /// This is a recursive helper function used to traverse the page table hierarchy for a given
     /// virtual address range and the given physical address of the page table for the current page
     /// table level. It invokes the appropriate user callbacks in [`crate::walker::PageWalker`],
-    /// while traversing the page tables.
+    /// while traversing the page tables. The physical addresses of the page tables visited on the
+    /// way down are recorded in the given path, where the depth is the number of page tables
+    /// visited before this one.
+    #[allow(clippy::too_many_arguments)]
     fn do_walk<PageWalker, Mapper, Error>(
         &self,
         phys_addr: u64,
@@ -58,6 +438,8 @@ impl<'a> PageFormat<'a> {
         range: Range<usize>,
         walker: &mut PageWalker,
         mapper: &Mapper,
+        path: &mut [u64; MAX_LEVELS],
+        depth: usize,
     ) -> Result<(), Error>
     where
         PageWalker: crate::walker::PageWalker<Mapper, Error>,
@@ -70,56 +452,104 @@ impl<'a> PageFormat<'a> {
 
         let level = &self.levels[index];
 
-        // Split up the range by page boundaries, such that we have a range for each page that is
-        // inclusive of the original range. For instance, the range 0x0000..0x1fff spans two 4K
-        // pages, so this iterator would return 0x0000..0x0fff and 0x1000..0x1fff. We also make
-        // sure that the page ranges are sign extended where appropriate. In addition, calculate
-        // the PTE index.
-        let page_ranges = (level.pte_index(range.start)..=level.pte_index(range.end))
-            .scan(self.sign_extend(range.start), |state, pte_index| {
-                let page_range = *state..level.end(*state).min(range.end);
-                *state = self.sign_extend(level.end(*state) + 1);
+        // Record the physical address of this page table, such that it can be recognized if one
+        // of its PTEs refers back to it.
+        if depth < MAX_LEVELS {
+            path[depth] = phys_addr;
+        }
 
-                Some((pte_index, page_range))
-            });
+        // There is nothing to do for an empty range.
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let page_ranges = self.page_ranges(index, range.clone());
+
+        // Map the whole page table at once if the mapper supports it, such that the PTEs can be
+        // indexed directly. The mapped page table must hold all the PTEs of the page table.
+        let table = match self.pte_size {
+            8 => mapper.map_table(phys_addr, level.entries())?,
+            _ => None,
+        };
+        let table = match table {
+            Some(table) => Some(table.get(..level.entries()).ok_or(Mapper::PTE_NOT_FOUND)?),
+            _ => None,
+        };
+
+        // Otherwise, the PTEs are read in batches, as the PTE indices are consecutive.
+        let last_index = self.last_pte_index(index, range.clone());
+        let mut batch = [0u64; PTE_BATCH_SIZE];
+        let mut batch_range = 0..0;
 
         for (pte_index, page_range) in page_ranges {
-            // Get the PTE index for this page range, and then index into the page table to get the
-            // corresponding PTE.
-            let offset = (pte_index * self.pte_size) as u64;
-            let pte = mapper.read_pte(self.pte_size, phys_addr + offset)?;
+            // Read the next batch of PTEs if the PTE index is not part of the current batch.
+            if table.is_none() && !batch_range.contains(&pte_index) {
+                let count = (last_index - pte_index + 1).min(PTE_BATCH_SIZE);
+                let offset = (pte_index * self.pte_size) as u64;
+                let count = self.read_ptes(mapper, phys_addr + offset, &mut batch[..count])?;
+
+                if count == 0 {
+                    return Err(Mapper::PTE_NOT_FOUND);
+                }
+
+                batch_range = pte_index..pte_index + count;
+            }
+
+            // Get the corresponding PTE for this page range from the mapped page table or the
+            // batch.
+            let pte = match table {
+                Some(table) => self.swap_pte(table[pte_index]),
+                _ => batch[pte_index - batch_range.start],
+            };
+            let location = PteLocation {
+                phys_addr: phys_addr + (pte_index * self.pte_size) as u64,
+                index: pte_index,
+            };
 
             // Determine whether the PTE refers to a page or a page table. That is, it is a page if
             // we are at a leaf page table or if the PTE refers to a huge page. Otherwise, it is a
             // page table.
             let page_type = match index == 0 || level.is_huge_page(pte) {
-                true => PteType::Page(index),
+                true => PteType::Page(index, level.is_huge_page(pte)),
                 _    => PteType::PageTable(index),
             };
 
             // Invoke the user callback to handle this PTE.
-            walker.handle_pte(mapper, page_type, page_range.clone(), &pte)?;
+            walker.handle_pte(mapper, page_type, page_range.clone(), location, &pte)?;
 
             // Invoke the user callback to handle this PTE hole, i.e. when the PTE is not marked as
             // present.
             if !level.is_present(pte) {
-                walker.handle_pte_hole(mapper, index, page_range.clone(), &pte)?;
+                walker.handle_pte_hole(mapper, index, level.hole_kind(pte), page_range.clone(), location, &pte)?;
             }
 
-            // If the user did not decide to unmap this page, then we are done with this PTE and
-            // can resume to the next one.
-            if index == 0 || level.is_huge_page(pte) {
+            // If the PTE refers to a page, then we are done with this PTE and can resume to the
+            // next one. The same applies if the PTE is not present, as there is no page table to
+            // descend into.
+            if index == 0 || level.is_huge_page(pte) || !level.is_present(pte) {
+                continue;
+            }
+
+            // At this point we are dealing with a normal page table. Do not descend into a page
+            // table that has already been visited on the way down, as is the case for recursive
+            // page table mappings, i.e. a PTE that refers back to one of its parent page tables.
+            if path[..(depth + 1).min(MAX_LEVELS)].contains(&self.pte_to_phys(pte)) {
                 continue;
             }
 
-            // At this point we are dealing with a normal page table. Extract the physical address
-            // from the current PTE, and recurse the page table hierarchy.
-            let phys_addr = pte & self.physical_mask;
-            self.do_walk(phys_addr, index - 1, page_range.clone(), walker, mapper)?;
+            // Give the user the opportunity to skip the page table, e.g. to prune the search.
+            if walker.handle_pre_table(mapper, index, page_range.clone(), location, &pte)? == Descend::Skip {
+                continue;
+            }
+
+            // Extract the physical address from the current PTE, and recurse the page table
+            // hierarchy.
+            let phys_addr = self.pte_to_phys(pte);
+            self.do_walk(phys_addr, index - 1, page_range.clone(), walker, mapper, path, depth + 1)?;
 
             // Provide an opportunity to the user to handle the PTE of the page table upon
             // recursion. For instance, to free the page table.
-            walker.handle_post_pte(mapper, index, page_range, &pte)?;
+            walker.handle_post_pte(mapper, index, page_range, location, &pte)?;
         }
 
         Ok(())
@@ -129,6 +559,26 @@ impl<'a> PageFormat<'a> {
     /// address range and the given physical address of the root page table of the page table
     /// hierarchy. It invokes the appropriate user callbacks in [`crate::walker::PageWalker`],
     /// while traversing the page tables.
+    ///
+    /// The page tables are traversed depth-first, such that all the pages in the range that share
+    /// the same page table are handled while the PTE of that page table is still at hand. As a
+    /// result, every PTE within the range is read exactly once during a single walk, regardless of
+    /// how many pages share the same parent page tables. Furthermore, consecutive PTEs within the
+    /// same page table are read in batches of up to [`PTE_BATCH_SIZE`] PTEs using
+    /// [`crate::address_space::PageTableMapper::read_ptes`]. If the mapper implements
+    /// [`crate::address_space::PageTableMapper::map_table`], each page table is instead mapped
+    /// once and its PTEs are indexed directly. The walk fails with
+    /// [`crate::address_space::PageTableMapper::PTE_NOT_FOUND`] if the mapped page table holds
+    /// fewer PTEs than the page table.
+    ///
+    /// Virtual addresses beyond the reach of the root page table, e.g. beyond 4G for x86 PAE, are
+    /// out of range. The walk stops at the last entry of the root page table rather than reading
+    /// past the end of it.
+    ///
+    /// The walk does not descend into a page table that it has already visited on the way down
+    /// from the root page table. That is, for a recursive page table mapping, where a PTE refers
+    /// back to the root page table or one of the other parent page tables, the walker callbacks
+    /// are invoked for the PTE itself, but the page tables are not walked again through it.
     pub fn walk<PageWalker, Mapper, Error>(
         &self,
         phys_addr: u64,
@@ -140,13 +590,80 @@ impl<'a> PageFormat<'a> {
         PageWalker: crate::walker::PageWalker<Mapper, Error>,
         Mapper: crate::address_space::PageTableMapper<Error>,
     {
-        self.do_walk(phys_addr, self.levels.len() - 1, range, walker, mapper)
+        self.do_walk(phys_addr, self.levels.len() - 1, range, walker, mapper, &mut [0; MAX_LEVELS], 0)
+    }
+
+    /// Traverses the page table hierarchy like [`PageFormat::walk`], and adds the statistics of
+    /// the walk to the given [`WalkStats`]. The statistics are collected by wrapping the walker in
+    /// a [`crate::walkers::StatsWalker`].
+    pub fn walk_with_stats<PageWalker, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        range: Range<usize>,
+        walker: &mut PageWalker,
+        mapper: &Mapper,
+        stats: &mut WalkStats,
+    ) -> Result<(), Error>
+    where
+        PageWalker: crate::walker::PageWalker<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        let mut walker = crate::walkers::StatsWalker::new(walker, stats, !range.is_empty());
+
+        self.walk(phys_addr, range, &mut walker, mapper)
+    }
+
+    /// Traverses the page table hierarchy like [`PageFormat::walk`], but first validates that the
+    /// given virtual address range is canonical using [`PageFormat::is_canonical_range`]. If the
+    /// range is not canonical, e.g. because it straddles the non-canonical addresses, this returns
+    /// [`crate::address_space::PageTableMapper::PTE_NOT_FOUND`] without walking the page tables,
+    /// rather than sign extending the range and walking the page tables with surprising PTE
+    /// indices.
+    pub fn walk_checked<PageWalker, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        range: Range<usize>,
+        walker: &mut PageWalker,
+        mapper: &Mapper,
+    ) -> Result<(), Error>
+    where
+        PageWalker: crate::walker::PageWalker<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        if !self.is_canonical_range(range.clone()) {
+            return Err(Mapper::PTE_NOT_FOUND);
+        }
+
+        self.walk(phys_addr, range, walker, mapper)
+    }
+
+    /// Traverses the sub-hierarchy rooted at the page table at the given physical address like
+    /// [`PageFormat::walk`], but starts at the given page level rather than at the root page
+    /// level. This is useful to inspect a known intermediate page table directly. The given
+    /// virtual address range should lie within the virtual address range covered by that page
+    /// table, as only the virtual address bits of the given page level and below are used to
+    /// index into the page tables. If the page level is out of bounds, the walk starts at the
+    /// root page level instead.
+    pub fn walk_from<PageWalker, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        start_level: usize,
+        range: Range<usize>,
+        walker: &mut PageWalker,
+        mapper: &Mapper,
+    ) -> Result<(), Error>
+    where
+        PageWalker: crate::walker::PageWalker<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        self.do_walk(phys_addr, start_level, range, walker, mapper, &mut [0; MAX_LEVELS], 0)
     }
 
     /// This is a recursive helper function used to traverse the page table hierarchy for a given
     /// virtual address range and the given physical address of the page table for the current page
     /// table level. It invokes the appropriate user callbacks in [`crate::walker::PageWalkerMut`],
-    /// while traversing the page tables.
+    /// while traversing the page tables. See [`PageFormat::do_walk`] for the path and the depth.
+    #[allow(clippy::too_many_arguments)]
     fn do_walk_mut<PageWalkerMut, Mapper, Error>(
         &self,
         phys_addr: u64,
@@ -154,6 +671,8 @@ impl<'a> PageFormat<'a> {
         range: Range<usize>,
         walker: &mut PageWalkerMut,
         mapper: &mut Mapper,
+        path: &mut [u64; MAX_LEVELS],
+        depth: usize,
     ) -> Result<(), Error>
     where
         PageWalkerMut: crate::walker::PageWalkerMut<Mapper, Error>,
@@ -166,59 +685,86 @@ impl<'a> PageFormat<'a> {
 
         let level = &self.levels[index];
 
-        // Split up the range by page boundaries, such that we have a range for each page that is
-        // inclusive of the original range. For instance, the range 0x0000..0x1fff spans two 4K
-        // pages, so this iterator would return 0x0000..0x0fff and 0x1000..0x1fff. We also make
-        // sure that the page ranges are sign extended where appropriate. In addition, calculate
-        // the PTE index.
-        let page_ranges = (level.pte_index(range.start)..=level.pte_index(range.end))
-            .scan(self.sign_extend(range.start), |state, pte_index| {
-                let page_range = *state..level.end(*state).min(range.end);
-                *state = self.sign_extend(level.end(*state) + 1);
+        // Record the physical address of this page table, such that it can be recognized if one
+        // of its PTEs refers back to it.
+        if depth < MAX_LEVELS {
+            path[depth] = phys_addr;
+        }
 
-                Some((pte_index, page_range))
-            });
+        // There is nothing to do for an empty range.
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let page_ranges = self.page_ranges(index, range.clone());
 
         for (pte_index, page_range) in page_ranges {
             // Get the PTE index for this page range, and then index into the page table to get the
             // corresponding PTE.
             let offset = (pte_index * self.pte_size) as u64;
-            let mut pte = mapper.read_pte(self.pte_size, phys_addr + offset)?;
+            let mut pte = self.read_pte(mapper, phys_addr + offset)?;
+            let original = pte;
+            let location = PteLocation {
+                phys_addr: phys_addr + offset,
+                index: pte_index,
+            };
 
             // Determine whether the PTE refers to a page or a page table. That is, it is a page if
             // we are at a leaf page table or if the PTE refers to a huge page. Otherwise, it is a
             // page table.
             let page_type = match index == 0 || level.is_huge_page(pte) {
-                true => PteType::Page(index),
+                true => PteType::Page(index, level.is_huge_page(pte)),
                 _    => PteType::PageTable(index),
             };
 
             // Invoke the user callback to handle this PTE.
-            walker.handle_pte(mapper, page_type, page_range.clone(), &mut pte)?;
+            walker.handle_pte(mapper, page_type, page_range.clone(), location, &mut pte)?;
 
             // Invoke the user callback to handle this PTE hole, i.e. when the PTE is not marked as
             // present.
             if !level.is_present(pte) {
-                walker.handle_pte_hole(mapper, index, page_range.clone(), &mut pte)?;
+                walker.handle_pte_hole(mapper, index, level.hole_kind(pte), page_range.clone(), location, &mut pte)?;
             }
 
-            // If the user did not decide to unmap this page, then we are done with this PTE and
-            // can resume to the next one.
-            mapper.write_pte(self.pte_size, phys_addr + offset, pte)?;
+            // Write back the PTE, but only if the user callbacks changed it, as writing to the
+            // page tables may be expensive.
+            if pte != original {
+                self.write_pte(mapper, phys_addr + offset, pte)?;
+            }
 
-            if index == 0 || level.is_huge_page(pte) {
+            // If the PTE refers to a page, then we are done with this PTE and can resume to the
+            // next one. The same applies if the user did not fill the PTE hole, as there is no
+            // page table to descend into. Note that this uses the updated PTE, such that a PTE
+            // hole that has been filled with a huge page is not mistaken for a page table.
+            if index == 0 || level.is_huge_page(pte) || !level.is_present(pte) {
+                continue;
+            }
+
+            // At this point we are dealing with a normal page table. Do not descend into a page
+            // table that has already been visited on the way down, as is the case for recursive
+            // page table mappings, i.e. a PTE that refers back to one of its parent page tables.
+            if path[..(depth + 1).min(MAX_LEVELS)].contains(&self.pte_to_phys(pte)) {
+                continue;
+            }
+
+            // Give the user the opportunity to skip the page table, e.g. to prune the search.
+            if walker.handle_pre_table(mapper, index, page_range.clone(), location, &pte)? == Descend::Skip {
                 continue;
             }
 
-            // At this point we are dealing with a normal page table. Extract the physical address
-            // from the current PTE, and recurse the page table hierarchy.
-            let phys_addr = pte & self.physical_mask;
-            self.do_walk_mut(phys_addr, index - 1, page_range.clone(), walker, mapper)?;
+            // Extract the physical address from the current PTE, and recurse the page table
+            // hierarchy.
+            let page_table = self.pte_to_phys(pte);
+            self.do_walk_mut(page_table, index - 1, page_range.clone(), walker, mapper, path, depth + 1)?;
 
             // Provide an opportunity to the user to handle the PTE of the page table upon
             // recursion. For instance, to free the page table.
-            walker.handle_post_pte(mapper, index, page_range, &mut pte)?;
-            mapper.write_pte(self.pte_size, phys_addr + offset, pte)?;
+            let original = pte;
+            walker.handle_post_pte(mapper, index, page_range, location, &mut pte)?;
+
+            if pte != original {
+                self.write_pte(mapper, phys_addr + offset, pte)?;
+            }
         }
 
         Ok(())
@@ -226,8 +772,13 @@ impl<'a> PageFormat<'a> {
 
     /// This is a recursive function used to traverse the page table hierarchy for a given virtual
     /// address range and the given physical address of the root page table of the page table
-    /// hierarchy. It invokes the appropriate user callbacks in [`crate::walker::PageWalker`],
+    /// hierarchy. It invokes the appropriate user callbacks in [`crate::walker::PageWalkerMut`],
     /// while traversing the page tables.
+    ///
+    /// Like [`PageFormat::walk`], every PTE within the range is read exactly once using
+    /// [`crate::address_space::PageTableMapper::read_pte`] during a single walk. A PTE is only
+    /// written back using [`crate::address_space::PageTableMapper::write_pte`] if one of the user
+    /// callbacks changed it. Recursive page table mappings are not walked again either.
     pub fn walk_mut<PageWalkerMut, Mapper, Error>(
         &self,
         phys_addr: u64,
@@ -239,6 +790,63 @@ impl<'a> PageFormat<'a> {
         PageWalkerMut: crate::walker::PageWalkerMut<Mapper, Error>,
         Mapper: crate::address_space::PageTableMapper<Error>,
     {
-        self.do_walk_mut(phys_addr, self.levels.len() - 1, range, walker, mapper)
+        self.do_walk_mut(phys_addr, self.levels.len() - 1, range, walker, mapper, &mut [0; MAX_LEVELS], 0)
+    }
+
+    /// Traverses the page table hierarchy like [`PageFormat::walk_mut`], and adds the statistics
+    /// of the walk to the given [`WalkStats`]. See [`PageFormat::walk_with_stats`].
+    pub fn walk_mut_with_stats<PageWalkerMut, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        range: Range<usize>,
+        walker: &mut PageWalkerMut,
+        mapper: &mut Mapper,
+        stats: &mut WalkStats,
+    ) -> Result<(), Error>
+    where
+        PageWalkerMut: crate::walker::PageWalkerMut<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        let mut walker = crate::walkers::StatsWalker::new(walker, stats, !range.is_empty());
+
+        self.walk_mut(phys_addr, range, &mut walker, mapper)
+    }
+
+    /// Traverses the page table hierarchy like [`PageFormat::walk_mut`], but first validates that
+    /// the given virtual address range is canonical. See [`PageFormat::walk_checked`].
+    pub fn walk_mut_checked<PageWalkerMut, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        range: Range<usize>,
+        walker: &mut PageWalkerMut,
+        mapper: &mut Mapper,
+    ) -> Result<(), Error>
+    where
+        PageWalkerMut: crate::walker::PageWalkerMut<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        if !self.is_canonical_range(range.clone()) {
+            return Err(Mapper::PTE_NOT_FOUND);
+        }
+
+        self.walk_mut(phys_addr, range, walker, mapper)
+    }
+
+    /// Traverses the sub-hierarchy rooted at the page table at the given physical address like
+    /// [`PageFormat::walk_mut`], but starts at the given page level rather than at the root page
+    /// level. See [`PageFormat::walk_from`] for more details.
+    pub fn walk_mut_from<PageWalkerMut, Mapper, Error>(
+        &self,
+        phys_addr: u64,
+        start_level: usize,
+        range: Range<usize>,
+        walker: &mut PageWalkerMut,
+        mapper: &mut Mapper,
+    ) -> Result<(), Error>
+    where
+        PageWalkerMut: crate::walker::PageWalkerMut<Mapper, Error>,
+        Mapper: crate::address_space::PageTableMapper<Error>,
+    {
+        self.do_walk_mut(phys_addr, start_level, range, walker, mapper, &mut [0; MAX_LEVELS], 0)
     }
 }
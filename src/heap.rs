@@ -0,0 +1,121 @@
+//! This module provides the [`MemoryMapper`] struct which is a [`PageTableMapper`] that models a
+//! flat physical memory on the heap. This is useful for testing and experimenting with page tables
+//! without having access to actual physical memory.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+
+/// The errors returned by the [`MemoryMapper`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The PTE was not found.
+    PteNotFound,
+    /// The page was not present.
+    PageNotPresent,
+    /// The function has not been implemented.
+    NotImplemented,
+    /// The physical address is outside of the physical memory.
+    OutOfBounds,
+    /// There are no free pages left in the physical memory.
+    OutOfMemory,
+    /// The page size is zero.
+    InvalidPageSize,
+}
+
+/// The [`MemoryMapper`] struct is an implementation of a [`PageTableMapper`] that models a flat
+/// physical memory as a [`Vec<u8>`]. Pages are allocated from the start of the physical memory
+/// using a simple bump allocator, and freed pages are kept in a free list to be reused by
+/// subsequent allocations.
+pub struct MemoryMapper {
+    /// The physical memory.
+    pub memory: Vec<u8>,
+    /// The size of a page.
+    page_size: usize,
+    /// The physical address of the next page that has never been allocated.
+    next: u64,
+    /// The physical addresses of the pages that have been freed.
+    free: Vec<u64>,
+}
+
+impl MemoryMapper {
+    /// Creates a new [`MemoryMapper`] with a physical memory of the given size in bytes, using the
+    /// given page size for allocations. The size of the physical memory is rounded down to the
+    /// page size. Returns [`MemoryError::InvalidPageSize`] if the page size is zero.
+    pub fn new(size: usize, page_size: usize) -> Result<Self, MemoryError> {
+        if page_size == 0 {
+            return Err(MemoryError::InvalidPageSize);
+        }
+
+        Ok(Self {
+            memory: vec![0; size - size % page_size],
+            page_size,
+            next: 0,
+            free: Vec::new(),
+        })
+    }
+
+    /// Returns the range of the physical memory for the given physical address and size, or
+    /// [`MemoryError::OutOfBounds`] if it is outside of the physical memory.
+    fn range(&self, phys_addr: u64, size: usize) -> Result<Range<usize>, MemoryError> {
+        let start = usize::try_from(phys_addr)
+            .map_err(|_| MemoryError::OutOfBounds)?;
+        let end = start.checked_add(size)
+            .ok_or(MemoryError::OutOfBounds)?;
+
+        if end > self.memory.len() {
+            return Err(MemoryError::OutOfBounds);
+        }
+
+        Ok(start..end)
+    }
+}
+
+impl PageTableMapper<MemoryError> for MemoryMapper {
+    const PTE_NOT_FOUND: MemoryError = MemoryError::PteNotFound;
+    const PAGE_NOT_PRESENT: MemoryError = MemoryError::PageNotPresent;
+    const NOT_IMPLEMENTED: MemoryError = MemoryError::NotImplemented;
+
+    fn read_bytes(&self, bytes: &mut [u8], phys_addr: u64) -> Result<usize, MemoryError> {
+        let range = self.range(phys_addr, bytes.len())?;
+
+        bytes.copy_from_slice(&self.memory[range]);
+
+        Ok(bytes.len())
+    }
+
+    fn write_bytes(&mut self, phys_addr: u64, bytes: &[u8]) -> Result<usize, MemoryError> {
+        let range = self.range(phys_addr, bytes.len())?;
+
+        self.memory[range].copy_from_slice(bytes);
+
+        Ok(bytes.len())
+    }
+
+    fn alloc_page(&mut self) -> Result<u64, MemoryError> {
+        if let Some(page) = self.free.pop() {
+            return Ok(page);
+        }
+
+        let page = self.next;
+        self.range(page, self.page_size)
+            .map_err(|_| MemoryError::OutOfMemory)?;
+        self.next += self.page_size as u64;
+
+        Ok(page)
+    }
+
+    fn zero_page(&mut self, _pte_size: usize, phys_addr: u64, size: usize) -> Result<(), MemoryError> {
+        let range = self.range(phys_addr, size)?;
+
+        self.memory[range].fill(0);
+
+        Ok(())
+    }
+
+    fn free_page(&mut self, pte: u64) {
+        self.free.push(pte);
+    }
+}
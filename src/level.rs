@@ -1,6 +1,8 @@
 //! This module provides the [`PageLevel`] struct used to describe a single level in a page table
 //! hierarchy. The full page table hierarchy is described by [`crate::format::PageFormat`].
 
+use crate::walker::HoleKind;
+
 /// Describes a single page level of the page hierarchy.
 #[derive(Clone, Debug)]
 pub struct PageLevel {
@@ -21,18 +23,54 @@ pub struct PageLevel {
 }
 
 impl PageLevel {
+    /// Creates a new page level for the given number of bits to shift right in the virtual address
+    /// and the given number of index bits. The page level starts out without a present bit, without
+    /// support for huge pages and without a page table mask, which can then be set using
+    /// [`PageLevel::with_present`], [`PageLevel::with_huge`] and [`PageLevel::with_table_mask`].
+    /// These functions are `const`, such that they can be used to describe the page levels in a
+    /// `const` or `static`.
+    pub const fn new(shift_bits: usize, va_bits: usize) -> Self {
+        Self {
+            shift_bits,
+            va_bits,
+            present_bit: (0, 0),
+            huge_page_bit: (0, 0),
+            page_table_mask: 0,
+        }
+    }
+
+    /// Sets the present bit in the PTE, where the mask selects the relevant bits and the value is
+    /// what they should be upon masking for the PTE to be present.
+    pub const fn with_present(mut self, mask: u64, value: u64) -> Self {
+        self.present_bit = (mask, value);
+        self
+    }
+
+    /// Sets the huge page bit in the PTE, where the mask selects the relevant bits and the value
+    /// is what they should be upon masking for the PTE to point to a huge page.
+    pub const fn with_huge(mut self, mask: u64, value: u64) -> Self {
+        self.huge_page_bit = (mask, value);
+        self
+    }
+
+    /// Sets the page table mask that should be set when allocating new page tables.
+    pub const fn with_table_mask(mut self, mask: u64) -> Self {
+        self.page_table_mask = mask;
+        self
+    }
+
     /// Calculates the number of entries present in a page table for this page level.
-    pub fn entries(&self) -> usize {
+    pub const fn entries(&self) -> usize {
         1 << self.va_bits
     }
 
     /// Calculates the page size for this page level.
-    pub fn page_size(&self) -> usize {
+    pub const fn page_size(&self) -> usize {
         1 << self.shift_bits
     }
 
     /// Calculates the shifted mask to select the appropriate bits from the virtual address.
-    pub fn mask(&self) -> usize {
+    pub const fn mask(&self) -> usize {
         ((1 << self.va_bits) - 1) << self.shift_bits
     }
 
@@ -55,10 +93,53 @@ impl PageLevel {
         (pte & self.present_bit.0) == self.present_bit.1
     }
 
+    /// Given a PTE, it marks the PTE as present by first clearing the bits selected by the mask of
+    /// the present bit, and then setting the value of the present bit. This ensures that the PTE
+    /// is marked as present regardless of its current present bits, which matters for present
+    /// fields that consist of multiple bits, e.g. `(0b11, 0b11)` on ARM, and for present bits that
+    /// have to be cleared to mark the PTE as present.
+    pub fn set_present(&self, pte: u64) -> u64 {
+        (pte & !self.present_bit.0) | self.present_bit.1
+    }
+
+    /// Returns the empty PTE, i.e. the PTE that is not marked as present and that has all the
+    /// other bits cleared. This is zero, unless the present bits have an inverted polarity, i.e.
+    /// when the present bits have to be cleared to mark the PTE as present. In that case, the
+    /// empty PTE has these present bits set instead. The page tables are filled with this PTE
+    /// when they are allocated, and PTEs are reset to this PTE when they are cleared.
+    pub fn empty_pte(&self) -> u64 {
+        self.present_bit.0 & !self.present_bit.1
+    }
+
+    /// Given a PTE, it marks the PTE as not present by first clearing the bits selected by the mask
+    /// of the present bit, and then setting the bits of the mask that differ from the value of the
+    /// present bit. This is the inverse of [`PageLevel::set_present`], and leaves the other bits of
+    /// the PTE intact.
+    pub fn clear_present(&self, pte: u64) -> u64 {
+        (pte & !self.present_bit.0) | self.empty_pte()
+    }
+
+    /// Given a PTE that is not marked as present, it checks whether the PTE is empty or whether
+    /// some of the bits other than the present bits are set, e.g. to encode a swap entry.
+    pub fn hole_kind(&self, pte: u64) -> HoleKind {
+        match pte & !self.present_bit.0 {
+            0 => HoleKind::Empty,
+            _ => HoleKind::NonZero,
+        }
+    }
+
+    /// Checks if the current page level supports huge pages.
+    pub fn supports_huge_pages(&self) -> bool {
+        self.huge_page_bit.0 != 0
+    }
+
     /// Given a PTE, it checks if the PTE points to a huge page. Always returns `false` if the
-    /// current page level does not support huge pages.
+    /// current page level does not support huge pages. The present bits are checked as well, such
+    /// that a huge page bit that encodes a huge page as the absence of a bit, e.g. the block
+    /// descriptors on AArch64, does not cause a PTE that is not present to be considered a huge
+    /// page.
     pub fn is_huge_page(&self, pte: u64) -> bool {
-        if self.huge_page_bit.0 != 0 {
+        if self.supports_huge_pages() {
             let mask = self.present_bit.0 | self.huge_page_bit.0;
             let value = self.present_bit.1 | self.huge_page_bit.1;
 
@@ -31,18 +31,45 @@
 //! [`AddressSpace`] provides functions to allocate and free pages for a given virtual address
 //! range, change the protection of a given virtual address range and allows mapping and unmapping
 //! a physical address range to a given virtual address range for memory-mapped I/O.
+//!
+//! Finally, the `alloc` feature enables the functionality that depends on the `alloc` crate, such
+//! as `AddressSpace::iter_mappings` to list the mappings of a virtual address range, `diff` to
+//! compare the mappings of two page table hierarchies, `TrackingMapper` to record the physical
+//! addresses written to by a [`PageTableMapper`] and `MemoryMapper` which implements a
+//! [`PageTableMapper`] on top of a flat physical memory on the heap for testing purposes.
 
 #![no_std]
 #![deny(missing_docs, rustdoc::broken_intra_doc_links)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod address_space;
 pub mod arch;
+pub mod contiguous;
+#[cfg(feature = "alloc")]
+pub mod diff;
+pub mod dual_root;
+pub mod error;
 pub mod format;
+#[cfg(feature = "alloc")]
+pub mod heap;
 pub mod level;
+#[cfg(feature = "alloc")]
+pub mod tracking;
 pub mod walker;
 pub mod walkers;
 
-pub use address_space::{AddressSpace, PageTableMapper};
-pub use format::PageFormat;
+pub use address_space::{AddressSpace, PageTableMapper, ReadOnlyAddressSpace};
+pub use contiguous::{ContiguousMapper, ContiguousPhysMapper};
+#[cfg(feature = "alloc")]
+pub use diff::{diff, Difference, DifferenceKind};
+pub use dual_root::DualRootAddressSpace;
+pub use error::{FormatError, WalkError};
+pub use format::{Endian, PageFormat, WalkStats};
+#[cfg(feature = "alloc")]
+pub use heap::{MemoryError, MemoryMapper};
 pub use level::PageLevel;
-pub use walker::{PageWalker, PageWalkerMut, PteType};
+#[cfg(feature = "alloc")]
+pub use tracking::TrackingMapper;
+pub use walker::{Descend, HoleKind, PageWalker, PageWalkerMut, PteLocation, PteType};
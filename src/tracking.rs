@@ -0,0 +1,115 @@
+//! This module provides the [`TrackingMapper`] struct which wraps a [`PageTableMapper`] to keep
+//! track of the physical addresses that are written to.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use crate::address_space::PageTableMapper;
+
+/// The [`TrackingMapper`] struct wraps any [`PageTableMapper`] and forwards all calls to the inner
+/// mapper, while recording the physical addresses passed to [`PageTableMapper::write_pte`],
+/// [`PageTableMapper::write_bytes`] and [`PageTableMapper::zero_page`]. This is useful to find out
+/// which physical frames a walker touches, e.g. for debugging or testing.
+pub struct TrackingMapper<Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The inner mapper.
+    pub mapper: Mapper,
+    /// The physical addresses that have been written to in the order they were first written to.
+    dirtied: Vec<u64>,
+    /// A marker for Error.
+    error: PhantomData<Error>,
+}
+
+impl<Mapper, Error> TrackingMapper<Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Wraps the given mapper.
+    pub fn new(mapper: Mapper) -> Self {
+        Self {
+            mapper,
+            dirtied: Vec::new(),
+            error: PhantomData,
+        }
+    }
+
+    /// Returns the set of physical addresses that have been written to in the order they were first
+    /// written to.
+    pub fn dirtied(&self) -> &[u64] {
+        &self.dirtied
+    }
+
+    /// Clears the set of physical addresses that have been written to.
+    pub fn clear(&mut self) {
+        self.dirtied.clear();
+    }
+
+    /// Unwraps the inner mapper.
+    pub fn into_inner(self) -> Mapper {
+        self.mapper
+    }
+
+    /// Records the given physical address, unless it has been recorded before.
+    fn record(&mut self, phys_addr: u64) {
+        if !self.dirtied.contains(&phys_addr) {
+            self.dirtied.push(phys_addr);
+        }
+    }
+}
+
+impl<Mapper, Error> PageTableMapper<Error> for TrackingMapper<Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    const PTE_NOT_FOUND: Error = Mapper::PTE_NOT_FOUND;
+    const PAGE_NOT_PRESENT: Error = Mapper::PAGE_NOT_PRESENT;
+    const NOT_IMPLEMENTED: Error = Mapper::NOT_IMPLEMENTED;
+
+    fn page_not_present(virt: usize) -> Error {
+        Mapper::page_not_present(virt)
+    }
+
+    fn read_pte(&self, pte_size: usize, phys_addr: u64) -> Result<u64, Error> {
+        self.mapper.read_pte(pte_size, phys_addr)
+    }
+
+    fn read_ptes(&self, pte_size: usize, phys_addr: u64, ptes: &mut [u64]) -> Result<usize, Error> {
+        self.mapper.read_ptes(pte_size, phys_addr, ptes)
+    }
+
+    fn write_pte(&mut self, pte_size: usize, phys_addr: u64, value: u64) -> Result<(), Error> {
+        self.record(phys_addr);
+        self.mapper.write_pte(pte_size, phys_addr, value)
+    }
+
+    fn read_bytes(&self, bytes: &mut [u8], phys_addr: u64) -> Result<usize, Error> {
+        self.mapper.read_bytes(bytes, phys_addr)
+    }
+
+    fn write_bytes(&mut self, phys_addr: u64, bytes: &[u8]) -> Result<usize, Error> {
+        self.record(phys_addr);
+        self.mapper.write_bytes(phys_addr, bytes)
+    }
+
+    fn alloc_page(&mut self) -> Result<u64, Error> {
+        self.mapper.alloc_page()
+    }
+
+    fn alloc_huge_page(&mut self, size: usize) -> Result<Option<u64>, Error> {
+        self.mapper.alloc_huge_page(size)
+    }
+
+    fn map_table(&self, phys_addr: u64, entries: usize) -> Result<Option<&[u64]>, Error> {
+        self.mapper.map_table(phys_addr, entries)
+    }
+
+    fn zero_page(&mut self, pte_size: usize, phys_addr: u64, size: usize) -> Result<(), Error> {
+        self.record(phys_addr);
+        self.mapper.zero_page(pte_size, phys_addr, size)
+    }
+
+    fn free_page(&mut self, pte: u64) {
+        self.mapper.free_page(pte)
+    }
+}
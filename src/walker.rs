@@ -8,8 +8,9 @@ use core::ops::Range;
 /// The PTE can either be a page or page table.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PteType {
-    /// The PTE refers to a physical page.
-    Page(usize),
+    /// The PTE refers to a physical page. The flag indicates whether the PTE refers to a huge page
+    /// as determined by [`crate::level::PageLevel::is_huge_page`] during the walk.
+    Page(usize, bool),
     /// The PTE refers to another page table.
     PageTable(usize),
 }
@@ -17,18 +18,12 @@ pub enum PteType {
 impl PteType {
     /// Returns `true` if the [`PteType`] is a page and `false` otherwise.
     pub fn is_page(&self) -> bool {
-        match self {
-            PteType::Page(_) => true,
-            _ => false,
-        }
+        matches!(self, PteType::Page(..))
     }
 
     /// Returns `true` if the [`PteType`] is a page table and `false` otherwise.
     pub fn is_page_table(&self) -> bool {
-        match self {
-            PteType::PageTable(_) => true,
-            _ => false,
-        }
+        matches!(self, PteType::PageTable(_))
     }
 
     /// Extracts the level at which the PTE is found. The level is a monotonicly increasing number
@@ -36,64 +31,136 @@ impl PteType {
     /// table.
     pub fn level(&self) -> usize {
         match self {
-            Self::Page(level) => *level,
+            Self::Page(level, _) => *level,
             Self::PageTable(level) => *level,
         }
     }
 
     /// Returns whether the current PTE refers to a huge page, i.e. it checks whether the page type
-    /// is a page and the level is non-zero. Returns `true` if it is a huge page and `false`
-    /// otherwise.
+    /// is a page that has been marked as a huge page during the walk. Returns `true` if it is a
+    /// huge page and `false` otherwise.
     pub fn is_huge_page(&self) -> bool {
-        match self {
-            Self::Page(level) if *level != 0 => true,
-            _ => false,
-        }
+        matches!(self, Self::Page(_, true))
     }
 }
 
+/// Describes where a PTE is located in physical memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PteLocation {
+    /// The physical address of the PTE, i.e. the physical address of the page table plus the
+    /// offset of the PTE within that page table.
+    pub phys_addr: u64,
+    /// The index of the PTE within its page table, i.e. the index returned by
+    /// [`crate::level::PageLevel::pte_index`] for the virtual addresses covered by the PTE.
+    pub index: usize,
+}
+
+/// A PTE hole can either be an empty PTE or a PTE that is not marked as present, but that still
+/// holds information in its other bits. For instance, operating systems may use the latter to
+/// encode the swap slot of a page that has been swapped out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoleKind {
+    /// The PTE is empty, i.e. all the bits other than the present bits are zero.
+    Empty,
+    /// The PTE is not present, but has some of the bits other than the present bits set, e.g. to
+    /// encode a swap entry.
+    NonZero,
+}
+
+/// Decides whether the page table walker descends into a page table, as returned by
+/// [`PageWalker::handle_pre_table`] and [`PageWalkerMut::handle_pre_table`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Descend {
+    /// Descend into the page table.
+    Into,
+    /// Skip the page table and all the PTEs below it. As the walker does not descend into the
+    /// page table, the post-PTE callback is not invoked for it either.
+    Skip,
+}
+
 /// The [`crate::format::PageFormat::walk`] function expects a type that implements this trait to
 /// invoke the appropriate user callbacks, such that the user can provide an implementation for
 /// interacting with the various PTEs during the page table walk. For the mutable version, see
 /// [`crate::format::PageFormat::walk_mut`] and [`PageWalkerMut`].
+///
+/// The PTEs are read through the [`crate::address_space::PageTableMapper`] that is passed to the
+/// walk, rather than through the walker itself. As all the callbacks are optional, a minimal
+/// walker only has to implement the callbacks it is interested in, e.g. just
+/// [`PageWalker::handle_pte`].
+///
+/// The page tables are traversed depth-first in ascending order of the virtual addresses, and the
+/// order in which the callbacks are invoked is guaranteed. For every PTE within the range:
+///
+/// 1. [`PageWalker::handle_pte`] is invoked first, i.e. the PTEs of page tables are visited in
+///    pre-order, before any of the PTEs below them.
+/// 2. [`PageWalker::handle_pte_hole`] is invoked if the PTE is not marked as present.
+/// 3. If the PTE refers to a page table, [`PageWalker::handle_pre_table`] is invoked, after which
+///    the walker descends into the page table and visits all the PTEs below it within the range.
+/// 4. [`PageWalker::handle_post_pte`] is invoked for the PTE of the page table once all the PTEs
+///    below it have been visited, i.e. the PTEs of page tables are also visited in post-order.
+///
+/// Hence, [`PageWalker::handle_pte`] can be used to install or inspect page tables top-down,
+/// whereas [`PageWalker::handle_post_pte`] can be used to free page tables bottom-up.
 pub trait PageWalker<Mapper, Error>
 where
     Mapper: crate::address_space::PageTableMapper<Error>,
 {
     /// This callback handles the current PTE unconditionally and is given the [`PteType`], the
-    /// virtual address range and an immutable reference to the PTE. The implementation of this
-    /// callback is optional.
+    /// virtual address range, the [`PteLocation`] and an immutable reference to the PTE. The
+    /// implementation of this callback is optional.
     fn handle_pte(
         &mut self,
         _mapper: &Mapper,
         _page_type: PteType,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &u64,
     ) -> Result<(), Error> {
         Ok(())
     }
 
     /// This callback handles a PTE hole, i.e. a PTE that is not marked as present, and is given
-    /// the level, the virtual address range and an immutable reference to the PTE. The
-    /// implementation of this callback is optional.
+    /// the level, the [`HoleKind`], the virtual address range, the [`PteLocation`] and an
+    /// immutable reference to the PTE. The implementation of this callback is optional.
     fn handle_pte_hole(
         &mut self,
         _mapper: &Mapper,
         _level: usize,
+        _kind: HoleKind,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &u64,
     ) -> Result<(), Error> {
         Ok(())
     }
 
+    /// This callback handles the PTE of a present page table before recursing the page table
+    /// hierarchy, and is given the level, the virtual address range, the [`PteLocation`] and an
+    /// immutable reference to the PTE. Returning [`Descend::Skip`] prevents the walker from
+    /// descending into the page table, e.g. to prune subtrees that are of no interest. In that
+    /// case, none of the PTEs below are visited and the post-PTE callback is not invoked for this
+    /// PTE. The implementation of this callback is optional, and by default the walker descends
+    /// into every page table.
+    fn handle_pre_table(
+        &mut self,
+        _mapper: &Mapper,
+        _level: usize,
+        _range: Range<usize>,
+        _location: PteLocation,
+        _pte: &u64,
+    ) -> Result<Descend, Error> {
+        Ok(Descend::Into)
+    }
+
     /// This callback handles the PTE of a page table after recursing the page table hierarchy, and
-    /// is given the level, the virtual address and an immutable reference to the PTE. The
-    /// implementation of this callback is optional.
+    /// is given the level, the virtual address range, the [`PteLocation`] and an immutable
+    /// reference to the PTE. The implementation of this callback is optional.
     fn handle_post_pte(
         &mut self,
         _mapper: &Mapper,
         _level: usize,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &u64,
     ) -> Result<(), Error> {
         Ok(())
@@ -104,44 +171,79 @@ where
 /// to invoke the appropriate user callbacks, such that the user can provide an implementation for
 /// interacting with the various PTEs during the page table walk. For the immutable version, see
 /// [`crate::format::PageFormat::walk`] and [`PageWalker`].
+///
+/// Like [`PageWalker`], the PTEs are read and written through the
+/// [`crate::address_space::PageTableMapper`] that is passed to the walk, and all the callbacks are
+/// optional. The callbacks are invoked in the same order as those of [`PageWalker`], where a PTE
+/// changed by one callback is passed to the next callback before it is written back. As the
+/// walker only descends into a page table once the PTE hole callback has been invoked, a PTE
+/// hole that is filled with a page table is descended into right away.
 pub trait PageWalkerMut<Mapper, Error>
 where
     Mapper: crate::address_space::PageTableMapper<Error>,
 {
     /// This callback handles the current PTE unconditionally and is given the [`PteType`], the
-    /// virtual address range and a mutable reference to the PTE. The implementation of this
-    /// callback is optional.
+    /// virtual address range, the [`PteLocation`] and a mutable reference to the PTE. The
+    /// implementation of this callback is optional.
     fn handle_pte(
         &mut self,
         _mapper: &mut Mapper,
         _page_type: PteType,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &mut u64,
     ) -> Result<(), Error> {
         Ok(())
     }
 
     /// This callback handles a PTE hole, i.e. a PTE that is not marked as present, and is given
-    /// the level, the virtual address range and a mutable reference to the PTE. The
-    /// implementation of this callback is optional.
+    /// the level, the [`HoleKind`], the virtual address range, the [`PteLocation`] and a mutable
+    /// reference to the PTE. The implementation of this callback is optional.
+    ///
+    /// The callback may fill the PTE hole. Whether the walker then descends into the PTE is
+    /// decided by the updated PTE: the walker only descends if the PTE is present and refers to a
+    /// page table. That is, if the callback fills the PTE hole at an intermediate level with a huge
+    /// page, i.e. with the huge page bit of that level set, then the walker does not descend into
+    /// it as if it were a page table.
     fn handle_pte_hole(
         &mut self,
         _mapper: &mut Mapper,
         _level: usize,
+        _kind: HoleKind,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &mut u64,
     ) -> Result<(), Error> {
         Ok(())
     }
 
+    /// This callback handles the PTE of a present page table before recursing the page table
+    /// hierarchy, and is given the level, the virtual address range, the [`PteLocation`] and an
+    /// immutable reference to the PTE. Returning [`Descend::Skip`] prevents the walker from
+    /// descending into the page table, e.g. to prune subtrees that are of no interest. In that
+    /// case, none of the PTEs below are visited and the post-PTE callback is not invoked for this
+    /// PTE. The implementation of this callback is optional, and by default the walker descends
+    /// into every page table.
+    fn handle_pre_table(
+        &mut self,
+        _mapper: &mut Mapper,
+        _level: usize,
+        _range: Range<usize>,
+        _location: PteLocation,
+        _pte: &u64,
+    ) -> Result<Descend, Error> {
+        Ok(Descend::Into)
+    }
+
     /// This callback handles the PTE of a page table after recursing the page table hierarchy, and
-    /// is given the level, the virtual address and a mutable reference to the PTE. The
-    /// implementation of this callback is optional.
+    /// is given the level, the virtual address range, the [`PteLocation`] and a mutable reference
+    /// to the PTE. The implementation of this callback is optional.
     fn handle_post_pte(
         &mut self,
         _mapper: &mut Mapper,
         _level: usize,
         _range: Range<usize>,
+        _location: PteLocation,
         _pte: &mut u64,
     ) -> Result<(), Error> {
         Ok(())
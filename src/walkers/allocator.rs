@@ -1,10 +1,12 @@
 //! This modules implements the [`PteAllocator`] struct which is a helper used to allocate the pages
-//! and the underlying page tables for a given range of virtual addresses.
+//! and the underlying page tables for a given range of virtual addresses, and the
+//! [`PteFrameAllocator`] struct which is a helper used to do the same with pages supplied by the
+//! caller.
 
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::PageFormat;
+use crate::{HoleKind, PageFormat, PteLocation};
 
 /// The [`PteAllocator`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
 /// allocate pages and the underlying page tables for a given virtual address range. This is used
@@ -19,40 +21,111 @@ where
     pub format: &'a PageFormat<'a>,
     /// The mask to set for pages.
     pub mask: Option<u64>,
+    /// Whether huge pages should be allocated where the virtual address range is suitably
+    /// aligned, and the mapper is able to allocate them.
+    pub huge_pages: bool,
     /// A marker for Error.
     pub error: PhantomData<Error>,
     /// A marker for Mapper.
     pub mapper: PhantomData<Mapper>,
 }
 
+impl<'a, Mapper, Error> PteAllocator<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Allocates and clears a page table for the given level, and marks the PTE as present.
+    fn alloc_page_table(&self, mapper: &mut Mapper, index: usize, pte: &mut u64) -> Result<(), Error> {
+        let level = &self.format.levels[index];
+        let page_table = mapper.alloc_page()?;
+        self.format.clear_page_table(mapper, index, page_table)?;
+
+        // Mark the page table as present, set the page table mask and ensure it is **not** a huge
+        // page.
+        *pte = self.format.make_pte(index, page_table, level.page_table_mask);
+
+        Ok(())
+    }
+}
+
 impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteAllocator<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// Allocates the page or page table for the current level as we are handling PTE holes. If the
-    /// mask is set to None, then this function only allocates page tables.
-    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
+    /// mask is set to None, then this function only allocates page tables. Present PTEs are never
+    /// passed to this function, so existing pages are never overwritten. If huge pages are enabled,
+    /// the current level supports huge pages and the virtual address range covers the whole page
+    /// of the current level, then this function tries to allocate a huge page using
+    /// [`PageTableMapper::alloc_huge_page`], such that the walker does not descend any further.
+    /// Otherwise, it falls back to allocating a page table.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
         let level = &self.format.levels[index];
+        let page_mask = level.page_size() - 1;
 
-        match index {
-            0 => {
-                if let Some(mask) = self.mask {
-                    let page = mapper.alloc_page()?;
+        match (index, self.mask) {
+            (0, Some(mask)) => {
+                let page = mapper.alloc_page()?;
 
-                    // Mark the page as present and set the page mask.
-                    *pte = page | level.present_bit.1 | mask;
+                // Mark the page as present and set the page mask.
+                *pte = self.format.make_pte(index, page, mask);
+            }
+            (0, None) => (),
+            (_, Some(mask)) if self.huge_pages &&
+                level.supports_huge_pages() &&
+                range.start & page_mask == 0 &&
+                range.end.wrapping_sub(range.start) == level.page_size() => {
+                match mapper.alloc_huge_page(level.page_size())? {
+                    // Mark the page as present, set the page mask and ensure it is a huge page.
+                    Some(page) => *pte = self.format.make_huge_pte(index, page, mask),
+                    None => self.alloc_page_table(mapper, index, pte)?,
                 }
             }
-            _ => {
-                let page_table = mapper.alloc_page()?;
+            _ => self.alloc_page_table(mapper, index, pte)?,
+        }
 
-                // Mark the page table as present, set the page table mask and ensure it is
-                // **not** a huge page.
-                *pte = page_table | level.present_bit.1 | level.page_table_mask |
-                    ((level.huge_page_bit.0 ^ level.huge_page_bit.1) & level.huge_page_bit.0);
-            }
+        Ok(())
+    }
+}
+
+/// The [`PteFrameAllocator`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used
+/// to allocate pages and the underlying page tables for a given virtual address range like
+/// [`PteAllocator`], but invokes the user closure to supply the physical page for each PTE hole at
+/// the leaf page level instead of allocating it using [`PageTableMapper::alloc_page`]. The
+/// closure is given the virtual address of the page. This is used by the
+/// [`AddressSpace::allocate_range_with`] method.
+///
+/// [`AddressSpace::allocate_range_with`]: `super::super::AddressSpace::allocate_range_with`
+pub struct PteFrameAllocator<'a, Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(usize) -> Result<u64, Error>,
+{
+    /// The walker used to allocate the page tables.
+    pub allocator: PteAllocator<'a, Mapper, Error>,
+    /// The mask to set for pages.
+    pub mask: u64,
+    /// The closure that supplies the physical page for a given virtual address.
+    pub f: F,
+}
+
+impl<'a, Mapper, Error, F> crate::PageWalkerMut<Mapper, Error> for PteFrameAllocator<'a, Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(usize) -> Result<u64, Error>,
+{
+    /// Allocates the page table for PTE holes of page tables. For PTE holes of pages, this invokes
+    /// the closure to get the physical page, and marks the page as present.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if index != 0 {
+            return self.allocator.handle_pte_hole(mapper, index, kind, range, location, pte);
         }
 
+        let page = (self.f)(range.start)?;
+
+        // Mark the page as present and set the page mask.
+        *pte = self.allocator.format.make_pte(0, page, self.mask);
+
         Ok(())
     }
 }
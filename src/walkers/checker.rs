@@ -0,0 +1,33 @@
+//! This modules implements the [`HoleChecker`] struct which is a helper used to check that all the
+//! pages within a given range of virtual addresses are present.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{HoleKind, PteLocation};
+
+/// The [`HoleChecker`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// check that all the pages within a given virtual address range are present. This is used by the
+/// [`AddressSpace::protect_range_strict`] method.
+///
+/// [`AddressSpace::protect_range_strict`]: `super::super::AddressSpace::protect_range_strict`
+pub struct HoleChecker<Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<Mapper, Error> crate::PageWalker<Mapper, Error> for HoleChecker<Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Returns the error from [`PageTableMapper::page_not_present`] for the start of the range as
+    /// the range contains a PTE hole.
+    fn handle_pte_hole(&mut self, _mapper: &Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), Error> {
+        Err(Mapper::page_not_present(range.start))
+    }
+}
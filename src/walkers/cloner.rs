@@ -0,0 +1,120 @@
+//! This modules implements the [`PteCloner`] struct which is a helper used to clone the page table
+//! hierarchy for a given range of virtual addresses into another page table hierarchy.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::format::MAX_LEVELS;
+use crate::{HoleKind, PageFormat, PteLocation, PteType};
+
+/// The [`PteCloner`] struct is an implementation of a [`crate::walker::PageWalker`] used to clone
+/// the page table hierarchy for a given virtual address range into a destination page table
+/// hierarchy. Page tables that are present in the source hierarchy, but missing in the destination
+/// hierarchy are allocated, while the PTEs of pages are copied verbatim, such that both page table
+/// hierarchies share the same physical pages. Non-present PTEs that are not empty, e.g. swap
+/// entries, are copied verbatim as well. This is used by the [`AddressSpace::clone_into`] method.
+///
+/// [`AddressSpace::clone_into`]: `super::super::AddressSpace::clone_into`
+pub struct PteCloner<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The physical addresses of the destination page tables for each page level that are
+    /// currently being walked. The last page level should be set to the destination root.
+    pub tables: [u64; MAX_LEVELS],
+    /// The page table mapper for the destination page table hierarchy.
+    pub dest_mapper: &'a mut Mapper,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+}
+
+impl<'a, Mapper, Error> PteCloner<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Returns the physical address of the destination PTE corresponding to the source PTE at the
+    /// given level for the given virtual address range.
+    fn dest_pte_addr(&self, index: usize, range: &Range<usize>) -> u64 {
+        let level = &self.format.levels[index];
+        let offset = (level.pte_index(range.start) * self.format.pte_size) as u64;
+
+        self.tables[index] + offset
+    }
+
+    /// Writes the given PTE verbatim to the destination PTE at the given physical address. Returns
+    /// [`PageTableMapper::PTE_NOT_FOUND`] if the destination PTE refers to a page table, as the
+    /// page table and the page tables below it would be leaked otherwise.
+    fn copy_pte(&mut self, index: usize, phys_addr: u64, pte: u64) -> Result<(), Error> {
+        let level = &self.format.levels[index];
+        let dest_pte = self.format.read_pte(self.dest_mapper, phys_addr)?;
+
+        if index != 0 && level.is_present(dest_pte) && !level.is_huge_page(dest_pte) {
+            return Err(Mapper::PTE_NOT_FOUND);
+        }
+
+        self.format.write_pte(self.dest_mapper, phys_addr, pte)
+    }
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for PteCloner<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Copies the PTE of a page to the destination page table. If the PTE points to a page table,
+    /// then this function looks up the corresponding destination page table and allocates it if
+    /// it is missing. Returns [`PageTableMapper::PTE_NOT_FOUND`] if the destination maps a huge
+    /// page where the source maps a page table, as the huge page cannot be descended into, or if
+    /// the destination maps a page table where the source maps a huge page.
+    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        let index = pte_type.level();
+        let level = &self.format.levels[index];
+
+        if !level.is_present(*pte) {
+            return Ok(());
+        }
+
+        // Get the physical address of the corresponding destination PTE.
+        let phys_addr = self.dest_pte_addr(index, &range);
+
+        if pte_type.is_page() {
+            // Copy the PTE of the page verbatim.
+            return self.copy_pte(index, phys_addr, *pte);
+        }
+
+        let address_mask = self.format.address_mask();
+        let mut dest_pte = self.format.read_pte(self.dest_mapper, phys_addr)?;
+
+        // The destination maps a huge page that would otherwise be treated as a page table.
+        if level.is_huge_page(dest_pte) {
+            return Err(Mapper::PTE_NOT_FOUND);
+        }
+
+        // Allocate the destination page table if it is missing, and copy the flags of the source
+        // PTE.
+        if !level.is_present(dest_pte) {
+            let page_table = self.dest_mapper.alloc_page()?;
+            self.format.clear_page_table(self.dest_mapper, index, page_table)?;
+
+            dest_pte = self.format.phys_to_pte(page_table) | (*pte & !address_mask);
+            self.format.write_pte(self.dest_mapper, phys_addr, dest_pte)?;
+        }
+
+        self.tables[index - 1] = self.format.pte_to_phys(dest_pte);
+
+        Ok(())
+    }
+
+    /// Copies non-present PTEs that are not empty, e.g. swap entries, verbatim to the destination
+    /// page table, such that both page table hierarchies refer to the same swapped out pages.
+    fn handle_pte_hole(&mut self, _mapper: &Mapper, level: usize, kind: HoleKind, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        if kind != HoleKind::NonZero {
+            return Ok(());
+        }
+
+        let phys_addr = self.dest_pte_addr(level, &range);
+
+        self.copy_pte(level, phys_addr, *pte)
+    }
+}
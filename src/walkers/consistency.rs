@@ -0,0 +1,63 @@
+//! This modules implements the [`PteConsistencyFixer`] struct which is a helper used to propagate
+//! the permission bits of pages to the page tables for a given range of virtual addresses.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::format::MAX_LEVELS;
+use crate::{PageFormat, PteLocation, PteType};
+
+/// The [`PteConsistencyFixer`] struct is an implementation of a [`crate::walker::PageWalkerMut`]
+/// used to propagate the permission bits of the PTEs within a page table to the PTE pointing to
+/// that page table for a given virtual address range, such that the permissions of a page table
+/// never restrict the permissions of the pages it maps unintentionally. This is used by the
+/// [`AddressSpace::propagate_permissions`] method.
+///
+/// [`AddressSpace::propagate_permissions`]: `super::super::AddressSpace::propagate_permissions`
+pub struct PteConsistencyFixer<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The mask of permission bits to propagate.
+    pub mask: u64,
+    /// The union of the permission bits of the PTEs that are present for each page level that is
+    /// currently being walked.
+    pub bits: [u64; MAX_LEVELS],
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteConsistencyFixer<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Adds the permission bits of the PTE to the union for the current page level, if the PTE is
+    /// present. If the PTE points to a page table, then this function also resets the union for
+    /// the next page level.
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let index = pte_type.level();
+
+        if self.format.levels[index].is_present(*pte) {
+            self.bits[index] |= *pte & self.mask;
+        }
+
+        if pte_type.is_page_table() {
+            self.bits[index - 1] = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the union of the permission bits of the PTEs within the page table in the PTE pointing
+    /// to that page table.
+    fn handle_post_pte(&mut self, _mapper: &mut Mapper, index: usize, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        *pte |= self.bits[index - 1];
+        self.bits[index] |= *pte & self.mask;
+
+        Ok(())
+    }
+}
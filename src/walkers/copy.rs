@@ -1,12 +1,19 @@
-//! This modules implements the [`CopyFromWalker`] and [`CopyToWalker`] structs which are walkers used to copy data from and to a virtual address range.
+//! This modules implements the [`CopyFromWalker`], [`CopyToWalker`], [`CopyToAllocWalker`],
+//! [`FillWalker`] and [`VerifyWalker`] structs which are walkers used to copy data from and to a
+//! virtual address range, to fill a virtual address range and to compare a virtual address range
+//! against a buffer.
 
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::{PageFormat, PteType};
+use crate::walkers::PteAllocator;
+use crate::{HoleKind, PageFormat, PteLocation, PteType};
 
 /// The [`CopyFromWalker`] struct is an implementation of a [`crate::walker::PageWalker`] used to
-/// copy data from a given a virtual address range.
+/// copy data from a given a virtual address range. Adjacent pages that are physically contiguous,
+/// e.g. within a direct-mapped region, are copied using a single call to
+/// [`PageTableMapper::read_bytes`]. Use [`CopyFromWalker::flush`] after the walk to ensure that
+/// all the data has been copied.
 ///
 /// This is used by the [`AddressSpace::copy_from`] method.
 ///
@@ -15,10 +22,14 @@ pub struct CopyFromWalker<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
-    /// The offset within the buffer.
+    /// The offset within the buffer, i.e. the number of bytes copied so far.
     pub offset: usize,
     /// Storage for the copied data.
     pub data: &'a mut [u8],
+    /// The run of physically contiguous bytes that has yet to be copied as the physical address
+    /// and the size, such that adjacent pages that are physically contiguous are copied using a
+    /// single call to [`PageTableMapper::read_bytes`].
+    pub pending: Option<(u64, usize)>,
     /// The page format.
     pub format: &'a PageFormat<'a>,
     /// A marker for Error.
@@ -27,42 +38,103 @@ where
     pub mapper: PhantomData<Mapper>,
 }
 
+impl<'a, Mapper, Error> CopyFromWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Copies the pending run of physically contiguous bytes to the buffer. As the mapper may read
+    /// fewer bytes than requested, e.g. when the run spans physical pages that the mapper cannot
+    /// access at once, this keeps reading until the whole run has been copied.
+    pub fn flush(&mut self, mapper: &Mapper) -> Result<(), Error> {
+        let (phys_addr, size) = match self.pending.take() {
+            Some(pending) => pending,
+            _ => return Ok(()),
+        };
+
+        let end = self.offset + size;
+        let mut addr = phys_addr;
+
+        while self.offset < end {
+            let count = mapper.read_bytes(&mut self.data[self.offset..end], addr)?;
+
+            if count == 0 {
+                return Err(Mapper::PAGE_NOT_PRESENT);
+            }
+
+            self.offset += count;
+            addr += count as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the given bytes to the pending run if they are physically contiguous with it, or copies
+    /// the pending run and starts a new one otherwise. The pending run is copied as soon as it
+    /// reaches the end of the buffer.
+    fn push(&mut self, mapper: &Mapper, phys_addr: u64, size: usize) -> Result<(), Error> {
+        match self.pending {
+            Some((start, len)) if start + len as u64 == phys_addr => {
+                self.pending = Some((start, len + size));
+            }
+            _ => {
+                self.flush(mapper)?;
+                self.pending = Some((phys_addr, size));
+            }
+        }
+
+        if self.offset + self.pending.map_or(0, |(_, len)| len) == self.data.len() {
+            self.flush(mapper)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for CopyFromWalker<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// Maps the page and copies the data to the buffer.
-    fn handle_pte(&mut self, mapper: &Mapper, pte_type: PteType, range: Range<usize>, pte: &u64) -> Result<(), Error> {
+    fn handle_pte(&mut self, mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
         let level = match pte_type {
-            PteType::Page(level) => level,
+            PteType::Page(level, _) => level,
             _ => return Ok(()),
         };
 
-        let level = &self.format.levels[level];
-
-        if !level.is_present(*pte) {
-            return Err(Mapper::PAGE_NOT_PRESENT);
+        if !self.format.levels[level].is_present(*pte) {
+            return Ok(());
         }
 
         // Get the physical address of the page.
-        let phys_addr = *pte & self.format.physical_mask;
+        let phys_addr = self.format.pte_to_phys(*pte);
 
         // Get the page offset.
-        let offset = (range.start & (level.page_size() - 1)) as u64;
+        let offset = self.format.page_offset(range.start, level);
 
-        // Determine how many bytes to copy.
-        let size = (self.data.len() - self.offset).min(level.page_size());
+        // Determine how many bytes to copy, i.e. the remainder of the page or the remainder of the
+        // buffer, whichever is smaller. This accounts for copies that start or end in the middle
+        // of a page.
+        let remaining = self.data.len() - self.offset - self.pending.map_or(0, |(_, len)| len);
+        let size = remaining.min(self.format.levels[level].page_size() - offset);
 
-        // Copy the bytes.
-        mapper.read_bytes(&mut self.data[self.offset..self.offset + size], phys_addr + offset)?;
-        self.offset += size;
+        // Copy the bytes, coalescing them with the preceding pages if they are physically
+        // contiguous.
+        self.push(mapper, phys_addr + offset as u64, size)
+    }
 
-        Ok(())
+    /// Copies the pending run and returns the error from [`PageTableMapper::page_not_present`] for
+    /// the start of the range as the data cannot be copied from a PTE hole.
+    fn handle_pte_hole(&mut self, mapper: &Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), Error> {
+        self.flush(mapper)?;
+
+        Err(Mapper::page_not_present(range.start))
     }
 }
 
 /// The [`CopyToWalker`] struct is an implementation of a [`crate::walker::PageWalker`] used to
-/// copy data to a given a virtual address range.
+/// copy data to a given a virtual address range. Like [`CopyFromWalker`], adjacent pages that are
+/// physically contiguous are copied using a single call to [`PageTableMapper::write_bytes`]. Use
+/// [`CopyToWalker::flush`] after the walk to ensure that all the data has been copied.
 ///
 /// This is used by the [`AddressSpace::copy_to`] method.
 ///
@@ -71,10 +143,14 @@ pub struct CopyToWalker<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
-    /// The offset within the buffer.
+    /// The offset within the buffer, i.e. the number of bytes copied so far.
     pub offset: usize,
     /// Storage for the data to copy.
     pub data: &'a [u8],
+    /// The run of physically contiguous bytes that has yet to be copied as the physical address
+    /// and the size, such that adjacent pages that are physically contiguous are copied using a
+    /// single call to [`PageTableMapper::write_bytes`].
+    pub pending: Option<(u64, usize)>,
     /// The page format.
     pub format: &'a PageFormat<'a>,
     /// A marker for Error.
@@ -83,36 +159,294 @@ where
     pub mapper: PhantomData<Mapper>,
 }
 
+impl<'a, Mapper, Error> CopyToWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Copies the pending run of physically contiguous bytes from the buffer. As the mapper may
+    /// write fewer bytes than requested, e.g. when the run spans physical pages that the mapper
+    /// cannot access at once, this keeps writing until the whole run has been copied.
+    pub fn flush(&mut self, mapper: &mut Mapper) -> Result<(), Error> {
+        let (phys_addr, size) = match self.pending.take() {
+            Some(pending) => pending,
+            _ => return Ok(()),
+        };
+
+        let end = self.offset + size;
+        let mut addr = phys_addr;
+
+        while self.offset < end {
+            let count = mapper.write_bytes(addr, &self.data[self.offset..end])?;
+
+            if count == 0 {
+                return Err(Mapper::PAGE_NOT_PRESENT);
+            }
+
+            self.offset += count;
+            addr += count as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the given bytes to the pending run if they are physically contiguous with it, or copies
+    /// the pending run and starts a new one otherwise. The pending run is copied as soon as it
+    /// reaches the end of the buffer.
+    fn push(&mut self, mapper: &mut Mapper, phys_addr: u64, size: usize) -> Result<(), Error> {
+        match self.pending {
+            Some((start, len)) if start + len as u64 == phys_addr => {
+                self.pending = Some((start, len + size));
+            }
+            _ => {
+                self.flush(mapper)?;
+                self.pending = Some((phys_addr, size));
+            }
+        }
+
+        if self.offset + self.pending.map_or(0, |(_, len)| len) == self.data.len() {
+            self.flush(mapper)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for CopyToWalker<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// Maps the page and copies the data from the buffer.
-    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let level = match pte_type {
+            PteType::Page(level, _) => level,
+            _ => return Ok(()),
+        };
+
+        if !self.format.levels[level].is_present(*pte) {
+            return Ok(());
+        }
+
+        // Get the physical address of the page.
+        let phys_addr = self.format.pte_to_phys(*pte);
+
+        // Get the page offset.
+        let offset = self.format.page_offset(range.start, level);
+
+        // Determine how many bytes to copy, i.e. the remainder of the page or the remainder of the
+        // buffer, whichever is smaller. This accounts for copies that start or end in the middle
+        // of a page.
+        let remaining = self.data.len() - self.offset - self.pending.map_or(0, |(_, len)| len);
+        let size = remaining.min(self.format.levels[level].page_size() - offset);
+
+        // Copy the bytes, coalescing them with the preceding pages if they are physically
+        // contiguous.
+        self.push(mapper, phys_addr + offset as u64, size)
+    }
+
+    /// Copies the pending run and returns the error from [`PageTableMapper::page_not_present`] for
+    /// the start of the range as the data cannot be copied to a PTE hole.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), Error> {
+        self.flush(mapper)?;
+
+        Err(Mapper::page_not_present(range.start))
+    }
+}
+
+/// The [`CopyToAllocWalker`] struct is an implementation of a [`crate::walker::PageWalkerMut`]
+/// used to copy data to a given virtual address range like [`CopyToWalker`], but allocates the
+/// pages and the underlying page tables for any PTE holes that it encounters. A freshly allocated
+/// page is zeroed before the data is copied, such that the part of the page that is not covered by
+/// the data is zeroed.
+///
+/// This is used by the [`AddressSpace::copy_to_alloc`] method.
+///
+/// [`AddressSpace::copy_to_alloc`]: `super::super::AddressSpace::copy_to_alloc`
+pub struct CopyToAllocWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The walker used to copy the data to the pages that are present.
+    pub walker: CopyToWalker<'a, Mapper, Error>,
+    /// The walker used to allocate the page tables.
+    pub allocator: PteAllocator<'a, Mapper, Error>,
+    /// The mask to set for newly allocated pages.
+    pub mask: u64,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for CopyToAllocWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Copies the data from the buffer to the page if it is present.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.walker.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Allocates the page table for PTE holes of page tables. For PTE holes of pages, this
+    /// allocates and zeroes the page, marks it as present and copies the data from the buffer.
+    /// Only empty PTE holes are filled. For PTE holes that are not empty, e.g. swap entries, this
+    /// returns the error from [`PageTableMapper::page_not_present`] like [`CopyToWalker`], such
+    /// that the information stored in the PTE is preserved.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if kind == HoleKind::NonZero {
+            return self.walker.handle_pte_hole(mapper, index, kind, range, location, pte);
+        }
+
+        if index != 0 {
+            return self.allocator.handle_pte_hole(mapper, index, kind, range, location, pte);
+        }
+
+        let format = self.walker.format;
+        let page = mapper.alloc_page()?;
+        mapper.zero_page(format.pte_size, page, format.levels[0].page_size())?;
+
+        // Mark the page as present and set the page mask.
+        *pte = format.make_pte(0, page, self.mask);
+
+        // Copy the data to the page now that it is present.
+        self.walker.handle_pte(mapper, PteType::Page(0, false), range, location, pte)
+    }
+}
+
+/// The [`FillWalker`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// fill a given virtual address range with a repeated byte.
+///
+/// This is used by the [`AddressSpace::fill`] method.
+///
+/// [`AddressSpace::fill`]: `super::super::AddressSpace::fill`
+pub struct FillWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The byte to fill the range with.
+    pub byte: u8,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for FillWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Maps the page and fills it with the byte.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
         let level = match pte_type {
-            PteType::Page(level) => level,
+            PteType::Page(level, _) => level,
             _ => return Ok(()),
         };
 
-        let level = &self.format.levels[level];
+        if !self.format.levels[level].is_present(*pte) {
+            return Ok(());
+        }
+
+        // Get the physical address of the page.
+        let phys_addr = self.format.pte_to_phys(*pte);
+
+        // Get the page offset.
+        let offset = self.format.page_offset(range.start, level) as u64;
+
+        // Fill the bytes in chunks. The range has already been clipped to the page and to the
+        // range that is being filled.
+        let bytes = [self.byte; 256];
+        let size = range.end - range.start;
+
+        for chunk in (0..size).step_by(bytes.len()) {
+            let chunk_size = (size - chunk).min(bytes.len());
+
+            mapper.write_bytes(phys_addr + offset + chunk as u64, &bytes[..chunk_size])?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the error from [`PageTableMapper::page_not_present`] for the start of the range as
+    /// a PTE hole cannot be filled.
+    fn handle_pte_hole(&mut self, _mapper: &mut Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), Error> {
+        Err(Mapper::page_not_present(range.start))
+    }
+}
 
-        if !level.is_present(*pte) {
-            return Err(Mapper::PAGE_NOT_PRESENT);
+/// The [`VerifyWalker`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// compare the data of a given virtual address range against a buffer.
+///
+/// This is used by the [`AddressSpace::verify`] method.
+///
+/// [`AddressSpace::verify`]: `super::super::AddressSpace::verify`
+pub struct VerifyWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The offset within the buffer, i.e. the number of bytes compared so far.
+    pub offset: usize,
+    /// The expected data.
+    pub expected: &'a [u8],
+    /// The offset of the first byte that differs, if any.
+    pub mismatch: Option<usize>,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for VerifyWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Maps the page and compares the data against the buffer, unless a difference has already
+    /// been found.
+    fn handle_pte(&mut self, mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        let level = match pte_type {
+            PteType::Page(level, _) => level,
+            _ => return Ok(()),
+        };
+
+        if self.mismatch.is_some() || !self.format.levels[level].is_present(*pte) {
+            return Ok(());
         }
 
         // Get the physical address of the page.
-        let phys_addr = *pte & self.format.physical_mask;
+        let phys_addr = self.format.pte_to_phys(*pte);
 
         // Get the page offset.
-        let offset = (range.start & (level.page_size() - 1)) as u64;
+        let offset = self.format.page_offset(range.start, level) as u64;
 
-        // Determine how many bytes to copy.
-        let size = (self.data.len() - self.offset).min(level.page_size());
+        // Compare the bytes in chunks. The range has already been clipped to the page and to the
+        // range that is being compared.
+        let mut bytes = [0u8; 256];
+        let size = range.end - range.start;
+
+        for chunk in (0..size).step_by(bytes.len()) {
+            let chunk_size = (size - chunk).min(bytes.len());
+            let start = self.offset + chunk;
+
+            mapper.read_bytes(&mut bytes[..chunk_size], phys_addr + offset + chunk as u64)?;
+
+            let expected = &self.expected[start..start + chunk_size];
+
+            if let Some(index) = bytes[..chunk_size].iter().zip(expected).position(|(a, b)| a != b) {
+                self.mismatch = Some(start + index);
+                return Ok(());
+            }
+        }
 
-        // Copy the bytes.
-        mapper.write_bytes(phys_addr + offset, &self.data[self.offset..self.offset + size])?;
         self.offset += size;
 
         Ok(())
     }
+
+    /// Returns the error from [`PageTableMapper::page_not_present`] for the start of the range as
+    /// the data of a PTE hole cannot be compared, unless a difference has already been found.
+    fn handle_pte_hole(&mut self, _mapper: &Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), Error> {
+        if self.mismatch.is_some() {
+            return Ok(());
+        }
+
+        Err(Mapper::page_not_present(range.start))
+    }
 }
@@ -0,0 +1,84 @@
+//! This modules implements the [`TableCounter`] and [`TableSizeCounter`] structs which are helpers
+//! used to count the page tables that have to be allocated to map a given range of virtual
+//! addresses, and to count the bytes consumed by the page tables backing a given range of virtual
+//! addresses.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{Descend, HoleKind, PageFormat, PteLocation};
+
+/// The [`TableCounter`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// count the page tables that would have to be allocated to map all the pages within a given
+/// virtual address range without modifying the page tables. This is used by the
+/// [`AddressSpace::count_required_tables`] method.
+///
+/// [`AddressSpace::count_required_tables`]: `super::super::AddressSpace::count_required_tables`
+pub struct TableCounter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The number of page tables that have to be allocated.
+    pub count: usize,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for TableCounter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Counts the page tables that have to be allocated for a PTE hole at a non-leaf level. As
+    /// the walker does not descend into PTE holes, the whole hierarchy of page tables below the
+    /// PTE hole is accounted for here. For every level below, this is the number of distinct page
+    /// tables covering the range, such that page tables shared by adjacent pages are only counted
+    /// once.
+    fn handle_pte_hole(&mut self, _mapper: &Mapper, index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), Error> {
+        for level in 0..index {
+            // The virtual address range covered by a single page table at this level is the page
+            // size of the level above.
+            let table_size = self.format.levels[level + 1].page_size();
+
+            self.count += (range.end - 1) / table_size - range.start / table_size + 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// The [`TableSizeCounter`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// count the bytes consumed by the page tables below the root page table that back a given virtual
+/// address range. This is used by the [`AddressSpace::table_overhead_bytes`] method.
+///
+/// [`AddressSpace::table_overhead_bytes`]: `super::super::AddressSpace::table_overhead_bytes`
+pub struct TableSizeCounter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The number of bytes consumed by the page tables.
+    pub bytes: usize,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for TableSizeCounter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Counts the size of the page table that the walk is about to descend into. As the walk
+    /// descends into every page table exactly once, a page table that is shared by multiple pages
+    /// within the range is only counted once.
+    fn handle_pre_table(&mut self, _mapper: &Mapper, index: usize, _range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<Descend, Error> {
+        self.bytes += self.format.levels[index - 1].entries() * self.format.pte_size;
+
+        Ok(Descend::Into)
+    }
+}
@@ -0,0 +1,47 @@
+//! This modules implements the [`PteCowMarker`] struct which is a helper used to prepare the pages
+//! for a given range of virtual addresses for copy-on-write.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PageFormat, PteLocation, PteType};
+
+/// The [`PteCowMarker`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// mark the writable pages of a given virtual address range as copy-on-write. This is used by the
+/// [`AddressSpace::mark_cow`] method.
+///
+/// [`AddressSpace::mark_cow`]: `super::super::AddressSpace::mark_cow`
+pub struct PteCowMarker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The mask of bits that mark a page as writable.
+    pub write_mask: u64,
+    /// The mask of (software) bits that mark a page as copy-on-write.
+    pub cow_mask: u64,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteCowMarker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Checks if the PTE points to a page that is present and writable, and if so, clears the
+    /// write bits and sets the copy-on-write bits.
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if let PteType::Page(level, _) = pte_type {
+            let level = &self.format.levels[level];
+
+            if level.is_present(*pte) && *pte & self.write_mask == self.write_mask {
+                *pte = (*pte & !self.write_mask) | self.cow_mask;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! This modules implements the [`ExtentWalker`] struct which is a helper used to determine the
+//! extent of the pages that are present within a given range of virtual addresses.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PageFormat, PteLocation, PteType};
+
+/// The [`ExtentWalker`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// determine the smallest virtual address range that contains all the pages that are present
+/// within a given virtual address range. This is used by the [`AddressSpace::mapped_extent`]
+/// method.
+///
+/// [`AddressSpace::mapped_extent`]: `super::super::AddressSpace::mapped_extent`
+pub struct ExtentWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Storage for the extent, if any pages are present.
+    pub extent: Option<Range<usize>>,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for ExtentWalker<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Extends the extent to include the virtual address range of the page, if the PTE points to
+    /// a page that is present.
+    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        let level = match pte_type {
+            PteType::Page(level, _) => level,
+            _ => return Ok(()),
+        };
+
+        if !self.format.levels[level].is_present(*pte) {
+            return Ok(());
+        }
+
+        // As the pages are walked in ascending order, the first page determines the start of the
+        // extent and the last page determines the end of the extent.
+        match &mut self.extent {
+            Some(extent) => extent.end = range.end,
+            extent => *extent = Some(range),
+        }
+
+        Ok(())
+    }
+}
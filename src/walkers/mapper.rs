@@ -4,49 +4,99 @@
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::PageFormat;
+use crate::{HoleKind, PageFormat, PteLocation};
 
 /// The [`PteMapper`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to map
 /// a physical address range and allocate the underlying page tables for a given virtual address
-/// range. This is used by the [`AddressSpace::map_range`] method.
+/// range. This is used by the [`AddressSpace::map_range`] and [`AddressSpace::map_range_to`]
+/// methods.
 ///
 /// [`AddressSpace::map_range`]: `super::super::AddressSpace::map_range`
+/// [`AddressSpace::map_range_to`]: `super::super::AddressSpace::map_range_to`
 pub struct PteMapper<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// The page format.
     pub format: &'a PageFormat<'a>,
+    /// The virtual address that maps to the physical base address. The virtual address of each
+    /// page relative to this address determines the offset into the physical address range.
+    pub virt_base: usize,
+    /// The physical base address to map the virtual address range to.
+    pub phys_base: u64,
     /// The mask to set for pages.
     pub mask: u64,
+    /// An optional closure that is given the virtual address and the page level of each page that
+    /// is being mapped, and that returns the mask to set for that page instead of the mask above.
+    pub flags: Option<&'a mut dyn FnMut(usize, usize) -> u64>,
+    /// Whether huge pages should be used where the virtual address range and the physical address
+    /// range are suitably aligned.
+    pub huge_pages: bool,
     /// A marker for Error.
     pub error: PhantomData<Error>,
     /// A marker for Mapper.
     pub mapper: PhantomData<Mapper>,
 }
 
+impl<'a, Mapper, Error> PteMapper<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Calculates the physical address of the page that maps the given virtual address, which is
+    /// the physical base address plus the offset of the page relative to the virtual base
+    /// address.
+    fn phys_addr(&self, virt_addr: usize) -> u64 {
+        let page_mask = self.format.levels[0].page_size() - 1;
+        let offset = (virt_addr & !page_mask).wrapping_sub(self.virt_base & !page_mask);
+
+        self.phys_base.wrapping_add(offset as u64)
+    }
+
+    /// Determines the mask to set for the page at the given virtual address and page level.
+    fn mask(&mut self, virt_addr: usize, index: usize) -> u64 {
+        match self.flags.as_mut() {
+            Some(f) => f(virt_addr, index),
+            _ => self.mask,
+        }
+    }
+}
+
 impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteMapper<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
-    /// Allocates the page or page table for the current level as we are handling PTE holes. If the
-    /// mask is set to None, then this function only allocates page tables.
-    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
+    /// Maps the page or allocates the page table for the current level as we are handling PTE
+    /// holes. The physical address of a page is the physical base address plus the offset of the
+    /// page relative to the virtual base address. If huge pages are enabled, the current level
+    /// supports huge pages and both the virtual address range and the physical address are
+    /// aligned to the page size of the current level, then this function maps a huge page instead
+    /// of allocating a page table, such that the walker does not descend any further.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
         let level = &self.format.levels[index];
+        let page_mask = level.page_size() - 1;
 
         match index {
             0 => {
                 // Mark the page as present and set the page mask.
-                *pte = level.present_bit.1 | self.mask;
-                self.mask = self.mask + level.page_size() as u64;
+                let mask = self.mask(range.start, index);
+                *pte = self.format.make_pte(index, self.phys_addr(range.start), mask);
+            }
+            _ if self.huge_pages &&
+                level.supports_huge_pages() &&
+                range.start & page_mask == 0 &&
+                range.end.wrapping_sub(range.start) == level.page_size() &&
+                self.phys_addr(range.start) & page_mask as u64 == 0 => {
+                // Mark the page as present, set the page mask and ensure it is a huge page.
+                let mask = self.mask(range.start, index);
+                *pte = self.format.make_huge_pte(index, self.phys_addr(range.start), mask);
             }
             _ => {
                 let page_table = mapper.alloc_page()?;
+                self.format.clear_page_table(mapper, index, page_table)?;
 
                 // Mark the page table as present, set the page table mask and ensure it is
                 // **not** a huge page.
-                *pte = page_table | level.present_bit.1 | level.page_table_mask |
-                    level.huge_page_bit.0 ^ level.huge_page_bit.1;
+                *pte = self.format.make_pte(index, page_table, level.page_table_mask);
             }
         }
 
@@ -0,0 +1,91 @@
+//! This modules implements the [`MappingCollector`] struct which is a helper used to collect the
+//! mappings of the pages that are present within a given range of virtual addresses.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PageFormat, PteLocation, PteType};
+
+/// Describes the mapping of a single page that is present.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    /// The virtual address range of the mapping.
+    pub virt: Range<usize>,
+    /// The physical address that the start of the virtual address range maps to.
+    pub phys: u64,
+    /// The page level of the page.
+    pub level: usize,
+    /// The PTE bits that do not refer to the physical address, i.e. the protection flags.
+    pub flags: u64,
+}
+
+/// The [`MappingCollector`] struct is an implementation of a [`crate::walker::PageWalker`] used to
+/// collect the mappings of the pages that are present within a given virtual address range in
+/// ascending order. This is used by the [`AddressSpace::iter_mappings`] and
+/// [`AddressSpace::iter_mappings_coalesced`] methods.
+///
+/// [`AddressSpace::iter_mappings`]: `super::super::AddressSpace::iter_mappings`
+/// [`AddressSpace::iter_mappings_coalesced`]: `super::super::AddressSpace::iter_mappings_coalesced`
+pub struct MappingCollector<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Storage for the collected mappings.
+    pub mappings: Vec<Mapping>,
+    /// Whether consecutive pages that are virtually and physically contiguous, and that share the
+    /// same page size and flags should be merged into a single mapping.
+    pub coalesce: bool,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalker<Mapper, Error> for MappingCollector<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Records the mapping of the page, if the PTE points to a page that is present. If
+    /// coalescing is enabled and the page directly follows the previous mapping, then this
+    /// function extends the previous mapping instead.
+    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        let level = match pte_type {
+            PteType::Page(level, _) => level,
+            _ => return Ok(()),
+        };
+
+        if !self.format.levels[level].is_present(*pte) {
+            return Ok(());
+        }
+
+        // Get the physical address that the start of the range maps to.
+        let phys = self.format.pte_to_phys(*pte) + self.format.page_offset(range.start, level) as u64;
+        let flags = *pte & !self.format.address_mask();
+
+        if self.coalesce {
+            if let Some(last) = self.mappings.last_mut() {
+                // Extend the previous mapping if the page is virtually and physically contiguous,
+                // and has the same page size and flags.
+                if last.virt.end == range.start &&
+                    last.phys + (last.virt.end - last.virt.start) as u64 == phys &&
+                    last.level == level &&
+                    last.flags == flags {
+                    last.virt.end = range.end;
+                    return Ok(());
+                }
+            }
+        }
+
+        self.mappings.push(Mapping {
+            virt: range,
+            phys,
+            level,
+            flags,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! This modules implements the [`PteMerger`] struct which is a helper used to merge pages back
+//! into a single huge page.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PageFormat, PteLocation, PteType};
+
+/// The [`PteMerger`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// merge the pages of the leaf page table covering a given virtual address into a single huge
+/// page. This is used by the [`AddressSpace::try_merge_huge_page`] method.
+///
+/// [`AddressSpace::try_merge_huge_page`]: `super::super::AddressSpace::try_merge_huge_page`
+pub struct PteMerger<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Whether the pages have been merged into a huge page.
+    pub merged: bool,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteMerger<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Checks if the PTE points to a leaf page table at a page level that supports huge pages. If
+    /// so, maps in the page table to check if all the pages are present, physically contiguous,
+    /// aligned to the huge page size and share the same protection flags. If all of these
+    /// conditions are met, then this function replaces the PTE with a huge page and frees the
+    /// page table.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if pte_type != PteType::PageTable(1) {
+            return Ok(());
+        }
+
+        let level = &self.format.levels[1];
+        let child = &self.format.levels[0];
+        let address_mask = self.format.address_mask();
+
+        if !level.supports_huge_pages() || !level.is_present(*pte) {
+            return Ok(());
+        }
+
+        let page_table = self.format.pte_to_phys(*pte);
+
+        // The first page determines the physical address and the protection flags of the huge
+        // page.
+        let first = self.format.read_pte(mapper, page_table)?;
+        let phys_addr = self.format.pte_to_phys(first);
+        let flags = first & !address_mask;
+
+        if !child.is_present(first) || phys_addr & (level.page_size() - 1) as u64 != 0 {
+            return Ok(());
+        }
+
+        // Check if all the pages are present, physically contiguous and share the same
+        // protection flags.
+        for i in 1..child.entries() {
+            let offset = (i * self.format.pte_size) as u64;
+            let entry = self.format.read_pte(mapper, page_table + offset)?;
+
+            if !child.is_present(entry) ||
+                self.format.pte_to_phys(entry) != phys_addr + (i * child.page_size()) as u64 ||
+                entry & !address_mask != flags {
+                return Ok(());
+            }
+        }
+
+        // Mark the huge page as present, set the protection flags and ensure it is a huge page.
+        let flags = flags & !child.present_bit.0;
+
+        *pte = self.format.make_huge_pte(1, phys_addr, flags);
+
+        mapper.free_page(page_table);
+        self.merged = true;
+
+        Ok(())
+    }
+}
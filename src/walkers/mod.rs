@@ -3,17 +3,57 @@
 //! [`AddressSpace`]: `super::AddressSpace`
 
 pub mod allocator;
+pub mod checker;
+pub mod cloner;
+pub mod consistency;
 pub mod copy;
+pub mod counter;
+pub mod cow;
+pub mod extent;
 pub mod mapper;
+#[cfg(feature = "alloc")]
+pub mod mappings;
+pub mod merger;
+pub mod populator;
+pub mod mutator;
 pub mod protector;
 pub mod reader;
+#[cfg(feature = "alloc")]
+pub mod recorder;
+pub mod remapper;
 pub mod remover;
+pub mod splitter;
+pub mod stats;
+pub mod tee;
+#[cfg(feature = "alloc")]
+pub mod transaction;
+pub mod visitor;
 pub mod writer;
 
-pub use allocator::PteAllocator;
-pub use copy::{CopyFromWalker, CopyToWalker};
+pub use allocator::{PteAllocator, PteFrameAllocator};
+pub use checker::HoleChecker;
+pub use cloner::PteCloner;
+pub use consistency::PteConsistencyFixer;
+pub use copy::{CopyFromWalker, CopyToAllocWalker, CopyToWalker, FillWalker, VerifyWalker};
+pub use counter::{TableCounter, TableSizeCounter};
+pub use cow::PteCowMarker;
+pub use extent::ExtentWalker;
 pub use mapper::PteMapper;
+#[cfg(feature = "alloc")]
+pub use mappings::{Mapping, MappingCollector};
+pub use merger::PteMerger;
+pub use populator::PtePopulator;
+pub use mutator::PteMutator;
 pub use protector::PteProtector;
 pub use reader::PteReader;
+#[cfg(feature = "alloc")]
+pub use recorder::PteChangeRecorder;
+pub use remapper::PteRemapper;
 pub use remover::{PteRemovalFlags, PteRemover};
+pub use splitter::PteSplitter;
+pub use stats::StatsWalker;
+pub use tee::Tee;
+#[cfg(feature = "alloc")]
+pub use transaction::PteTransaction;
+pub use visitor::PteVisitor;
 pub use writer::PteWriter;
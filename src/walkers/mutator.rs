@@ -0,0 +1,46 @@
+//! This modules implements the [`PteMutator`] struct which is a helper used to apply a user
+//! closure to the PTEs for a given range of virtual addresses.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PteLocation, PteType};
+
+/// The [`PteMutator`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// apply a user closure to the PTEs within a given virtual address range, which allows the user to
+/// modify the PTEs without having to implement [`crate::walker::PageWalkerMut`]. The closure is
+/// given the [`PteType`], the virtual address range and a mutable reference to the PTE. This is
+/// used by the [`AddressSpace::for_each_pte_mut`] method.
+///
+/// [`AddressSpace::for_each_pte_mut`]: `super::super::AddressSpace::for_each_pte_mut`
+pub struct PteMutator<Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(PteType, Range<usize>, &mut u64),
+{
+    /// The closure to apply to the PTEs.
+    pub f: F,
+    /// Whether the closure should also be applied to the PTEs of page tables rather than just the
+    /// PTEs of pages.
+    pub tables: bool,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<Mapper, Error, F> crate::PageWalkerMut<Mapper, Error> for PteMutator<Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(PteType, Range<usize>, &mut u64),
+{
+    /// Applies the closure to the PTE, if the PTE points to a page, or if the PTE points to a page
+    /// table and page tables are included.
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if pte_type.is_page() || self.tables {
+            (self.f)(pte_type, range, pte);
+        }
+
+        Ok(())
+    }
+}
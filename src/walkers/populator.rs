@@ -0,0 +1,50 @@
+//! This modules implements the [`PtePopulator`] struct which is a helper used to populate the page
+//! for a single virtual address on demand, e.g. to resolve a page fault.
+
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::walkers::PteAllocator;
+use crate::{HoleKind, PteLocation};
+
+/// The [`PtePopulator`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// populate the page for a single virtual address. It allocates the underlying page tables for any
+/// PTE holes, and invokes the user closure to supply the physical address and the flags of the
+/// page for the PTE hole at the leaf page level. The closure is not invoked if the page is already
+/// present. This is used by the [`AddressSpace::populate`] method.
+///
+/// [`AddressSpace::populate`]: `super::super::AddressSpace::populate`
+pub struct PtePopulator<'a, Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnOnce() -> Result<(u64, u64), Error>,
+{
+    /// The walker used to allocate the page tables.
+    pub allocator: PteAllocator<'a, Mapper, Error>,
+    /// The closure that supplies the physical address and the flags of the page. This is taken
+    /// once the closure has been invoked.
+    pub f: Option<F>,
+}
+
+impl<'a, Mapper, Error, F> crate::PageWalkerMut<Mapper, Error> for PtePopulator<'a, Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnOnce() -> Result<(u64, u64), Error>,
+{
+    /// Allocates the page table for PTE holes of page tables. For the PTE hole of the page, this
+    /// invokes the closure to get the physical address and the flags of the page, and marks the
+    /// page as present.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if index != 0 {
+            return self.allocator.handle_pte_hole(mapper, index, kind, range, location, pte);
+        }
+
+        if let Some(f) = self.f.take() {
+            let (phys_addr, flags) = f()?;
+
+            // Mark the page as present and set the flags.
+            *pte = self.allocator.format.make_pte(0, phys_addr, flags);
+        }
+
+        Ok(())
+    }
+}
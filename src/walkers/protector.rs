@@ -4,13 +4,14 @@
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::{PageFormat, PteType};
+use crate::{PageFormat, PteLocation, PteType};
 
 /// The [`PteProtector`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
 /// change the protection flags of a given virtual address range. This function is used by the
-/// [`AddressSpace::protect_range`] method.
+/// [`AddressSpace::protect_range`] and [`AddressSpace::protect_range_with_tables`] methods.
 ///
 /// [`AddressSpace::protect_range`]: `super::super::AddressSpace::protect_range`
+/// [`AddressSpace::protect_range_with_tables`]: `super::super::AddressSpace::protect_range_with_tables`
 pub struct PteProtector<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
@@ -18,6 +19,12 @@ where
     /// The protection flags that should be set. The first mask is the mask of bits that should be
     /// cleared. The second mask is the mask of bits that should be set.
     pub mask: (u64, u64),
+    /// The mask of sticky bits that the first mask never clears, e.g. the accessed and dirty bits,
+    /// such that clearing a broad mask does not lose the working set information.
+    pub sticky: u64,
+    /// Whether the bits of the second mask that are part of the page table mask should also be
+    /// set in the PTEs of the page tables mapping the pages.
+    pub tables: bool,
     /// The page format.
     pub format: &'a PageFormat<'a>,
     /// A marker for Error.
@@ -31,19 +38,19 @@ where
     Mapper: PageTableMapper<Error>,
 {
     /// Checks if the PTE points to a page that is present, and changes the protection flags if so.
-    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
-        let physical_mask = self.format.physical_mask;
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let address_mask = self.format.address_mask();
 
-        if let PteType::Page(level) = pte_type {
+        if let PteType::Page(level, _) = pte_type {
             let level = &self.format.levels[level];
 
             if level.is_present(*pte) {
                 // Ensure the mask does not modify the physical address bits, the huge page bits or the
-                // present bits.
+                // present bits. In addition, the sticky bits are never cleared.
                 let clear_mask = self.mask.0 &
-                    !(physical_mask | level.huge_page_bit.0 | level.present_bit.0);
+                    !(address_mask | level.huge_page_bit.0 | level.present_bit.0 | self.sticky);
                 let set_mask   = self.mask.1 &
-                    !(physical_mask | level.huge_page_bit.0 | level.present_bit.0);
+                    !(address_mask | level.huge_page_bit.0 | level.present_bit.0);
 
                 *pte = (*pte & !clear_mask) | set_mask;
             }
@@ -51,4 +58,17 @@ where
 
         Ok(())
     }
+
+    /// Sets the bits of the second mask that are part of the page table mask in the PTE of the
+    /// page table, if enabled. The bits are never cleared, as that would also affect the other
+    /// pages mapped by the page table.
+    fn handle_post_pte(&mut self, _mapper: &mut Mapper, index: usize, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let level = &self.format.levels[index];
+
+        if self.tables && level.is_present(*pte) {
+            *pte |= self.mask.1 & level.page_table_mask;
+        }
+
+        Ok(())
+    }
 }
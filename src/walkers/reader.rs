@@ -4,19 +4,23 @@
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::PteType;
+use crate::{PteLocation, PteType};
 
 /// The [`PteReader`] struct is an implementation of a [`crate::walker::PageWalker`] used to
-/// retrieve the PTE for a given virtual address, which is used by the [`AddressSpace::read_pte`]
-/// method.
+/// retrieve the PTE and the page level for a given virtual address. For single lookups,
+/// [`crate::PageFormat::resolve`] is cheaper, as used by the [`AddressSpace::read_pte`] and
+/// [`AddressSpace::read_pte_at`] methods.
 ///
 /// [`AddressSpace::read_pte`]: `super::super::AddressSpace::read_pte`
+/// [`AddressSpace::read_pte_at`]: `super::super::AddressSpace::read_pte_at`
 pub struct PteReader<Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// Storage for the retrieved PTE.
     pub pte: Option<u64>,
+    /// Storage for the page level at which the PTE was found.
+    pub level: usize,
     /// A marker for Error.
     pub error: PhantomData<Error>,
     /// A marker for Mapper.
@@ -27,10 +31,11 @@ impl<Mapper, Error> crate::PageWalker<Mapper, Error> for PteReader<Mapper, Error
 where
     Mapper: PageTableMapper<Error>,
 {
-    /// Stores the PTE of the page, if the virtual address resolves to a page.
-    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, _range: Range<usize>, pte: &u64) -> Result<(), Error> {
+    /// Stores the PTE and the page level of the page, if the virtual address resolves to a page.
+    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
         if pte_type.is_page() {
             self.pte = Some(*pte);
+            self.level = pte_type.level();
         }
 
         Ok(())
@@ -0,0 +1,67 @@
+//! This modules implements the [`PteChangeRecorder`] struct which is a helper used to keep track
+//! of the pages of which the PTEs have been changed by another walker.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{Descend, HoleKind, PteLocation, PteType};
+
+/// The [`PteChangeRecorder`] struct is an implementation of a [`crate::walker::PageWalkerMut`]
+/// that wraps another [`crate::walker::PageWalkerMut`] and forwards all the callbacks to it. In
+/// addition, it records the virtual address range of every page of which the wrapped walker
+/// changed the PTE in [`crate::walker::PageWalkerMut::handle_pte`], such that the caller can
+/// invalidate the TLB entries of exactly those pages. This is used by the
+/// [`AddressSpace::protect_range_report`] method.
+///
+/// [`AddressSpace::protect_range_report`]: `super::super::AddressSpace::protect_range_report`
+pub struct PteChangeRecorder<Walker, Mapper, Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// The wrapped walker.
+    pub walker: Walker,
+    /// The virtual address ranges of the pages of which the PTEs have been changed in ascending
+    /// order.
+    pub changed: Vec<Range<usize>>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<Walker, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteChangeRecorder<Walker, Mapper, Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Forwards the PTE to the wrapped walker, and records the virtual address range if the PTE
+    /// refers to a page and the wrapped walker changed the PTE.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let original = *pte;
+
+        self.walker.handle_pte(mapper, pte_type, range.clone(), location, pte)?;
+
+        if pte_type.is_page() && *pte != original {
+            self.changed.push(range);
+        }
+
+        Ok(())
+    }
+
+    /// Forwards the PTE hole to the wrapped walker.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.walker.handle_pte_hole(mapper, index, kind, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to the wrapped walker before descending into it.
+    fn handle_pre_table(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        self.walker.handle_pre_table(mapper, index, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to the wrapped walker.
+    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.walker.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
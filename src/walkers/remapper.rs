@@ -0,0 +1,57 @@
+//! This modules implements the [`PteRemapper`] struct which is a helper used to change the
+//! physical backing of the pages within a given range of virtual addresses.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{HoleKind, PageFormat, PteLocation, PteType};
+
+/// The [`PteRemapper`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// change the physical backing of the pages within a given virtual address range, while preserving
+/// their flags. This is used by the [`AddressSpace::remap_range`] method.
+///
+/// [`AddressSpace::remap_range`]: `super::super::AddressSpace::remap_range`
+pub struct PteRemapper<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// The virtual address that maps to the physical base address. The virtual address of each
+    /// page relative to this address determines the offset into the physical address range.
+    pub virt_base: usize,
+    /// The physical base address to remap the virtual address range to.
+    pub phys_base: u64,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteRemapper<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Replaces the physical address of the page with the physical base address plus the offset
+    /// of the page relative to the virtual base address, while keeping the flags intact. For huge
+    /// pages, the offset is calculated at the granularity of the huge page.
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let address_mask = self.format.address_mask();
+
+        if let PteType::Page(level, _) = pte_type {
+            let page_mask = self.format.levels[level].page_size() - 1;
+            let offset = (range.start & !page_mask).wrapping_sub(self.virt_base & !page_mask);
+            let phys_addr = self.format.phys_to_pte(self.phys_base.wrapping_add(offset as u64));
+
+            *pte = (*pte & !address_mask) | phys_addr;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the error from [`PageTableMapper::page_not_present`] for the start of the range as
+    /// only existing mappings can be remapped.
+    fn handle_pte_hole(&mut self, _mapper: &mut Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), Error> {
+        Err(Mapper::page_not_present(range.start))
+    }
+}
@@ -5,31 +5,55 @@ use bitflags::bitflags;
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::{PageFormat, PteType};
+use crate::format::{MAX_LEVELS, PTE_BATCH_SIZE};
+use crate::{PageFormat, PteLocation, PteType};
 
 bitflags! {
-    /// Flags to configure the behavior of the `[PteRemover`] walker.
+    /// Flags to configure the behavior of the [`PteRemover`] walker. The flags combine as follows:
+    ///
+    /// * The empty set of flags only unmaps the pages and keeps the page tables allocated.
+    /// * [`PteRemovalFlags::FREE_PAGES`] frees the pages, but keeps the page tables allocated,
+    ///   such that the range can be remapped quickly.
+    /// * [`PteRemovalFlags::FREE_PAGE_TABLES`] unmaps the pages and frees the page tables that
+    ///   have been fully cleared.
+    /// * [`PteRemovalFlags::FREE_PAGES`] and [`PteRemovalFlags::FREE_PAGE_TABLES`] free both the
+    ///   pages and the page tables that have been fully cleared.
+    ///
+    /// [`PteRemovalFlags::ZERO_PAGES`] only has an effect together with
+    /// [`PteRemovalFlags::FREE_PAGES`].
     pub struct PteRemovalFlags: u32 {
         /// Free the pages.
         const FREE_PAGES       = 1 << 0;
 
         /// Free the page tables if fully cleared.
         const FREE_PAGE_TABLES = 1 << 1;
+
+        /// Zero the contents of the pages before freeing them. See
+        /// [`AddressSpace::free_range_zeroed`].
+        ///
+        /// [`AddressSpace::free_range_zeroed`]: `crate::AddressSpace::free_range_zeroed`
+        const ZERO_PAGES       = 1 << 2;
     }
 }
 
 /// The [`PteRemover`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
 /// remove pages and the underlying page tables for a given virtual address range. This is used by
-/// the [`AddressSpace::unmap_range`] and [`AddressSpace::free_range`] methods.
+/// the [`AddressSpace::unmap_range`], [`AddressSpace::free_range`] and
+/// [`AddressSpace::free_range_zeroed`] methods.
 ///
 /// [`AddressSpace::unmap_range`]: `super::super::AddressSpace::unmap_range`
 /// [`AddressSpace::free_range`]: `super::super::AddressSpace::free_range`
+/// [`AddressSpace::free_range_zeroed`]: `super::super::AddressSpace::free_range_zeroed`
 pub struct PteRemover<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
     /// Flags to configure the behavior.
     pub flags: PteRemovalFlags,
+    /// The number of cleared PTEs in the page table that is currently being visited for every page
+    /// level. This is used to determine whether a page table has been fully cleared without having
+    /// to read back all of its PTEs.
+    pub cleared: [usize; MAX_LEVELS],
     /// The page format.
     pub format: &'a PageFormat<'a>,
     /// A marker for Error.
@@ -38,51 +62,123 @@ where
     pub mapper: PhantomData<Mapper>,
 }
 
+impl<'a, Mapper, Error> PteRemover<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Scrubs the contents of the page at the given physical address by writing zeroes over the
+    /// whole page, including huge pages, such that the freed memory does not leak to the next
+    /// user of the page.
+    fn zero_page(&self, mapper: &mut Mapper, pte_type: PteType, phys_addr: u64) -> Result<(), Error> {
+        let zeroes = [0u8; 256];
+        let page_size = self.format.page_size_at(pte_type);
+
+        for offset in (0..page_size).step_by(zeroes.len()) {
+            let size = (page_size - offset).min(zeroes.len());
+
+            mapper.write_bytes(phys_addr + offset as u64, &zeroes[..size])?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteRemover<'a, Mapper, Error>
 where
     Mapper: PageTableMapper<Error>,
 {
-    /// Frees the page if the PTE points to a present page and zeroes the PTE afterwards.
-    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
-        let physical_mask = self.format.physical_mask;
+    /// Frees the page if the PTE points to a present page and clears the PTE afterwards. If
+    /// [`PteRemovalFlags::ZERO_PAGES`] is set, the contents of the page are zeroed before the page
+    /// is freed. Keeps track of the number of cleared PTEs in the current page table.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let index = pte_type.level();
+        let level = &self.format.levels[index];
 
-        if let PteType::Page(level) = pte_type {
-            let level = &self.format.levels[level];
+        // Start counting again when we visit the first PTE of a page table within the range.
+        if location.index == 0 {
+            self.cleared[index] = 0;
+        }
 
-            if level.is_present(*pte) {
+        match pte_type {
+            PteType::Page(_, _) if level.is_present(*pte) => {
                 // Free the page and mark the PTE as non-present.
                 if self.flags.contains(PteRemovalFlags::FREE_PAGES) {
-                    mapper.free_page(physical_mask & *pte);
+                    if self.flags.contains(PteRemovalFlags::ZERO_PAGES) {
+                        self.zero_page(mapper, pte_type, self.format.pte_to_phys(*pte))?;
+                    }
+
+                    mapper.free_page(self.format.pte_to_phys(*pte));
                 }
 
-                *pte = 0;
+                *pte = level.empty_pte();
             }
+            // The page table will be counted upon recursion.
+            PteType::PageTable(_) if level.is_present(*pte) => return Ok(()),
+            _ => (),
+        }
+
+        if *pte == level.empty_pte() {
+            self.cleared[index] += 1;
         }
 
         Ok(())
     }
 
-    /// Maps in the page table to check if all entries have been cleared. If so, this function
-    /// frees the page table.
-    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
-        let level = &self.format.levels[index];
-        let physical_mask = self.format.physical_mask;
-        let phys_addr = physical_mask & *pte;
+    /// Checks if all entries of the page table have been cleared. If so, and if
+    /// [`PteRemovalFlags::FREE_PAGE_TABLES`] is set, this function frees the page table. If the
+    /// whole page table has been visited, then the number of cleared PTEs is used. Otherwise, the
+    /// page table is scanned using [`PageTableMapper::map_table`] if the mapper supports it, or
+    /// read once in batches to check the remaining entries otherwise.
+    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        // There is no need to check the page table if we are not going to free it.
+        if !self.flags.contains(PteRemovalFlags::FREE_PAGE_TABLES) {
+            return Ok(());
+        }
 
-        // Check if all entries have been cleared.
-        for i in 0..level.entries() {
-            let offset: u64 = (i * self.format.pte_size) as u64;
+        let level = &self.format.levels[index - 1];
+        let phys_addr = self.format.pte_to_phys(*pte);
 
-            if mapper.read_pte(self.format.pte_size, phys_addr + offset)? != 0 {
+        // Check if all entries have been cleared, i.e. whether they are all equal to the empty PTE.
+        // Scan the mapped page table if the mapper supports it, in which case the entries are
+        // compared in the byte order of the page tables.
+        let empty = level.empty_pte();
+        let table = match self.format.pte_size {
+            8 if self.cleared[index - 1] != level.entries() => mapper.map_table(phys_addr, level.entries())?,
+            _ => None,
+        };
+
+        if let Some(table) = table {
+            let table = table.get(..level.entries()).ok_or(Mapper::PTE_NOT_FOUND)?;
+            let empty = self.format.swap_pte(empty);
+
+            if table.iter().any(|&pte| pte != empty) {
                 return Ok(());
             }
-        }
+        } else if self.cleared[index - 1] != level.entries() {
+            let mut batch = [0u64; PTE_BATCH_SIZE];
+            let mut i = 0;
 
-        if self.flags.contains(PteRemovalFlags::FREE_PAGE_TABLES) {
-            mapper.free_page(physical_mask & *pte);
-            *pte = 0;
+            while i < level.entries() {
+                let count = (level.entries() - i).min(PTE_BATCH_SIZE);
+                let offset: u64 = (i * self.format.pte_size) as u64;
+                let count = self.format.read_ptes(mapper, phys_addr + offset, &mut batch[..count])?;
+
+                if count == 0 {
+                    return Err(Mapper::PTE_NOT_FOUND);
+                }
+
+                if batch[..count].iter().any(|&pte| pte != empty) {
+                    return Ok(());
+                }
+
+                i += count;
+            }
         }
 
+        mapper.free_page(phys_addr);
+        *pte = self.format.levels[index].empty_pte();
+        self.cleared[index] += 1;
+
         Ok(())
     }
 }
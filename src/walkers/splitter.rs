@@ -0,0 +1,82 @@
+//! This modules implements the [`PteSplitter`] struct which is a helper used to split a huge page
+//! into smaller pages.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{HoleKind, PageFormat, PteLocation, PteType};
+
+/// The [`PteSplitter`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
+/// split the huge pages covering a given virtual address range into pages of the next page level.
+/// As the walker descends into the newly allocated page tables, huge pages are split recursively
+/// until the virtual address range is mapped by the smallest page size. This is used by the
+/// [`AddressSpace::split_huge_page`] method.
+///
+/// [`AddressSpace::split_huge_page`]: `super::super::AddressSpace::split_huge_page`
+pub struct PteSplitter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteSplitter<'a, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Checks if the PTE points to a huge page that is present, and if so, allocates a page table
+    /// for the next page level, fills it with PTEs that map the consecutive physical pages of the
+    /// huge page using the same protection flags and replaces the PTE with one pointing to the
+    /// new page table.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let index = match pte_type {
+            PteType::Page(index, true) => index,
+            _ => return Ok(()),
+        };
+
+        let level = &self.format.levels[index];
+
+        if !level.is_present(*pte) {
+            return Ok(());
+        }
+
+        let child = &self.format.levels[index - 1];
+        let phys_addr = self.format.pte_to_phys(*pte);
+
+        // Extract the protection flags of the huge page.
+        let flags = *pte & !(self.format.address_mask() | level.present_bit.0 | level.huge_page_bit.0);
+
+        // Fill the new page table with PTEs that map the consecutive physical pages. If the next
+        // page level is not the leaf page level, then these PTEs are huge pages.
+        let page_table = mapper.alloc_page()?;
+
+        for i in 0..child.entries() {
+            let page = phys_addr + (i * child.page_size()) as u64;
+
+            let entry = match index - 1 {
+                0 => self.format.make_pte(0, page, flags),
+                _ => self.format.make_huge_pte(index - 1, page, flags),
+            };
+
+            let offset = (i * self.format.pte_size) as u64;
+            self.format.write_pte(mapper, page_table + offset, entry)?;
+        }
+
+        // Mark the page table as present, set the page table mask and ensure it is **not** a huge
+        // page.
+        *pte = self.format.make_pte(index, page_table, level.page_table_mask);
+
+        Ok(())
+    }
+
+    /// Returns the error from [`PageTableMapper::page_not_present`] for the start of the range as
+    /// only pages that are present can be split.
+    fn handle_pte_hole(&mut self, _mapper: &mut Mapper, _index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), Error> {
+        Err(Mapper::page_not_present(range.start))
+    }
+}
@@ -0,0 +1,126 @@
+//! This modules implements the [`StatsWalker`] struct which is a helper used to collect the
+//! statistics of a page table walk.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::format::WalkStats;
+use crate::{Descend, HoleKind, PteLocation, PteType};
+
+/// The [`StatsWalker`] struct is an implementation of both [`crate::walker::PageWalker`] and
+/// [`crate::walker::PageWalkerMut`] that forwards every callback to the given walker, and collects
+/// the statistics of the walk in the given [`WalkStats`] along the way. This is used by the
+/// [`crate::PageFormat::walk_with_stats`] and [`crate::PageFormat::walk_mut_with_stats`] methods.
+pub struct StatsWalker<'a, Walker, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The walker to forward the callbacks to.
+    pub walker: &'a mut Walker,
+    /// The statistics of the walk.
+    pub stats: &'a mut WalkStats,
+    /// The current depth, i.e. the number of page tables visited on the way down from the root
+    /// page table.
+    pub depth: usize,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Walker, Mapper, Error> StatsWalker<'a, Walker, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Wraps the given walker to collect the statistics in the given [`WalkStats`]. The root page
+    /// table should only be accounted for if the walk visits it, i.e. if the range is not empty.
+    pub fn new(walker: &'a mut Walker, stats: &'a mut WalkStats, root: bool) -> Self {
+        let depth = root as usize;
+
+        stats.tables_visited += depth;
+        stats.max_depth = stats.max_depth.max(depth);
+
+        Self {
+            walker,
+            stats,
+            depth,
+            error: PhantomData,
+            mapper: PhantomData,
+        }
+    }
+
+    /// Accounts for the page table that the walk is about to descend into, if any.
+    fn descend(&mut self, descend: Descend) {
+        if descend == Descend::Into {
+            self.depth += 1;
+            self.stats.tables_visited += 1;
+            self.stats.max_depth = self.stats.max_depth.max(self.depth);
+        }
+    }
+}
+
+impl<'a, Walker, Mapper, Error> crate::PageWalker<Mapper, Error> for StatsWalker<'a, Walker, Mapper, Error>
+where
+    Walker: crate::PageWalker<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Counts the PTE and forwards it to the walker.
+    fn handle_pte(&mut self, mapper: &Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.stats.ptes_read += 1;
+        self.walker.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Counts the PTE hole and forwards it to the walker.
+    fn handle_pte_hole(&mut self, mapper: &Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.stats.holes += 1;
+        self.walker.handle_pte_hole(mapper, index, kind, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to the walker, and counts the page table if the walk
+    /// descends into it.
+    fn handle_pre_table(&mut self, mapper: &Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        let descend = self.walker.handle_pre_table(mapper, index, range, location, pte)?;
+        self.descend(descend);
+
+        Ok(descend)
+    }
+
+    /// Forwards the PTE of the page table to the walker upon returning from the page table.
+    fn handle_post_pte(&mut self, mapper: &Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.depth -= 1;
+        self.walker.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
+
+impl<'a, Walker, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for StatsWalker<'a, Walker, Mapper, Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Counts the PTE and forwards it to the walker.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.stats.ptes_read += 1;
+        self.walker.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Counts the PTE hole and forwards it to the walker.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.stats.holes += 1;
+        self.walker.handle_pte_hole(mapper, index, kind, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to the walker, and counts the page table if the walk
+    /// descends into it.
+    fn handle_pre_table(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        let descend = self.walker.handle_pre_table(mapper, index, range, location, pte)?;
+        self.descend(descend);
+
+        Ok(descend)
+    }
+
+    /// Forwards the PTE of the page table to the walker upon returning from the page table.
+    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.depth -= 1;
+        self.walker.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
@@ -0,0 +1,125 @@
+//! This modules implements the [`Tee`] struct which is a helper used to run two walkers in a
+//! single page table walk.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{Descend, HoleKind, PteLocation, PteType};
+
+/// The [`Tee`] struct is an implementation of both [`crate::walker::PageWalker`] and
+/// [`crate::walker::PageWalkerMut`] that composes two walkers by forwarding every callback to the
+/// first walker and then to the second walker. This allows a single page table walk to feed
+/// multiple analyses, rather than walking the page tables once per analysis.
+///
+/// If the first walker returns an error, the callback is not forwarded to the second walker and
+/// the error is returned, i.e. the first error wins. The walk descends into a page table if either
+/// of the walkers wants to descend into it, such that a walker that skips a page table does not
+/// prevent the other walker from visiting it. For [`crate::walker::PageWalkerMut`], the second
+/// walker observes the changes that the first walker made to the PTE.
+pub struct Tee<First, Second, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// The first walker.
+    pub first: First,
+    /// The second walker.
+    pub second: Second,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<First, Second, Mapper, Error> Tee<First, Second, Mapper, Error>
+where
+    Mapper: PageTableMapper<Error>,
+{
+    /// Composes the given walkers.
+    pub fn new(first: First, second: Second) -> Self {
+        Self {
+            first,
+            second,
+            error: PhantomData,
+            mapper: PhantomData,
+        }
+    }
+
+    /// Unwraps the composed walkers.
+    pub fn into_inner(self) -> (First, Second) {
+        (self.first, self.second)
+    }
+}
+
+impl<First, Second, Mapper, Error> crate::PageWalker<Mapper, Error> for Tee<First, Second, Mapper, Error>
+where
+    First: crate::PageWalker<Mapper, Error>,
+    Second: crate::PageWalker<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Forwards the PTE to both walkers.
+    fn handle_pte(&mut self, mapper: &Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.first.handle_pte(mapper, pte_type, range.clone(), location, pte)?;
+        self.second.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Forwards the PTE hole to both walkers.
+    fn handle_pte_hole(&mut self, mapper: &Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.first.handle_pte_hole(mapper, index, kind, range.clone(), location, pte)?;
+        self.second.handle_pte_hole(mapper, index, kind, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to both walkers, and descends into the page table if
+    /// either of the walkers wants to.
+    fn handle_pre_table(&mut self, mapper: &Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        let first = self.first.handle_pre_table(mapper, index, range.clone(), location, pte)?;
+        let second = self.second.handle_pre_table(mapper, index, range, location, pte)?;
+
+        match (first, second) {
+            (Descend::Skip, Descend::Skip) => Ok(Descend::Skip),
+            _ => Ok(Descend::Into),
+        }
+    }
+
+    /// Forwards the PTE of the page table to both walkers.
+    fn handle_post_pte(&mut self, mapper: &Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), Error> {
+        self.first.handle_post_pte(mapper, index, range.clone(), location, pte)?;
+        self.second.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
+
+impl<First, Second, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for Tee<First, Second, Mapper, Error>
+where
+    First: crate::PageWalkerMut<Mapper, Error>,
+    Second: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Forwards the PTE to both walkers.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.first.handle_pte(mapper, pte_type, range.clone(), location, pte)?;
+        self.second.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Forwards the PTE hole to both walkers.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.first.handle_pte_hole(mapper, index, kind, range.clone(), location, pte)?;
+        self.second.handle_pte_hole(mapper, index, kind, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to both walkers, and descends into the page table if
+    /// either of the walkers wants to.
+    fn handle_pre_table(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        let first = self.first.handle_pre_table(mapper, index, range.clone(), location, pte)?;
+        let second = self.second.handle_pre_table(mapper, index, range, location, pte)?;
+
+        match (first, second) {
+            (Descend::Skip, Descend::Skip) => Ok(Descend::Skip),
+            _ => Ok(Descend::Into),
+        }
+    }
+
+    /// Forwards the PTE of the page table to both walkers.
+    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.first.handle_post_pte(mapper, index, range.clone(), location, pte)?;
+        self.second.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
@@ -0,0 +1,69 @@
+//! This modules implements the [`PteTransaction`] struct which is a helper used to keep track of
+//! the PTE holes filled by another walker, such that the changes can be rolled back upon failure.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{Descend, HoleKind, PageFormat, PteLocation, PteType};
+
+/// The [`PteTransaction`] struct is an implementation of a [`crate::walker::PageWalkerMut`] that
+/// wraps another [`crate::walker::PageWalkerMut`] and forwards all the callbacks to it. In
+/// addition, it records every PTE hole that the wrapped walker fills in a journal in the order in
+/// which the holes are filled, such that the changes can be rolled back if the walk fails. This is
+/// used by the [`AddressSpace::allocate_range`] and [`AddressSpace::map_range`] methods.
+///
+/// [`AddressSpace::allocate_range`]: `super::super::AddressSpace::allocate_range`
+/// [`AddressSpace::map_range`]: `super::super::AddressSpace::map_range`
+pub struct PteTransaction<'a, Walker, Mapper, Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// The wrapped walker.
+    pub walker: Walker,
+    /// The journal of filled PTE holes consisting of the page level, the virtual address and the
+    /// new PTE.
+    pub journal: Vec<(usize, usize, u64)>,
+    /// The page format.
+    pub format: &'a PageFormat<'a>,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<'a, Walker, Mapper, Error> crate::PageWalkerMut<Mapper, Error> for PteTransaction<'a, Walker, Mapper, Error>
+where
+    Walker: crate::PageWalkerMut<Mapper, Error>,
+    Mapper: PageTableMapper<Error>,
+{
+    /// Forwards the PTE to the wrapped walker.
+    fn handle_pte(&mut self, mapper: &mut Mapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.walker.handle_pte(mapper, pte_type, range, location, pte)
+    }
+
+    /// Forwards the PTE hole to the wrapped walker, and records the PTE in the journal if the
+    /// wrapped walker filled the PTE hole.
+    fn handle_pte_hole(&mut self, mapper: &mut Mapper, index: usize, kind: HoleKind, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        let start = range.start;
+
+        self.walker.handle_pte_hole(mapper, index, kind, range, location, pte)?;
+
+        if self.format.levels[index].is_present(*pte) {
+            self.journal.push((index, start, *pte));
+        }
+
+        Ok(())
+    }
+
+    /// Forwards the PTE of the page table to the wrapped walker before descending into it.
+    fn handle_pre_table(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<Descend, Error> {
+        self.walker.handle_pre_table(mapper, index, range, location, pte)
+    }
+
+    /// Forwards the PTE of the page table to the wrapped walker.
+    fn handle_post_pte(&mut self, mapper: &mut Mapper, index: usize, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        self.walker.handle_post_pte(mapper, index, range, location, pte)
+    }
+}
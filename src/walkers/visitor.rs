@@ -0,0 +1,40 @@
+//! This modules implements the [`PteVisitor`] struct which is a helper used to apply a user
+//! closure to the PTEs for a given range of virtual addresses without modifying them.
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use crate::address_space::PageTableMapper;
+use crate::{PteLocation, PteType};
+
+/// The [`PteVisitor`] struct is an implementation of a [`crate::walker::PageWalker`] used to apply
+/// a user closure to all the PTEs within a given virtual address range, i.e. both the PTEs of
+/// pages and the PTEs of page tables, which allows the user to inspect the PTEs without having to
+/// implement [`crate::walker::PageWalker`]. The closure is given the [`PteType`], the virtual
+/// address range and the PTE. This is used by the [`AddressSpace::for_each_pte`] method.
+///
+/// [`AddressSpace::for_each_pte`]: `super::super::AddressSpace::for_each_pte`
+pub struct PteVisitor<Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(PteType, Range<usize>, u64),
+{
+    /// The closure to apply to the PTEs.
+    pub f: F,
+    /// A marker for Error.
+    pub error: PhantomData<Error>,
+    /// A marker for Mapper.
+    pub mapper: PhantomData<Mapper>,
+}
+
+impl<Mapper, Error, F> crate::PageWalker<Mapper, Error> for PteVisitor<Mapper, Error, F>
+where
+    Mapper: PageTableMapper<Error>,
+    F: FnMut(PteType, Range<usize>, u64),
+{
+    /// Applies the closure to the PTE.
+    fn handle_pte(&mut self, _mapper: &Mapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), Error> {
+        (self.f)(pte_type, range, *pte);
+
+        Ok(())
+    }
+}
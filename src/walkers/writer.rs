@@ -4,7 +4,7 @@
 use core::marker::PhantomData;
 use core::ops::Range;
 use crate::address_space::PageTableMapper;
-use crate::PteType;
+use crate::{PteLocation, PteType};
 
 /// The [`PteWriter`] struct is an implementation of a [`crate::walker::PageWalkerMut`] used to
 /// store the PTE for a given virtual address, which is used by the [`AddressSpace::write_pte`]
@@ -28,8 +28,8 @@ where
     Mapper: PageTableMapper<Error>,
 {
     /// Store the PTE, if the virtual address resolves to a page.
-    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, pte: &mut u64) -> Result<(), Error> {
-        if let PteType::Page(_) = pte_type {
+    fn handle_pte(&mut self, _mapper: &mut Mapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), Error> {
+        if let PteType::Page(_, _) = pte_type {
             *pte = self.pte;
         }
 
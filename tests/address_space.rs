@@ -0,0 +1,214 @@
+//! Tests for constructing an [`AddressSpace`] and reading back its PTEs.
+
+mod common;
+
+use common::setup;
+use core::marker::PhantomData;
+use page_walker::arch::x86_64::*;
+use page_walker::walkers::PteReader;
+use page_walker::{
+    AddressSpace, FormatError, MemoryError, MemoryMapper, PageTableMapper, ReadOnlyAddressSpace,
+};
+
+/// An address space stored together with its page table mapper.
+struct Process {
+    space: AddressSpace<'static, MemoryMapper, MemoryError, MemoryMapper>,
+}
+
+#[test]
+fn owned_address_space_maps_and_reads_back() {
+    let (mapper, root) = setup();
+    let mut process = Process {
+        space: AddressSpace::new_owned(PAGE_FORMAT_4K_L4, mapper, root),
+    };
+
+    process.space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    // The address space can be moved to another thread together with its mapper.
+    let process = std::thread::spawn(move || {
+        assert_eq!(process.space.read_pte(0x2000).unwrap(), 0x8_1000 | PAGE_PRESENT | PAGE_WRITE);
+        process
+    }).join().unwrap();
+
+    // The mapper can be taken back out of the address space.
+    let mut mapper = process.space.into_mapper();
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    assert_eq!(space.translate(0x1123).unwrap(), Some(0x8_0123));
+}
+
+#[test]
+fn read_pte_at_returns_the_level_of_the_page() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    let huge = 0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE;
+    assert_eq!(space.read_pte_at(0x23_4567).unwrap(), (huge, 1));
+    assert_eq!(space.read_only().read_pte_at(0x23_4567).unwrap(), (huge, 1));
+    assert_eq!(space.read_pte(0x23_4567).unwrap(), huge);
+
+    assert_eq!(space.read_pte_at(0x1234).unwrap(), (0x8_0000 | PAGE_PRESENT | PAGE_WRITE, 0));
+}
+
+#[test]
+fn is_mapped_for_pages_holes_and_huge_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+
+    assert!(space.is_mapped(0x1000).unwrap());
+    assert!(space.is_mapped(0x1fff).unwrap());
+
+    // A hole within a page table that exists, and a hole above the leaf page level.
+    assert!(!space.is_mapped(0x2000).unwrap());
+    assert!(!space.is_mapped(0x4000_0000).unwrap());
+
+    assert!(space.is_mapped(0x20_0000).unwrap());
+    assert!(space.is_mapped(0x3f_ffff).unwrap());
+}
+
+#[test]
+fn resolve_matches_the_walk() {
+    let (mut mapper, root) = setup();
+    let mut walked = vec![];
+
+    {
+        let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+        space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+        space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE | PAGE_NX).unwrap();
+
+        for virt_addr in [0x1000, 0x2fff, 0x20_0000, 0x23_4567, 0x3f_ffff] {
+            let mut walker = PteReader {
+                pte: None,
+                level: 0,
+                error: PhantomData,
+                mapper: PhantomData,
+            };
+
+            space.walk(virt_addr..virt_addr + 1, &mut walker).unwrap();
+
+            // The fast path must agree with the full walk for both 4K and huge pages.
+            let entry = (walker.pte.unwrap(), walker.level);
+            assert_eq!(space.read_pte_at(virt_addr).unwrap(), entry);
+            walked.push((virt_addr, entry));
+        }
+    }
+
+    for (virt_addr, entry) in walked {
+        assert_eq!(PAGE_FORMAT_4K_L4.resolve(root, virt_addr, &mapper).unwrap(), Some(entry));
+    }
+}
+
+#[test]
+fn set_root_switches_the_page_table_hierarchy() {
+    let (mut mapper, first) = setup();
+    let second = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, first);
+
+    assert_eq!(space.root(), first);
+    assert_eq!(space.format().levels.len(), 4);
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    // Switch to the second page table hierarchy, which does not contain the first mapping.
+    space.set_root(second);
+    assert_eq!(space.root(), second);
+    assert_eq!(space.translate(0x1000).unwrap(), None);
+
+    space.map_range_to(0x1000..0x2000, 0x9_0000, PAGE_WRITE).unwrap();
+    assert_eq!(space.translate(0x1123).unwrap(), Some(0x9_0123));
+
+    // Switching back shows the original mapping.
+    space.set_root(first);
+    assert_eq!(space.translate(0x1123).unwrap(), Some(0x8_0123));
+}
+
+#[test]
+fn read_only_address_space_translates_concurrently() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0x1000..0x9000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    let space = ReadOnlyAddressSpace::new(PAGE_FORMAT_4K_L4, &mapper, root);
+
+    // Both threads share the same read-only view of the page tables.
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..2)
+            .map(|i| {
+                let space = &space;
+
+                scope.spawn(move || {
+                    for page in 0..8 {
+                        let virt_addr = 0x1000 + page * 0x1000 + i * 0x10;
+                        assert_eq!(space.translate(virt_addr).unwrap(), Some(0x8_0000 + (virt_addr as u64 - 0x1000)));
+                    }
+
+                    space.translate(0x9000).unwrap()
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), None);
+        }
+    });
+}
+
+#[test]
+fn try_new_rejects_invalid_roots() {
+    let (mut mapper, root) = setup();
+
+    assert_eq!(
+        AddressSpace::try_new(PAGE_FORMAT_4K_L4, &mut mapper, root + 0x800).err(),
+        Some(FormatError::UnalignedRoot),
+    );
+
+    // Bit 52 lies outside of the physical address bits of x86-64.
+    assert_eq!(
+        AddressSpace::try_new(PAGE_FORMAT_4K_L4, &mut mapper, root | 1 << 52).err(),
+        Some(FormatError::RootOutsidePhysicalMask),
+    );
+
+    let space = AddressSpace::try_new(PAGE_FORMAT_4K_L4, &mut mapper, root).unwrap();
+    assert_eq!(space.root(), root);
+}
+
+#[test]
+fn translate_path_of_a_hand_built_hierarchy() {
+    let format = &PAGE_FORMAT_4K_L4;
+    let (mut mapper, root) = setup();
+    let pdpt = mapper.alloc_page().unwrap();
+    let pd = mapper.alloc_page().unwrap();
+    let pt = mapper.alloc_page().unwrap();
+
+    // The virtual address uses index 1 of the PML4, index 2 of the PDPT, index 3 of the PD and
+    // index 4 of the PT. The fifth PDE maps a huge page instead.
+    let virt_addr = 1 << 39 | 2 << 30 | 3 << 21 | 4 << 12 | 0x123;
+    let huge_addr = 1 << 39 | 2 << 30 | 5 << 21;
+
+    format.write_pte(&mut mapper, root + 8, pdpt | PAGE_PRESENT | PAGE_WRITE).unwrap();
+    format.write_pte(&mut mapper, pdpt + 2 * 8, pd | PAGE_PRESENT | PAGE_WRITE).unwrap();
+    format.write_pte(&mut mapper, pd + 3 * 8, pt | PAGE_PRESENT | PAGE_WRITE).unwrap();
+    format.write_pte(&mut mapper, pd + 5 * 8, 0x4000_0000 | PAGE_PRESENT | PAGE_HUGE).unwrap();
+    format.write_pte(&mut mapper, pt + 4 * 8, 0x8_0000 | PAGE_PRESENT).unwrap();
+
+    let space = ReadOnlyAddressSpace::new(PAGE_FORMAT_4K_L4, &mapper, root);
+
+    // The path ends with the frame of the page.
+    let (path, count) = space.translate_path(virt_addr).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(path[..5], [root, pdpt, pd, pt, 0x8_0000]);
+
+    let (path, count) = space.translate_path(huge_addr).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(path[..4], [root, pdpt, pd, 0x4000_0000]);
+
+    // The path stops at the page table holding the PTE that is not present.
+    let (path, count) = space.translate_path(virt_addr + 0x1000).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(path[..4], [root, pdpt, pd, pt]);
+}
@@ -0,0 +1,123 @@
+//! Tests for allocating the pages of a range in the virtual address space.
+
+mod common;
+
+use common::{setup, TestMapper};
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, MemoryMapper, PageTableMapper};
+
+#[test]
+fn allocate_range_keeps_present_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x2000..0x3000, 0xf_0000, PAGE_USER).unwrap();
+    let middle = space.read_pte(0x2000).unwrap();
+
+    space.allocate_range(0x1000..0x4000, PAGE_WRITE).unwrap();
+
+    // The pre-existing page keeps its frame and flags.
+    assert_eq!(space.read_pte(0x2000).unwrap(), middle);
+
+    // The pages at the edges get new frames of their own.
+    let first = space.translate(0x1000).unwrap().unwrap();
+    let last = space.translate(0x3000).unwrap().unwrap();
+
+    assert_ne!(first, last);
+    assert!(first != 0xf_0000 && last != 0xf_0000);
+    assert_eq!(space.read_pte(0x1000).unwrap(), first | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.read_pte(0x3000).unwrap(), last | PAGE_PRESENT | PAGE_WRITE);
+}
+
+/// Creates a [`TestMapper`] with 4M of physical memory that hands out the given huge pages, and
+/// allocates the root page table.
+fn huge_setup(huge_pages: Vec<u64>) -> (TestMapper, u64) {
+    let mut mapper = TestMapper {
+        inner: MemoryMapper::new(4 << 20, 4096).unwrap(),
+        huge_pages,
+        ..TestMapper::new()
+    };
+    let root = mapper.alloc_page().unwrap();
+
+    (mapper, root)
+}
+
+#[test]
+fn allocate_range_huge_uses_huge_pages_when_aligned() {
+    let (mut mapper, root) = huge_setup(vec![0x4000_0000]);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range_huge(0x1f_f000..0x40_1000, PAGE_WRITE).unwrap();
+
+    // The 2M-aligned part of the range is backed by the huge page, the edges by small pages.
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap(), (0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE, 1));
+    assert_eq!(space.translate(0x3f_f123).unwrap(), Some(0x401f_f123));
+    assert_eq!(space.read_pte_at(0x1f_f000).unwrap().1, 0);
+    assert_eq!(space.read_pte_at(0x40_0000).unwrap().1, 0);
+    assert!(mapper.huge_pages.is_empty());
+}
+
+#[test]
+fn allocate_range_huge_falls_back_to_small_pages_when_unaligned() {
+    let (mut mapper, root) = huge_setup(vec![0x4000_0000]);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The range spans more than 2M, but does not cover any 2M-aligned page as a whole.
+    space.allocate_range_huge(0x20_1000..0x40_2000, PAGE_WRITE).unwrap();
+
+    for virt_addr in (0x20_1000..0x40_2000).step_by(0x1000) {
+        assert_eq!(space.read_pte_at(virt_addr).unwrap().1, 0);
+    }
+
+    assert_eq!(mapper.huge_pages, [0x4000_0000]);
+}
+
+#[test]
+fn allocate_range_huge_falls_back_to_small_pages_without_huge_pages() {
+    let (mut mapper, root) = huge_setup(vec![]);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range_huge(0x20_0000..0x40_0000, PAGE_WRITE).unwrap();
+
+    for virt_addr in (0x20_0000..0x40_0000).step_by(0x1000) {
+        assert_eq!(space.read_pte_at(virt_addr).unwrap().1, 0);
+    }
+}
+
+#[test]
+fn populate_maps_the_supplied_frame() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let pte = space.populate(0x1234_5678, || Ok((0x8_0000, PAGE_WRITE))).unwrap();
+    assert_eq!(pte, 0x8_0000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.translate(0x1234_5678).unwrap(), Some(0x8_0678));
+
+    // The closure is not invoked for a page that is already present.
+    let pte = space.populate(0x1234_5000, || panic!("the page is already present")).unwrap();
+    assert_eq!(pte, 0x8_0000 | PAGE_PRESENT | PAGE_WRITE);
+
+    // The error of the closure is returned and the page is left unmapped.
+    assert_eq!(space.populate(0x1234_6000, || Err(MemoryError::OutOfMemory)), Err(MemoryError::OutOfMemory));
+    assert!(!space.is_mapped(0x1234_6000).unwrap());
+}
+
+#[test]
+fn allocate_range_with_maps_the_supplied_frames() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Hand out the frames from a pool of non-contiguous frames in reverse order.
+    let mut pool = vec![0xe_0000, 0xc_4000, 0xa_8000];
+    let mut requested = vec![];
+
+    space.allocate_range_with(0x1000..0x4000, PAGE_WRITE, |virt_addr| {
+        requested.push(virt_addr);
+        pool.pop().ok_or(MemoryError::OutOfMemory)
+    }).unwrap();
+
+    assert_eq!(requested, [0x1000, 0x2000, 0x3000]);
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0xa_8000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.read_pte(0x2000).unwrap(), 0xc_4000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.read_pte(0x3000).unwrap(), 0xe_0000 | PAGE_PRESENT | PAGE_WRITE);
+}
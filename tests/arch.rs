@@ -0,0 +1,276 @@
+//! Tests for the page formats of the supported architectures.
+
+use core::ops::Range;
+use page_walker::arch::{aarch64, amdgpu, arm, default_format, mips, sparc, x86_64, Arch};
+use page_walker::{
+    AddressSpace, HoleKind, MemoryError, MemoryMapper, PageFormat, PageTableMapper, PageWalker,
+    PteLocation, PteType, ReadOnlyAddressSpace,
+};
+
+// The number of page levels of each format matches its name.
+const _: () = assert!(aarch64::PAGE_FORMAT_4K_L3.levels.len() == 3);
+const _: () = assert!(aarch64::PAGE_FORMAT_4K_L4.levels.len() == 4);
+const _: () = assert!(aarch64::PAGE_FORMAT_4K_L4_LPA.levels.len() == 4);
+const _: () = assert!(x86_64::PAGE_FORMAT_4K_L4.levels.len() == 4);
+const _: () = assert!(x86_64::PAGE_FORMAT_4K_L5.levels.len() == 5);
+
+#[test]
+fn aarch64_4k_l4_covers_48_bits() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(aarch64::PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // An address above 512G requires the fourth page level.
+    space.map_range_to(0x80_4000_0000..0x80_4000_1000, 0x8_0000, 0).unwrap();
+
+    assert_eq!(space.translate(0x80_4000_0123).unwrap(), Some(0x8_0123));
+    assert!(!space.is_mapped(0x4000_0000).unwrap());
+}
+
+#[test]
+fn aarch64_classifies_block_and_table_descriptors() {
+    let format = &aarch64::PAGE_FORMAT_4K_L4;
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let directory = mapper.alloc_page().unwrap();
+    let middle = mapper.alloc_page().unwrap();
+    let table = mapper.alloc_page().unwrap();
+
+    // Table descriptors have bits[1:0] = 0b11, block descriptors have bits[1:0] = 0b01 and 0b10
+    // is an invalid descriptor.
+    let block = 0x4000_0000 | 0b01;
+    assert!(format.levels[1].is_present(block));
+    assert!(format.levels[1].is_huge_page(block));
+    assert!(format.levels[1].is_present(middle | 0b11));
+    assert!(!format.levels[1].is_huge_page(middle | 0b11));
+    assert!(!format.levels[1].is_present(0x4000_0000 | 0b10));
+    assert!(!format.levels[1].is_huge_page(0x4000_0000 | 0b10));
+
+    // The first 2M are mapped by a block descriptor, the next 2M by a table descriptor pointing
+    // to a leaf page table of which the first PTE maps a page.
+    format.write_pte(&mut mapper, root, directory | 0b11).unwrap();
+    format.write_pte(&mut mapper, directory, middle | 0b11).unwrap();
+    format.write_pte(&mut mapper, middle, block).unwrap();
+    format.write_pte(&mut mapper, middle + 8, table | 0b11).unwrap();
+    format.write_pte(&mut mapper, table, 0x8_0000 | 0b11).unwrap();
+
+    let space = ReadOnlyAddressSpace::new(format.clone(), &mapper, root);
+    assert_eq!(space.translate(0x1234).unwrap(), Some(0x4000_1234));
+    assert_eq!(space.read_pte_at(0x1234).unwrap(), (block, 1));
+    assert_eq!(space.translate(0x20_0123).unwrap(), Some(0x8_0123));
+    assert_eq!(space.translate(0x20_1000).unwrap(), None);
+
+    let space = AddressSpace::new(format.clone(), &mut mapper, root);
+    let mut ptes = vec![];
+    space.for_each_pte(0..0x40_0000, |pte_type, range, pte| {
+        if format.levels[pte_type.level()].is_present(pte) {
+            ptes.push((pte_type, range));
+        }
+    }).unwrap();
+
+    assert_eq!(ptes, [
+        (PteType::PageTable(3), 0..0x40_0000),
+        (PteType::PageTable(2), 0..0x40_0000),
+        (PteType::Page(1, true), 0..0x20_0000),
+        (PteType::PageTable(1), 0x20_0000..0x40_0000),
+        (PteType::Page(0, false), 0x20_0000..0x20_1000),
+    ]);
+}
+
+#[test]
+fn aarch64_format_for_txsz() {
+    // A T0SZ of 25 gives a 39-bit virtual address space with three page levels.
+    let format = aarch64::format_for_txsz(25, aarch64::Granule::Size4K).unwrap();
+    assert_eq!(format.levels.len(), 3);
+    assert_eq!(format.levels[2].va_bits, 9);
+    assert_eq!(format.levels[2].shift_bits + format.levels[2].va_bits, 39);
+
+    // A T0SZ of 16 gives a 48-bit virtual address space with four page levels.
+    let format = aarch64::format_for_txsz(16, aarch64::Granule::Size4K).unwrap();
+    assert_eq!(format.levels.len(), 4);
+    assert_eq!(format.levels[3].va_bits, 9);
+    assert_eq!(format.levels[3].shift_bits + format.levels[3].va_bits, 48);
+
+    // A T0SZ of 28 gives a 36-bit virtual address space, where the root page table is only
+    // partially indexed.
+    let format = aarch64::format_for_txsz(28, aarch64::Granule::Size4K).unwrap();
+    assert_eq!(format.levels.len(), 3);
+    assert_eq!(format.levels[2].va_bits, 6);
+
+    assert!(aarch64::format_for_txsz(aarch64::MAX_TXSZ + 1, aarch64::Granule::Size4K).is_none());
+}
+
+#[test]
+fn aarch64_lpa_round_trips_frames_above_48_bits() {
+    let format = &aarch64::PAGE_FORMAT_4K_L4_LPA;
+
+    // The physical address bits 51:50 are stored in the PTE bits 9:8.
+    let phys_addr = 0x000f_1234_5678_9000;
+    let pte = format.phys_to_pte(phys_addr);
+    assert_eq!(pte, 0x0003_1234_5678_9000 | 0b11 << 8);
+    assert_eq!(format.pte_to_phys(pte), phys_addr);
+
+    // The relocated bits survive a mapping and a translation.
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(format.clone(), &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, phys_addr, 0).unwrap();
+    assert_eq!(format.pte_to_phys(space.read_pte(0x1000).unwrap()), phys_addr);
+    assert_eq!(space.translate(0x1234).unwrap(), Some(phys_addr | 0x234));
+
+    // With 64K pages, the physical address bits 51:48 are stored in the PTE bits 15:12 instead.
+    let format = PageFormat {
+        physical_mask: 0x0000_ffff_ffff_0000,
+        physical_high: Some(aarch64::PHYSICAL_HIGH_LPA_64K),
+        ..aarch64::PAGE_FORMAT_64K
+    };
+
+    let phys_addr = 0x000a_1234_5678_0000;
+    let pte = format.phys_to_pte(phys_addr);
+    assert_eq!(pte, 0x0000_1234_5678_0000 | 0xa << 12);
+    assert_eq!(format.pte_to_phys(pte), phys_addr);
+}
+
+#[test]
+fn mips_walk_hand_built_table() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let table = mapper.alloc_page().unwrap();
+
+    // The second root PTE covers 0x0040_0000..0x0080_0000 and points to the leaf page table, of
+    // which the fourth PTE maps a writeable page.
+    mips::PAGE_FORMAT_4K.write_pte(&mut mapper, root + 4, table | mips::PAGE_VALID).unwrap();
+    mips::PAGE_FORMAT_4K.write_pte(&mut mapper, table + 3 * 4, 0x8_0000 | mips::PAGE_VALID | mips::PAGE_DIRTY).unwrap();
+
+    let space = ReadOnlyAddressSpace::new(mips::PAGE_FORMAT_4K, &mapper, root);
+    assert_eq!(space.translate(0x0040_3123).unwrap(), Some(0x8_0123));
+    assert_eq!(space.read_pte_at(0x0040_3000).unwrap(), (0x8_0000 | mips::PAGE_VALID | mips::PAGE_DIRTY, 0));
+    assert_eq!(space.translate(0x0040_4000).unwrap(), None);
+    assert_eq!(space.translate(0x0080_3000).unwrap(), None);
+
+    let space = AddressSpace::new(mips::PAGE_FORMAT_4K, &mut mapper, root);
+    let mut ptes = vec![];
+    space.for_each_pte(0x0040_0000..0x0080_0000, |pte_type, range, pte| {
+        if mips::PAGE_FORMAT_4K.levels[pte_type.level()].is_present(pte) {
+            ptes.push((pte_type, range));
+        }
+    }).unwrap();
+
+    assert_eq!(ptes, [
+        (PteType::PageTable(1), 0x0040_0000..0x0080_0000),
+        (PteType::Page(0, false), 0x0040_3000..0x0040_4000),
+    ]);
+}
+
+#[test]
+fn sparc_walk_8k_and_4m_pages() {
+    let mut mapper = MemoryMapper::new(1 << 20, 8192).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(sparc::PAGE_FORMAT, &mut mapper, root);
+
+    let flags = sparc::PAGE_WRITE | sparc::PAGE_CP;
+    space.map_range_to(0x2000..0x4000, 0x10_0000, flags | sparc::PAGE_SZ8K).unwrap();
+    space.map_range_huge(0x80_0000..0xc0_0000, 0x4000_0000, flags | sparc::PAGE_SZ4M).unwrap();
+
+    // The 8K pages are mapped at the leaf page level.
+    assert_eq!(space.translate(0x3456).unwrap(), Some(0x10_1456));
+    assert_eq!(space.read_pte_at(0x2000).unwrap(), (0x10_0000 | sparc::PAGE_VALID | flags, 0));
+
+    // The 4M page is mapped at the second page level and has its size field set.
+    let (pte, level) = space.read_pte_at(0x9a_bcde).unwrap();
+    assert_eq!(level, 1);
+    assert_eq!(pte & sparc::PAGE_SZ_MASK, sparc::PAGE_SZ4M);
+    assert_eq!(pte & sparc::PAGE_HUGE, sparc::PAGE_HUGE);
+    assert_eq!(space.translate(0x9a_bcde).unwrap(), Some(0x401a_bcde));
+
+    assert!(!space.is_mapped(0x4000).unwrap());
+    assert!(!space.is_mapped(0xc0_0000).unwrap());
+}
+
+#[test]
+fn amdgpu_gfx9_resolves_gpu_addresses() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(amdgpu::PAGE_FORMAT_GFX9, &mut mapper, root);
+
+    let flags = amdgpu::PAGE_READABLE | amdgpu::PAGE_WRITEABLE;
+    space.map_range_to(0x7f_0000_0000..0x7f_0000_2000, 0x8_0000, flags).unwrap();
+    space.map_range_huge(0x7f_4000_0000..0x7f_4020_0000, 0x1_0000_0000, flags | amdgpu::PAGE_SYSTEM).unwrap();
+
+    assert_eq!(space.translate(0x7f_0000_1234).unwrap(), Some(0x8_1234));
+    assert_eq!(space.translate(0x7f_4012_3456).unwrap(), Some(0x1_0012_3456));
+    assert_eq!(space.read_pte_at(0x7f_4000_0000).unwrap().1, 1);
+    assert_eq!(space.read_pte(0x7f_4000_0000).unwrap() & amdgpu::PAGE_PDE_PTE, amdgpu::PAGE_PDE_PTE);
+    assert!(!space.is_mapped(0x7f_0000_2000).unwrap());
+
+    // The fragment field does not affect the translation.
+    space.protect_range(0x7f_0000_0000..0x7f_0000_1000, (0, 0x9 << 7)).unwrap();
+    assert_eq!(space.read_pte(0x7f_0000_0000).unwrap() & amdgpu::PAGE_FRAGMENT, 0x9 << 7);
+    assert_eq!(space.translate(0x7f_0000_0123).unwrap(), Some(0x8_0123));
+}
+
+/// Resolves a virtual address within an ARM supersection, which the short-descriptor format sees
+/// as a PTE hole at the first level.
+struct SupersectionResolver {
+    phys_addr: Option<u64>,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for SupersectionResolver {
+    fn handle_pte_hole(&mut self, _mapper: &MemoryMapper, index: usize, _kind: HoleKind, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), MemoryError> {
+        if index == 1 && arm::is_supersection(*pte) {
+            self.phys_addr = Some(arm::supersection_phys_addr(*pte, range.start));
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn arm_resolve_address_inside_supersection() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let format = &arm::PAGE_FORMAT_4K;
+
+    // The root page table spans several pages, which the bump allocator hands out contiguously.
+    let root = mapper.alloc_page().unwrap();
+    let size = format.levels[1].entries() * format.pte_size;
+
+    for _ in 1..size / 4096 {
+        mapper.alloc_page().unwrap();
+    }
+
+    // Map the 16M supersection at 0x1000_0000 to a physical address above 4G.
+    let ptes = arm::make_supersection_ptes(0x2_4000_0000, 0);
+    let first = format.levels[1].pte_index(0x1000_0000);
+
+    for (i, pte) in ptes.iter().enumerate() {
+        let phys_addr = root + ((first + i) * format.pte_size) as u64;
+        format.write_pte(&mut mapper, phys_addr, *pte).unwrap();
+    }
+
+    // Any of the replicated descriptors resolves to the same supersection.
+    for virt_addr in [0x1000_0000, 0x1012_3456, 0x10ff_ffff] {
+        let mut walker = SupersectionResolver { phys_addr: None };
+        format.walk(root, virt_addr..virt_addr + 1, &mut walker, &mapper).unwrap();
+
+        assert_eq!(walker.phys_addr, Some(0x2_4000_0000 + (virt_addr - 0x1000_0000) as u64));
+    }
+
+    // The descriptors next to the supersection are not part of it.
+    let next = format.read_pte(&mapper, root + ((first + 16) * format.pte_size) as u64).unwrap();
+    assert!(!arm::is_supersection(next));
+}
+
+#[test]
+fn default_format_of_each_architecture() {
+    let format = default_format(Arch::X86_64);
+    assert_eq!(format.levels.len(), 4);
+    assert_eq!(format.pte_size, 8);
+
+    assert_eq!(default_format(Arch::Aarch64).levels.len(), 4);
+
+    // Every default page format is valid.
+    for arch in Arch::ALL {
+        assert_eq!(default_format(arch).validate(), Ok(()), "{:?}", arch);
+    }
+}
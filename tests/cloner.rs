@@ -0,0 +1,105 @@
+//! Tests for cloning the page table hierarchy using [`AddressSpace::clone_into`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, PageTableMapper};
+
+#[test]
+fn clone_shares_pages_but_not_page_tables() {
+    let (mut mapper, root) = setup();
+    let (mut dest_mapper, dest_root) = setup();
+
+    // Offset the allocations of the destination, such that its page tables do not coincide with
+    // the ones of the source.
+    for _ in 0..8 {
+        dest_mapper.alloc_page().unwrap();
+    }
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.clone_into(dest_root, &mut dest_mapper, 0x1000..0x3000).unwrap();
+
+    let src_path = space.translate_path(0x1000).unwrap();
+
+    let mut dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    let dest_path = dest.translate_path(0x1000).unwrap();
+
+    // Both hierarchies map the same pages with the same flags.
+    for virt_addr in [0x1000, 0x2000] {
+        assert_eq!(dest.translate(virt_addr).unwrap(), space.translate(virt_addr).unwrap());
+        assert_eq!(dest.read_pte(virt_addr).unwrap(), space.read_pte(virt_addr).unwrap());
+    }
+
+    // The page tables below the root are distinct, whereas the pages are shared.
+    for index in 1..PAGE_FORMAT_4K_L4.levels.len() {
+        assert_ne!(dest_path.0[index], src_path.0[index], "page table {}", index);
+    }
+
+    assert_eq!(dest_path.0[4], src_path.0[4]);
+
+    // Unmapping the pages in the destination leaves the source intact.
+    dest.unmap_range(0x1000..0x3000).unwrap();
+    assert!(!dest.is_mapped(0x1000).unwrap());
+    assert!(space.is_mapped(0x1000).unwrap());
+}
+
+#[test]
+fn clone_copies_swap_entries() {
+    let (mut mapper, root) = setup();
+    let (mut dest_mapper, dest_root) = setup();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.swap_out(0x1000..0x2000, |_, _| 0x42 << 12).unwrap();
+
+    let swap_entry = space.read_pte(0x1000).unwrap();
+    assert!(!PAGE_FORMAT_4K_L4.levels[0].is_present(swap_entry));
+
+    space.clone_into(dest_root, &mut dest_mapper, 0x1000..0x3000).unwrap();
+
+    let dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    assert_eq!(dest.read_pte(0x1000).unwrap(), swap_entry);
+    assert_eq!(dest.translate(0x2000).unwrap(), space.translate(0x2000).unwrap());
+}
+
+#[test]
+fn clone_into_huge_page_is_rejected() {
+    let (mut mapper, root) = setup();
+    let (mut dest_mapper, dest_root) = setup();
+
+    let mut dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    dest.map_range_huge(0..0x20_0000, 0x20_0000, PAGE_WRITE).unwrap();
+    let huge_pte = dest.read_pte(0x1000).unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.clone_into(dest_root, &mut dest_mapper, 0x1000..0x2000), Err(MemoryError::PteNotFound));
+
+    // The huge page in the destination has been left untouched.
+    let dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    assert_eq!(dest.read_pte(0x1000).unwrap(), huge_pte);
+    assert_eq!(dest.translate(0x1000).unwrap(), Some(0x20_1000));
+}
+
+#[test]
+fn clone_huge_page_over_page_table_is_rejected() {
+    let (mut mapper, root) = setup();
+    let (mut dest_mapper, dest_root) = setup();
+
+    let mut dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    dest.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    let dest_page = dest.translate(0x1000).unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_huge(0..0x20_0000, 0x20_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.clone_into(dest_root, &mut dest_mapper, 0..0x20_0000), Err(MemoryError::PteNotFound));
+
+    // The page table in the destination, and the page it maps, have been left untouched.
+    let dest = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut dest_mapper, dest_root);
+    assert_eq!(dest.read_pte_at(0x1000).unwrap().1, 0);
+    assert_eq!(dest.translate(0x1000).unwrap(), dest_page);
+}
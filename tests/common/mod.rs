@@ -0,0 +1,233 @@
+//! Helpers shared by the tests.
+
+#![allow(dead_code)]
+
+use core::cell::Cell;
+use page_walker::arch::x86_64::{PAGE_HUGE, PAGE_PRESENT};
+use page_walker::{
+    Endian, MemoryError, MemoryMapper, PageFormat, PageLevel, PageTableMapper, ReadOnlyAddressSpace,
+};
+
+/// A [`PageTableMapper`] that wraps a [`MemoryMapper`] to count the calls made by the walkers,
+/// and that can be programmed to fail the allocation of pages after a given number of pages.
+pub struct TestMapper {
+    /// The inner mapper.
+    pub inner: MemoryMapper,
+    /// The number of calls to [`PageTableMapper::read_pte`].
+    pub reads: Cell<usize>,
+    /// The number of calls to [`PageTableMapper::read_ptes`].
+    pub batch_reads: Cell<usize>,
+    /// The number of calls to [`PageTableMapper::write_pte`].
+    pub writes: usize,
+    /// The number of calls to [`PageTableMapper::map_table`].
+    pub table_maps: Cell<usize>,
+    /// The number of calls to [`PageTableMapper::read_bytes`].
+    pub byte_reads: Cell<usize>,
+    /// The number of calls to [`PageTableMapper::write_bytes`].
+    pub byte_writes: usize,
+    /// Whether [`PageTableMapper::map_table`] is supported.
+    pub map_tables: bool,
+    /// The number of PTEs returned by [`PageTableMapper::map_table`] if it should return fewer
+    /// PTEs than requested.
+    pub short_tables: Option<usize>,
+    /// The error returned by [`PageTableMapper::map_table`], if any.
+    pub table_error: Option<MemoryError>,
+    /// The number of pages that may still be allocated, or `None` if unlimited.
+    pub allocs_left: Option<usize>,
+    /// Whether [`PageTableMapper::alloc_page`] should hand back pages filled with garbage.
+    pub dirty_pages: bool,
+    /// The physical addresses of the huge pages handed out by
+    /// [`PageTableMapper::alloc_huge_page`] in order.
+    pub huge_pages: Vec<u64>,
+    /// The physical addresses of the pages that have been freed in the order they were freed.
+    pub freed: Vec<u64>,
+    /// Whether the contents of each page were zero at the time it was freed, in the same order as
+    /// [`TestMapper::freed`].
+    pub freed_zeroed: Vec<bool>,
+}
+
+impl TestMapper {
+    /// Creates a new [`TestMapper`] with 1M of physical memory using 4K pages.
+    pub fn new() -> Self {
+        Self {
+            inner: MemoryMapper::new(1 << 20, 4096).unwrap(),
+            reads: Cell::new(0),
+            batch_reads: Cell::new(0),
+            writes: 0,
+            table_maps: Cell::new(0),
+            byte_reads: Cell::new(0),
+            byte_writes: 0,
+            map_tables: false,
+            short_tables: None,
+            table_error: None,
+            allocs_left: None,
+            dirty_pages: false,
+            huge_pages: Vec::new(),
+            freed: Vec::new(),
+            freed_zeroed: Vec::new(),
+        }
+    }
+
+    /// Resets the counters.
+    pub fn reset(&mut self) {
+        self.reads.set(0);
+        self.batch_reads.set(0);
+        self.writes = 0;
+        self.table_maps.set(0);
+        self.byte_reads.set(0);
+        self.byte_writes = 0;
+        self.freed.clear();
+        self.freed_zeroed.clear();
+    }
+
+    /// Returns the physical memory.
+    pub fn memory(&self) -> &[u8] {
+        &self.inner.memory
+    }
+}
+
+/// Creates a [`MemoryMapper`] with 1M of physical memory using 4K pages and allocates the root
+/// page table.
+pub fn setup() -> (MemoryMapper, u64) {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+
+    (mapper, root)
+}
+
+/// An x86-64 style page format of which the page tables only have the present bit set when
+/// allocated, such that the page tables do not grant any of the permissions of the pages.
+pub const PRESENT_TABLES: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel::new(12, 9)
+            .with_present(PAGE_PRESENT, PAGE_PRESENT),
+        PageLevel::new(21, 9)
+            .with_present(PAGE_PRESENT, PAGE_PRESENT)
+            .with_huge(PAGE_HUGE, PAGE_HUGE)
+            .with_table_mask(PAGE_PRESENT),
+        PageLevel::new(30, 9)
+            .with_present(PAGE_PRESENT, PAGE_PRESENT)
+            .with_huge(PAGE_HUGE, PAGE_HUGE)
+            .with_table_mask(PAGE_PRESENT),
+        PageLevel::new(39, 9)
+            .with_present(PAGE_PRESENT, PAGE_PRESENT)
+            .with_table_mask(PAGE_PRESENT),
+    ],
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: 8,
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// Returns the PTEs pointing to the page tables that map the given virtual address, from the root
+/// page level down to the page level above the page.
+pub fn table_ptes(format: &PageFormat, mapper: &MemoryMapper, root: u64, virt_addr: usize) -> Vec<u64> {
+    let space = ReadOnlyAddressSpace::new(format.clone(), mapper, root);
+    let (path, count) = space.translate_path(virt_addr).unwrap();
+
+    (1..count)
+        .map(|i| {
+            let level = &format.levels[format.levels.len() - i];
+            let phys_addr = path[i - 1] + (level.pte_index(virt_addr) * format.pte_size) as u64;
+
+            format.read_pte(mapper, phys_addr).unwrap()
+        })
+        .collect()
+}
+
+impl PageTableMapper<MemoryError> for TestMapper {
+    const PTE_NOT_FOUND: MemoryError = MemoryError::PteNotFound;
+    const PAGE_NOT_PRESENT: MemoryError = MemoryError::PageNotPresent;
+    const NOT_IMPLEMENTED: MemoryError = MemoryError::NotImplemented;
+
+    fn read_pte(&self, pte_size: usize, phys_addr: u64) -> Result<u64, MemoryError> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.read_pte(pte_size, phys_addr)
+    }
+
+    fn read_ptes(&self, pte_size: usize, phys_addr: u64, ptes: &mut [u64]) -> Result<usize, MemoryError> {
+        self.batch_reads.set(self.batch_reads.get() + 1);
+        self.inner.read_ptes(pte_size, phys_addr, ptes)
+    }
+
+    fn write_pte(&mut self, pte_size: usize, phys_addr: u64, value: u64) -> Result<(), MemoryError> {
+        self.writes += 1;
+        self.inner.write_pte(pte_size, phys_addr, value)
+    }
+
+    fn read_bytes(&self, bytes: &mut [u8], phys_addr: u64) -> Result<usize, MemoryError> {
+        self.byte_reads.set(self.byte_reads.get() + 1);
+        self.inner.read_bytes(bytes, phys_addr)
+    }
+
+    fn write_bytes(&mut self, phys_addr: u64, bytes: &[u8]) -> Result<usize, MemoryError> {
+        self.byte_writes += 1;
+        self.inner.write_bytes(phys_addr, bytes)
+    }
+
+    fn alloc_page(&mut self) -> Result<u64, MemoryError> {
+        match self.allocs_left {
+            Some(0) => return Err(MemoryError::OutOfMemory),
+            Some(ref mut left) => *left -= 1,
+            None => (),
+        }
+
+        let page = self.inner.alloc_page()?;
+
+        if self.dirty_pages {
+            let start = page as usize;
+            self.inner.memory[start..start + 4096].fill(0xff);
+        }
+
+        Ok(page)
+    }
+
+    fn alloc_huge_page(&mut self, _size: usize) -> Result<Option<u64>, MemoryError> {
+        match self.huge_pages.is_empty() {
+            true => Ok(None),
+            _ => Ok(Some(self.huge_pages.remove(0))),
+        }
+    }
+
+    fn map_table(&self, phys_addr: u64, entries: usize) -> Result<Option<&[u64]>, MemoryError> {
+        if !self.map_tables {
+            return Ok(None);
+        }
+
+        self.table_maps.set(self.table_maps.get() + 1);
+
+        if let Some(error) = self.table_error {
+            return Err(error);
+        }
+
+        let entries = self.short_tables.unwrap_or(entries);
+
+        let start = phys_addr as usize;
+        let bytes = self.inner.memory.get(start..start + entries * 8)
+            .ok_or(MemoryError::OutOfBounds)?;
+
+        // SAFETY: any bit pattern is a valid u64.
+        let (prefix, table, _) = unsafe { bytes.align_to::<u64>() };
+
+        if !prefix.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(table))
+    }
+
+    fn zero_page(&mut self, pte_size: usize, phys_addr: u64, size: usize) -> Result<(), MemoryError> {
+        self.inner.zero_page(pte_size, phys_addr, size)
+    }
+
+    fn free_page(&mut self, phys_addr: u64) {
+        let start = phys_addr as usize;
+        let zeroed = self.inner.memory.get(start..start + 4096)
+            .is_some_and(|page| page.iter().all(|&byte| byte == 0));
+
+        self.freed.push(phys_addr);
+        self.freed_zeroed.push(zeroed);
+        self.inner.free_page(phys_addr)
+    }
+}
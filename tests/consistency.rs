@@ -0,0 +1,32 @@
+//! Tests for [`AddressSpace::propagate_permissions`].
+
+mod common;
+
+use common::{setup, table_ptes, PRESENT_TABLES};
+use page_walker::arch::x86_64::*;
+use page_walker::AddressSpace;
+
+#[test]
+fn parent_gains_the_user_bit_of_a_user_leaf() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PRESENT_TABLES, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE | PAGE_USER).unwrap();
+    space.map_range_to(0x4000_0000..0x4000_1000, 0x9_0000, PAGE_WRITE).unwrap();
+
+    assert!(table_ptes(&PRESENT_TABLES, &mapper, root, 0x1000).iter().all(|pte| pte & PAGE_USER == 0));
+
+    let mut space = AddressSpace::new(PRESENT_TABLES, &mut mapper, root);
+    space.propagate_permissions(0..0x8000_0000, PAGE_USER).unwrap();
+
+    // The page tables above the user page now grant user access.
+    let ptes = table_ptes(&PRESENT_TABLES, &mapper, root, 0x1000);
+    assert_eq!(ptes.len(), 3);
+    assert!(ptes.iter().all(|pte| pte & PAGE_USER == PAGE_USER));
+
+    // The page directory of the supervisor page shares the root PTE with the user page, but is
+    // itself left supervisor-only.
+    let ptes = table_ptes(&PRESENT_TABLES, &mapper, root, 0x4000_0000);
+    assert_eq!(ptes[0] & PAGE_USER, PAGE_USER);
+    assert!(ptes[1..].iter().all(|pte| pte & PAGE_USER == 0));
+}
@@ -0,0 +1,104 @@
+//! Tests for the [`ContiguousMapper`] adapter.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{
+    AddressSpace, ContiguousMapper, ContiguousPhysMapper, MemoryError, PageTableMapper,
+    ReadOnlyAddressSpace,
+};
+
+/// A mapper that only provides read-only access to the physical memory.
+struct ReadOnlyMemory {
+    memory: Vec<u8>,
+}
+
+impl ContiguousPhysMapper<MemoryError> for ReadOnlyMemory {
+    const PTE_NOT_FOUND: MemoryError = MemoryError::PteNotFound;
+    const PAGE_NOT_PRESENT: MemoryError = MemoryError::PageNotPresent;
+    const NOT_IMPLEMENTED: MemoryError = MemoryError::NotImplemented;
+
+    fn phys_slice(&self) -> &[u8] {
+        &self.memory
+    }
+}
+
+/// A mapper that provides read and write access to the physical memory.
+struct WritableMemory {
+    memory: Vec<u8>,
+}
+
+impl ContiguousPhysMapper<MemoryError> for WritableMemory {
+    const PTE_NOT_FOUND: MemoryError = MemoryError::PteNotFound;
+    const PAGE_NOT_PRESENT: MemoryError = MemoryError::PageNotPresent;
+    const NOT_IMPLEMENTED: MemoryError = MemoryError::NotImplemented;
+
+    fn phys_slice(&self) -> &[u8] {
+        &self.memory
+    }
+
+    fn phys_slice_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.memory)
+    }
+}
+
+/// Maps two pages at `0x1000` filled with a pattern. Returns the physical memory and the root.
+fn physical_memory() -> (Vec<u8>, u64) {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.fill(0x1000..0x2000, 0xaa).unwrap();
+    space.fill(0x2000..0x3000, 0x55).unwrap();
+
+    (mapper.memory, root)
+}
+
+#[test]
+fn copy_from_with_only_phys_slice() {
+    let (memory, root) = physical_memory();
+    let mapper = ContiguousMapper::new(ReadOnlyMemory { memory });
+    let space = ReadOnlyAddressSpace::new(PAGE_FORMAT_4K_L4, &mapper, root);
+
+    let mut data = vec![0u8; 0x1000];
+    space.copy_from(&mut data, 0x1800).unwrap();
+
+    assert!(data[..0x800].iter().all(|&byte| byte == 0xaa));
+    assert!(data[0x800..].iter().all(|&byte| byte == 0x55));
+}
+
+#[test]
+fn write_without_phys_slice_mut_is_not_implemented() {
+    let (memory, root) = physical_memory();
+    let mut mapper = ContiguousMapper::new(ReadOnlyMemory { memory });
+
+    assert_eq!(mapper.write_bytes(0, &[0; 8]), Err(MemoryError::NotImplemented));
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    assert_eq!(space.copy_to(0x1000, &[0; 8]), Err((MemoryError::NotImplemented, 0x1000)));
+}
+
+#[test]
+fn copy_to_with_phys_slice_mut() {
+    let (memory, root) = physical_memory();
+    let mut mapper = ContiguousMapper::new(WritableMemory { memory });
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let data: Vec<u8> = (0..0x1000).map(|i| (i % 251) as u8).collect();
+    space.copy_to(0x1800, &data).unwrap();
+
+    let mut read_back = vec![0u8; data.len()];
+    space.copy_from(&mut read_back, 0x1800).unwrap();
+    assert_eq!(read_back, data);
+}
+
+#[test]
+fn access_outside_of_the_slice_is_not_present() {
+    let mut mapper = ContiguousMapper::new(WritableMemory { memory: vec![0; 0x1000] });
+    let mut bytes = [0u8; 8];
+
+    assert_eq!(mapper.read_bytes(&mut bytes, 0xffc), Err(MemoryError::PageNotPresent));
+    assert_eq!(mapper.write_bytes(0x1000, &bytes), Err(MemoryError::PageNotPresent));
+    assert_eq!(mapper.read_bytes(&mut bytes, u64::MAX), Err(MemoryError::PageNotPresent));
+}
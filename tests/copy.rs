@@ -0,0 +1,224 @@
+//! Tests for copying data from and to a virtual address range.
+
+mod common;
+
+use common::{setup, TestMapper};
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, PageTableMapper};
+
+/// Returns a buffer of the given size with a recognizable pattern.
+fn pattern(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 251) as u8 + 1).collect()
+}
+
+#[test]
+fn copy_to_alloc_populates_unmapped_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The data starts and ends in the middle of a page.
+    let data = pattern(0x2345);
+    space.copy_to_alloc(0x1_0800, &data, PAGE_WRITE).unwrap();
+
+    for virt_addr in (0x1_0000..0x1_3000).step_by(0x1000) {
+        assert!(space.is_mapped(virt_addr).unwrap());
+        assert_eq!(space.read_pte(virt_addr).unwrap() & PAGE_WRITE, PAGE_WRITE);
+    }
+
+    let mut read_back = vec![0u8; data.len()];
+    space.copy_from(&mut read_back, 0x1_0800).unwrap();
+    assert_eq!(read_back, data);
+
+    // The parts of the freshly allocated pages not covered by the data are zeroed.
+    let mut head = vec![0xffu8; 0x800];
+    space.copy_from(&mut head, 0x1_0000).unwrap();
+    assert!(head.iter().all(|&byte| byte == 0));
+
+    let mut tail = vec![0xffu8; 0x3000 - 0x800 - data.len()];
+    space.copy_from(&mut tail, 0x1_0800 + data.len()).unwrap();
+    assert!(tail.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn copy_to_alloc_keeps_existing_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    space.fill(0x1000..0x2000, 0xaa).unwrap();
+    let page = space.translate(0x1000).unwrap();
+
+    let data = pattern(0x1000);
+    space.copy_to_alloc(0x1800, &data, PAGE_WRITE).unwrap();
+
+    // The existing page is reused and the bytes before the copy are preserved.
+    assert_eq!(space.translate(0x1000).unwrap(), page);
+
+    let mut head = vec![0u8; 0x800];
+    space.copy_from(&mut head, 0x1000).unwrap();
+    assert!(head.iter().all(|&byte| byte == 0xaa));
+
+    let mut read_back = vec![0u8; data.len()];
+    space.copy_from(&mut read_back, 0x1800).unwrap();
+    assert_eq!(read_back, data);
+}
+
+/// Maps four pages at `0x1000` backed by distinct frames, copies the data to the given address
+/// and reads it back. Checks that the bytes around the copied range are left untouched.
+fn copy_round_trip(address: usize, size: usize) {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x5000, PAGE_WRITE).unwrap();
+    space.fill(0x1000..0x5000, 0xaa).unwrap();
+
+    let data = pattern(size);
+    space.copy_to(address, &data).unwrap();
+
+    let mut read_back = vec![0u8; size];
+    space.copy_from(&mut read_back, address).unwrap();
+    assert_eq!(read_back, data);
+
+    let mut all = vec![0u8; 0x4000];
+    space.copy_from(&mut all, 0x1000).unwrap();
+
+    let start = address - 0x1000;
+    assert!(all[..start].iter().all(|&byte| byte == 0xaa));
+    assert_eq!(&all[start..start + size], &data[..]);
+    assert!(all[start + size..].iter().all(|&byte| byte == 0xaa));
+}
+
+#[test]
+fn copy_starting_mid_page() {
+    copy_round_trip(0x1800, 0x2800);
+}
+
+#[test]
+fn copy_ending_mid_page() {
+    copy_round_trip(0x1000, 0x2800);
+}
+
+#[test]
+fn copy_starting_and_ending_mid_page() {
+    copy_round_trip(0x1800, 0x2345);
+    copy_round_trip(0x1800, 0x100);
+}
+
+#[test]
+fn copy_to_alloc_preserves_swap_entries() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.swap_out(0x2000..0x3000, |_, _| 0x42 << 12).unwrap();
+    let swap_entry = space.read_pte(0x2000).unwrap();
+
+    let data = pattern(0x1800);
+    assert_eq!(space.copy_to_alloc(0x1800, &data, PAGE_WRITE), Err(MemoryError::PageNotPresent));
+
+    // The swap entry has not been replaced by a freshly allocated page.
+    assert_eq!(space.read_pte(0x2000).unwrap(), swap_entry);
+}
+
+#[test]
+fn copy_reports_the_fault_address_of_a_hole() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The page at 0x2000 is a hole in between two mapped pages.
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    space.allocate_range(0x3000..0x4000, PAGE_WRITE).unwrap();
+
+    let mut data = vec![0u8; 0x2000];
+    let fault = Err((MemoryError::PageNotPresent, 0x2000));
+
+    // The copy faults at the base of the hole.
+    assert_eq!(space.copy_from(&mut data, 0x1800), fault);
+    assert_eq!(space.copy_to(0x1800, &data), fault);
+
+    // A copy that starts in the middle of the hole faults at the given address.
+    let fault = Err((MemoryError::PageNotPresent, 0x2800));
+
+    assert_eq!(space.copy_from(&mut data[..0x1000], 0x2800), fault);
+    assert_eq!(space.copy_to(0x2800, &data[..0x1000]), fault);
+}
+
+#[test]
+fn fill_two_pages_and_read_back() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x4000, PAGE_WRITE).unwrap();
+    space.copy_to(0x1000, &[0u8; 0x3000]).unwrap();
+
+    // Fill the two pages in the middle of the range, crossing a page boundary.
+    space.fill(0x1800..0x3800, 0xaa).unwrap();
+
+    let mut data = vec![0u8; 0x3000];
+    space.copy_from(&mut data, 0x1000).unwrap();
+
+    assert!(data[..0x800].iter().all(|&byte| byte == 0));
+    assert!(data[0x800..0x2800].iter().all(|&byte| byte == 0xaa));
+    assert!(data[0x2800..].iter().all(|&byte| byte == 0));
+
+    // Filling a range that includes a hole fails.
+    assert_eq!(space.fill(0x3800..0x4800, 0xaa), Err(MemoryError::PageNotPresent));
+}
+
+#[test]
+fn verify_reports_the_first_difference_across_a_page_boundary() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let data = pattern(0x2000);
+    space.allocate_range(0x1000..0x4000, PAGE_WRITE).unwrap();
+    space.copy_to(0x1800, &data).unwrap();
+
+    assert_eq!(space.verify(0x1800, &data), Ok(None));
+
+    // The first difference lies in the second page, after the page boundary at 0x2000.
+    let mut expected = data.clone();
+    expected[0x900] ^= 0xff;
+    expected[0x1000] ^= 0xff;
+    assert_eq!(space.verify(0x1800, &expected), Ok(Some(0x900)));
+
+    // A difference in the first page is reported before any later ones.
+    expected[0x7ff] ^= 0xff;
+    assert_eq!(space.verify(0x1800, &expected), Ok(Some(0x7ff)));
+
+    // Verifying a range that includes a hole fails.
+    assert_eq!(space.verify(0x3800, &[0u8; 0x1000]), Err(MemoryError::PageNotPresent));
+}
+
+#[test]
+fn copies_coalesce_physically_contiguous_pages() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+    let frames = mapper.inner.alloc_page().unwrap();
+    mapper.inner.alloc_page().unwrap();
+    mapper.inner.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0x1000..0x4000, frames, PAGE_WRITE).unwrap();
+
+    // The three pages are backed by physically contiguous frames.
+    let data = pattern(0x3000);
+    space.copy_to(0x1000, &data).unwrap();
+
+    let mut read_back = vec![0u8; data.len()];
+    space.copy_from(&mut read_back, 0x1000).unwrap();
+    assert_eq!(read_back, data);
+
+    assert_eq!(mapper.byte_writes, 1);
+    assert_eq!(mapper.byte_reads.get(), 1);
+
+    // Remapping the middle page elsewhere splits the copy into three calls.
+    let frame = mapper.inner.alloc_page().unwrap();
+    mapper.reset();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.write_pte(0x2000, frame | PAGE_PRESENT | PAGE_WRITE).unwrap();
+    space.copy_from(&mut read_back, 0x1000).unwrap();
+
+    assert_eq!(mapper.byte_reads.get(), 3);
+}
@@ -0,0 +1,94 @@
+//! Tests that count the calls made by the page table walkers to the [`PageTableMapper`].
+
+mod common;
+
+use common::TestMapper;
+use page_walker::arch::x86_64::*;
+use page_walker::format::PTE_BATCH_SIZE;
+use page_walker::{AddressSpace, PageTableMapper};
+
+/// The number of PTEs of each page table.
+const ENTRIES: usize = 512;
+
+#[test]
+fn mapping_contiguous_range_reads_every_pte_once() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    // Map a 2M range of 4K pages, which requires a page table at each of the three levels below
+    // the root.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0..0x20_0000, 0x2_0000_0000, PAGE_WRITE).unwrap();
+
+    // The PTEs of the page tables are read once, rather than once for every page they map.
+    assert_eq!(mapper.reads.get(), 3 + ENTRIES);
+    assert_eq!(mapper.writes, 3 + ENTRIES);
+}
+
+#[test]
+fn walk_reads_each_page_table_in_batches() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0..0x20_0000, 0x2_0000_0000, PAGE_WRITE).unwrap();
+
+    // A range of at most `PTE_BATCH_SIZE` pages is read using a single call per page table.
+    mapper.reset();
+
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    let mut count = 0;
+    space.for_each_pte(0..PTE_BATCH_SIZE * 0x1000, |_, _, _| count += 1).unwrap();
+
+    assert_eq!(count, 3 + PTE_BATCH_SIZE);
+    assert_eq!(mapper.reads.get(), 0);
+    assert_eq!(mapper.batch_reads.get(), 4);
+
+    // The whole page table of pages is read in batches of `PTE_BATCH_SIZE` PTEs.
+    mapper.reset();
+
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.for_each_pte(0..0x20_0000, |_, _, _| ()).unwrap();
+
+    assert_eq!(mapper.reads.get(), 0);
+    assert_eq!(mapper.batch_reads.get(), 3 + ENTRIES / PTE_BATCH_SIZE);
+}
+
+#[test]
+fn full_unmap_reads_linear_number_of_ptes() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0..0x20_0000, 0x2_0000_0000, PAGE_WRITE).unwrap();
+
+    mapper.reset();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.free_range(0..0x20_0000).unwrap();
+
+    // Every PTE in the range is read once. The page table of pages has been fully cleared, such
+    // that the number of cleared PTEs is used to decide that it is empty. The two page tables
+    // above only had one of their PTEs visited, and are therefore read once in batches.
+    assert_eq!(mapper.reads.get(), 3 + ENTRIES);
+    assert_eq!(mapper.batch_reads.get(), 2 * ENTRIES / PTE_BATCH_SIZE);
+    assert_eq!(mapper.freed.len(), 3 + ENTRIES);
+}
+
+#[test]
+fn noop_mutable_walk_writes_nothing() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+
+    mapper.reset();
+
+    // The walk visits every PTE of the range, including the PTE holes, but leaves them unchanged.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.for_each_pte_mut(0..0x20_0000, |_, _, _| ()).unwrap();
+
+    assert_eq!(mapper.reads.get(), 3 + ENTRIES);
+    assert_eq!(mapper.writes, 0);
+}
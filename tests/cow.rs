@@ -0,0 +1,28 @@
+//! Tests for [`AddressSpace::mark_cow`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::AddressSpace;
+
+/// A bit that is available for software use on x86-64, used to mark copy-on-write pages.
+const PAGE_COW: u64 = 1 << 9;
+
+#[test]
+fn mark_cow_only_affects_writable_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE | PAGE_USER).unwrap();
+    space.map_range_to(0x3000..0x4000, 0x8_2000, PAGE_USER).unwrap();
+
+    space.mark_cow(0x1000..0x4000, PAGE_WRITE, PAGE_COW).unwrap();
+
+    // The writable pages are now read-only and carry the copy-on-write marker.
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_USER | PAGE_COW);
+    assert_eq!(space.read_pte(0x2000).unwrap(), 0x8_1000 | PAGE_PRESENT | PAGE_USER | PAGE_COW);
+
+    // The read-only page is left untouched.
+    assert_eq!(space.read_pte(0x3000).unwrap(), 0x8_2000 | PAGE_PRESENT | PAGE_USER);
+}
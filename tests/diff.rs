@@ -0,0 +1,27 @@
+//! Tests for the [`diff()`] function.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{diff, AddressSpace, Difference, DifferenceKind, PageTableMapper};
+
+#[test]
+fn diff_reports_the_differing_leaf() {
+    let (mut mapper, root_a) = setup();
+    let root_b = mapper.alloc_page().unwrap();
+
+    // Both page table hierarchies map the same pages, except for the page at 0x2000.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root_a);
+    space.map_range_to(0x1000..0x4000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    space.set_root(root_b);
+    space.map_range_to(0x1000..0x4000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.protect_range(0x2000..0x3000, (PAGE_WRITE, 0)).unwrap();
+
+    let differences = diff(&PAGE_FORMAT_4K_L4, root_a, &mapper, root_b, &mapper, 0..0x4000_0000).unwrap();
+
+    assert_eq!(differences, [
+        Difference { virt: 0x2000..0x3000, kind: DifferenceKind::Different },
+    ]);
+}
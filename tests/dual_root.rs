@@ -0,0 +1,122 @@
+//! Tests for the [`DualRootAddressSpace`] with the AArch64 TTBR0 and TTBR1 page table hierarchies.
+
+use page_walker::arch::aarch64::*;
+use page_walker::{
+    AddressSpace, DualRootAddressSpace, Endian, MemoryError, MemoryMapper, PageFormat, PageLevel,
+    PageTableMapper,
+};
+
+/// The first virtual address of the upper half.
+const UPPER_HALF: usize = 1 << 63;
+
+/// A page format that covers the full 64-bit virtual address space, such that a range that
+/// straddles the split between the lower and the upper half can be mapped.
+const FORMAT_64: PageFormat<'static> = PageFormat {
+    levels: &[
+        PageLevel::new(12, 9).with_present(0b11, 0b11),
+        PageLevel::new(21, 9).with_present(0b11, 0b11),
+        PageLevel::new(30, 9).with_present(0b11, 0b11),
+        PageLevel::new(39, 9).with_present(0b11, 0b11),
+        PageLevel::new(48, 9).with_present(0b11, 0b11),
+        PageLevel::new(57, 7).with_present(0b11, 0b11),
+    ],
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: 8,
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// Creates a [`MemoryMapper`] and allocates the TTBR0 and TTBR1 root page tables.
+fn setup() -> (MemoryMapper, u64, u64) {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let ttbr0 = mapper.alloc_page().unwrap();
+    let ttbr1 = mapper.alloc_page().unwrap();
+
+    (mapper, ttbr0, ttbr1)
+}
+
+#[test]
+fn low_and_high_addresses_use_their_own_root() {
+    let (mut mapper, ttbr0, ttbr1) = setup();
+    let mut space = DualRootAddressSpace::new(PAGE_FORMAT_4K_L3, &mut mapper, ttbr0, ttbr1);
+
+    space.map_range_to(0x40_0000..0x40_1000, 0x8_0000, PAGE_AP_USER).unwrap();
+    space.map_range_to(0xffff_ff80_0080_0000..0xffff_ff80_0080_1000, 0x9_0000, 0).unwrap();
+
+    assert_eq!(space.translate(0x40_0123).unwrap(), Some(0x8_0123));
+    assert_eq!(space.translate(0xffff_ff80_0080_0123).unwrap(), Some(0x9_0123));
+
+    // Each mapping only exists in the page table hierarchy of its own half.
+    let lower = AddressSpace::new(PAGE_FORMAT_4K_L3, &mut mapper, ttbr0);
+    assert_eq!(lower.translate(0x40_0000).unwrap(), Some(0x8_0000));
+    assert_eq!(lower.translate(0xffff_ff80_0080_0000).unwrap(), None);
+
+    let upper = AddressSpace::new(PAGE_FORMAT_4K_L3, &mut mapper, ttbr1);
+    assert_eq!(upper.translate(0xffff_ff80_0080_0000).unwrap(), Some(0x9_0000));
+    assert_eq!(upper.translate(0x40_0000).unwrap(), None);
+}
+
+#[test]
+fn map_range_across_the_split_is_contiguous() {
+    let (mut mapper, ttbr0, ttbr1) = setup();
+    let mut space = DualRootAddressSpace::new(FORMAT_64, &mut mapper, ttbr0, ttbr1);
+
+    // The physical address is encoded in the mask.
+    space.map_range(UPPER_HALF - 0x2000..UPPER_HALF + 0x2000, 0x8_0000 | PAGE_AP_USER).unwrap();
+
+    assert_eq!(space.translate(UPPER_HALF - 0x2000).unwrap(), Some(0x8_0000));
+    assert_eq!(space.translate(UPPER_HALF - 0x1000).unwrap(), Some(0x8_1000));
+    assert_eq!(space.translate(UPPER_HALF).unwrap(), Some(0x8_2000));
+    assert_eq!(space.translate(UPPER_HALF + 0x1000).unwrap(), Some(0x8_3000));
+
+    assert_eq!(space.read_pte(UPPER_HALF).unwrap() & PAGE_AP_USER, PAGE_AP_USER);
+
+    // The pages of the lower half are only mapped by TTBR0, and the pages of the upper half only
+    // by TTBR1.
+    let lower = AddressSpace::new(FORMAT_64, &mut mapper, ttbr0);
+    assert_eq!(lower.translate(UPPER_HALF - 0x1000).unwrap(), Some(0x8_1000));
+    assert_eq!(lower.translate(UPPER_HALF).unwrap(), None);
+
+    let upper = AddressSpace::new(FORMAT_64, &mut mapper, ttbr1);
+    assert_eq!(upper.translate(UPPER_HALF - 0x1000).unwrap(), None);
+    assert_eq!(upper.translate(UPPER_HALF).unwrap(), Some(0x8_2000));
+}
+
+#[test]
+fn copy_across_the_split_uses_both_roots() {
+    let (mut mapper, ttbr0, ttbr1) = setup();
+    let mut space = DualRootAddressSpace::new(FORMAT_64, &mut mapper, ttbr0, ttbr1);
+
+    // The physical address is encoded in the mask.
+    space.map_range(UPPER_HALF - 0x1000..UPPER_HALF + 0x1000, 0x8_0000 | PAGE_AP_USER).unwrap();
+
+    let data: Vec<u8> = (0..0x1000).map(|i| (i % 251) as u8 + 1).collect();
+    space.copy_to(UPPER_HALF - 0x800, &data).unwrap();
+
+    let mut read_back = vec![0u8; data.len()];
+    space.copy_from(&mut read_back, UPPER_HALF - 0x800).unwrap();
+    assert_eq!(read_back, data);
+
+    // The head of the buffer has been copied through TTBR0 and the tail through TTBR1.
+    let mut head = vec![0u8; 0x800];
+    AddressSpace::new(FORMAT_64, &mut mapper, ttbr0).copy_from(&mut head, UPPER_HALF - 0x800).unwrap();
+    assert_eq!(head, data[..0x800]);
+
+    let mut tail = vec![0u8; 0x800];
+    AddressSpace::new(FORMAT_64, &mut mapper, ttbr1).copy_from(&mut tail, UPPER_HALF).unwrap();
+    assert_eq!(tail, data[0x800..]);
+}
+
+#[test]
+fn copy_across_the_split_reports_the_fault_address() {
+    let (mut mapper, ttbr0, ttbr1) = setup();
+    let mut space = DualRootAddressSpace::new(FORMAT_64, &mut mapper, ttbr0, ttbr1);
+
+    // Only the lower half is mapped.
+    space.map_range(UPPER_HALF - 0x1000..UPPER_HALF, 0x8_0000 | PAGE_AP_USER).unwrap();
+
+    let mut data = vec![0u8; 0x1000];
+    assert_eq!(space.copy_from(&mut data, UPPER_HALF - 0x800), Err((MemoryError::PageNotPresent, UPPER_HALF)));
+    assert_eq!(space.copy_to(UPPER_HALF - 0x800, &data), Err((MemoryError::PageNotPresent, UPPER_HALF)));
+}
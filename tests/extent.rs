@@ -0,0 +1,24 @@
+//! Tests for [`AddressSpace::mapped_extent`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::AddressSpace;
+
+#[test]
+fn mapped_extent_spans_the_first_to_the_last_page() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    assert_eq!(space.mapped_extent(0..0x4000_0000).unwrap(), None);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x10_0000..0x10_1000, 0x9_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.mapped_extent(0..0x4000_0000).unwrap(), Some(0x1000..0x10_1000));
+
+    // The extent is clamped to the given range.
+    assert_eq!(space.mapped_extent(0x1800..0x10_0800).unwrap(), Some(0x1800..0x10_0800));
+    assert_eq!(space.mapped_extent(0x2000..0x10_0000).unwrap(), None);
+}
@@ -0,0 +1,212 @@
+//! Tests for the helpers of [`PageFormat`] and [`PageLevel`].
+
+use page_walker::arch::x86_64::*;
+use page_walker::arch::{aarch64, amdgpu, arm, mips, sparc, x86};
+use page_walker::{
+    Endian, FormatError, MemoryMapper, PageFormat, PageLevel, PageTableMapper, ReadOnlyAddressSpace,
+};
+
+/// The page directory level of x86-64 built with the const builders.
+const PAGE_DIRECTORY: PageLevel = PageLevel::new(21, 9)
+    .with_present(PAGE_PRESENT, PAGE_PRESENT)
+    .with_huge(PAGE_HUGE, PAGE_HUGE)
+    .with_table_mask(PAGE_PRESENT | PAGE_WRITE | PAGE_USER);
+
+const _: () = assert!(PAGE_DIRECTORY.entries() == 512);
+const _: () = assert!(PAGE_DIRECTORY.page_size() == 0x20_0000);
+
+#[test]
+fn make_pte_for_leaf_table_and_huge_ptes() {
+    let format = &PAGE_FORMAT_4K_L4;
+
+    // The frame is masked into the physical mask, and the present bit is set.
+    assert_eq!(format.make_pte(0, 0x1234_5000, PAGE_WRITE), 0x1234_5000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(format.make_pte(0, 0xfff0_0000_1234_5fff, 0), 0x1234_5000 | PAGE_PRESENT);
+
+    // A PTE referring to a page table is never marked as a huge page.
+    assert_eq!(format.make_pte(1, 0x8_0000, PAGE_WRITE | PAGE_HUGE), 0x8_0000 | PAGE_PRESENT | PAGE_WRITE);
+
+    // A huge PTE has both the present bit and the huge bit set.
+    assert_eq!(format.make_huge_pte(1, 0x4000_0000, PAGE_WRITE), 0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE);
+    assert!(format.levels[1].is_huge_page(format.make_huge_pte(1, 0x4000_0000, 0)));
+    assert!(!format.levels[1].is_huge_page(format.make_pte(1, 0x4000_0000, 0)));
+}
+
+#[test]
+fn supports_huge_pages_for_every_bundled_format() {
+    let formats: &[(&str, PageFormat<'static>, &[bool])] = &[
+        ("aarch64 4K L3", aarch64::PAGE_FORMAT_4K_L3, &[false, true, true]),
+        ("aarch64 4K L4", aarch64::PAGE_FORMAT_4K_L4, &[false, true, true, false]),
+        ("aarch64 4K L4 LPA", aarch64::PAGE_FORMAT_4K_L4_LPA, &[false, true, true, false]),
+        ("aarch64 16K", aarch64::PAGE_FORMAT_16K, &[false, true, false, false]),
+        ("aarch64 64K", aarch64::PAGE_FORMAT_64K, &[false, true, false]),
+        ("amdgpu GFX9", amdgpu::PAGE_FORMAT_GFX9, &[false, true, true, false]),
+        ("arm 4K", arm::PAGE_FORMAT_4K, &[false, true]),
+        ("arm 4K PAE", arm::PAGE_FORMAT_4K_PAE, &[false, true, true]),
+        ("mips 4K", mips::PAGE_FORMAT_4K, &[false, false]),
+        ("sparc 8K", sparc::PAGE_FORMAT_8K, &[false, true, false]),
+        ("x86 4K", x86::PAGE_FORMAT_4K, &[false, true]),
+        ("x86 4K PAE", x86::PAGE_FORMAT_4K_PAE, &[false, true, false]),
+        ("x86-64 4K L4", PAGE_FORMAT_4K_L4, &[false, true, true, false]),
+        ("x86-64 4K L5", PAGE_FORMAT_4K_L5, &[false, true, true, false, false]),
+    ];
+
+    for (name, format, expected) in formats {
+        let supported: Vec<bool> = format.levels.iter()
+            .map(|level| level.supports_huge_pages())
+            .collect();
+
+        assert_eq!(&supported, expected, "{}", name);
+    }
+}
+
+#[test]
+fn set_present_sets_both_bits_of_arm_present_field() {
+    let level = &arm::PAGE_FORMAT_4K.levels[0];
+
+    // Only PTEs with both bits of the present field set are considered present.
+    assert!(!level.is_present(0x8_0000 | 0b01));
+    assert!(!level.is_present(0x8_0000 | 0b10));
+
+    // Marking a PTE as present sets both bits, regardless of which bits were already set.
+    for pte in [0x8_0000, 0x8_0000 | 0b01, 0x8_0000 | 0b10, 0x8_0000 | 0b11] {
+        assert_eq!(level.set_present(pte), 0x8_0000 | 0b11);
+        assert!(level.is_present(level.set_present(pte)));
+    }
+
+    assert_eq!(arm::PAGE_FORMAT_4K.make_pte(0, 0x8_0000, 0b01), 0x8_0000 | 0b11);
+}
+
+#[test]
+fn page_sizes_of_x86_64() {
+    let sizes: Vec<usize> = PAGE_FORMAT_4K_L4.page_sizes().collect();
+
+    assert_eq!(sizes, [0x1000, 0x20_0000, 0x4000_0000]);
+}
+
+#[test]
+fn level_names_of_x86_64_and_the_default() {
+    assert_eq!(PAGE_FORMAT_4K_L4.level_name(3), "PML4E");
+    assert_eq!(PAGE_FORMAT_4K_L4.level_name(0), "PTE");
+
+    // Formats without names fall back to the index of the page level.
+    assert_eq!(mips::PAGE_FORMAT_4K.level_name(1), "L1");
+}
+
+#[test]
+fn page_offsets_within_4k_2m_and_1g_pages() {
+    let format = &PAGE_FORMAT_4K_L4;
+    let addr = 0x7f12_3456_789a;
+
+    assert_eq!(format.page_offset(addr, 0), 0x89a);
+    assert_eq!(format.page_offset(addr, 1), 0x16_789a);
+    assert_eq!(format.page_offset(addr, 2), 0x3456_789a);
+
+    // Page-aligned addresses have no offset.
+    assert_eq!(format.page_offset(0x4000_0000, 2), 0);
+}
+
+#[test]
+fn walk_big_endian_table_on_any_host() {
+    let format = PageFormat { endian: Endian::Big, ..PAGE_FORMAT_4K_L4 };
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let pdpt = mapper.alloc_page().unwrap();
+    let pd = mapper.alloc_page().unwrap();
+    let pt = mapper.alloc_page().unwrap();
+
+    // Store the PTEs as big-endian bytes, like a page table dumped from a big-endian target.
+    let ptes = [
+        (root, pdpt | PAGE_PRESENT | PAGE_WRITE),
+        (pdpt, pd | PAGE_PRESENT | PAGE_WRITE),
+        (pd, pt | PAGE_PRESENT | PAGE_WRITE),
+        (pt + 3 * 8, 0x8_0000 | PAGE_PRESENT | PAGE_WRITE),
+    ];
+
+    for (phys_addr, pte) in ptes {
+        mapper.write_bytes(phys_addr, &pte.to_be_bytes()).unwrap();
+    }
+
+    let space = ReadOnlyAddressSpace::new(format.clone(), &mapper, root);
+    assert_eq!(space.translate(0x3123).unwrap(), Some(0x8_0123));
+    assert_eq!(space.read_pte(0x3000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.translate(0x4000).unwrap(), None);
+
+    // Writing a PTE stores it in big-endian byte order as well.
+    format.write_pte(&mut mapper, pt + 4 * 8, 0x9_0000 | PAGE_PRESENT).unwrap();
+
+    let mut bytes = [0u8; 8];
+    mapper.read_bytes(&mut bytes, pt + 4 * 8).unwrap();
+    assert_eq!(u64::from_be_bytes(bytes), 0x9_0000 | PAGE_PRESENT);
+
+    let space = ReadOnlyAddressSpace::new(format, &mapper, root);
+    assert_eq!(space.translate(0x4567).unwrap(), Some(0x9_0567));
+}
+
+#[test]
+fn const_page_level_matches_the_bundled_level() {
+    let level = &PAGE_FORMAT_4K_L4.levels[1];
+
+    assert_eq!(PAGE_DIRECTORY.entries(), 512);
+    assert_eq!(PAGE_DIRECTORY.page_size(), 0x20_0000);
+    assert_eq!(PAGE_DIRECTORY.shift_bits, level.shift_bits);
+    assert_eq!(PAGE_DIRECTORY.va_bits, level.va_bits);
+    assert_eq!(PAGE_DIRECTORY.present_bit, level.present_bit);
+    assert_eq!(PAGE_DIRECTORY.huge_page_bit, level.huge_page_bit);
+    assert!(PAGE_DIRECTORY.supports_huge_pages());
+}
+
+#[test]
+fn canonical_hole_of_x86_64() {
+    assert_eq!(PAGE_FORMAT_4K_L4.canonical_hole(), Some(0x0000_8000_0000_0000..0xffff_8000_0000_0000));
+    assert_eq!(PAGE_FORMAT_4K_L5.canonical_hole(), Some(0x0100_0000_0000_0000..0xff00_0000_0000_0000));
+}
+
+#[test]
+fn validate_rejects_a_physical_mask_overlapping_the_flags() {
+    // The physical mask overlaps the present bit.
+    let format = PageFormat { physical_mask: 0x000f_ffff_ffff_f001, ..PAGE_FORMAT_4K_L4 };
+    assert_eq!(format.validate(), Err(FormatError::PhysicalMaskOverlapsFlags));
+
+    // The physical mask overlaps the huge page bit.
+    let format = PageFormat { physical_mask: 0x000f_ffff_ffff_f080, ..PAGE_FORMAT_4K_L4 };
+    assert_eq!(format.validate(), Err(FormatError::PhysicalMaskOverlapsFlags));
+}
+
+#[test]
+fn validate_accepts_every_bundled_format() {
+    let formats: &[(&str, PageFormat<'static>)] = &[
+        ("aarch64 4K L3", aarch64::PAGE_FORMAT_4K_L3),
+        ("aarch64 4K L4", aarch64::PAGE_FORMAT_4K_L4),
+        ("aarch64 4K L4 LPA", aarch64::PAGE_FORMAT_4K_L4_LPA),
+        ("aarch64 16K", aarch64::PAGE_FORMAT_16K),
+        ("aarch64 64K", aarch64::PAGE_FORMAT_64K),
+        ("amdgpu GFX9", amdgpu::PAGE_FORMAT_GFX9),
+        ("arm 4K", arm::PAGE_FORMAT_4K),
+        ("arm 4K PAE", arm::PAGE_FORMAT_4K_PAE),
+        ("mips 4K", mips::PAGE_FORMAT_4K),
+        ("sparc 8K", sparc::PAGE_FORMAT_8K),
+        ("x86 4K", x86::PAGE_FORMAT_4K),
+        ("x86 4K PAE", x86::PAGE_FORMAT_4K_PAE),
+        ("x86-64 4K L4", PAGE_FORMAT_4K_L4),
+        ("x86-64 4K L5", PAGE_FORMAT_4K_L5),
+    ];
+
+    for (name, format) in formats {
+        assert_eq!(format.validate(), Ok(()), "{}", name);
+    }
+}
+
+#[test]
+fn validate_rejects_unsupported_pte_sizes() {
+    // PTEs wider than 8 bytes do not fit in a u64.
+    let format = PageFormat { pte_size: 16, ..PAGE_FORMAT_4K_L4 };
+    assert_eq!(format.validate(), Err(FormatError::UnsupportedPteSize));
+
+    // PTE sizes have to be a power of two.
+    let format = PageFormat { pte_size: 3, ..x86::PAGE_FORMAT_4K };
+    assert_eq!(format.validate(), Err(FormatError::UnsupportedPteSize));
+
+    let format = PageFormat { pte_size: 4, ..x86::PAGE_FORMAT_4K };
+    assert_eq!(format.validate(), Ok(()));
+}
@@ -0,0 +1,101 @@
+//! Tests that build an x86-64 four-level page table hierarchy entirely through the
+//! [`AddressSpace`] on top of the [`MemoryMapper`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, MemoryMapper, PageTableMapper};
+
+#[test]
+fn zero_page_size_is_rejected() {
+    assert_eq!(MemoryMapper::new(1 << 20, 0).err(), Some(MemoryError::InvalidPageSize));
+}
+
+#[test]
+fn allocate_range_maps_distinct_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x40_0000..0x40_4000, PAGE_WRITE).unwrap();
+
+    let mut pages = Vec::new();
+
+    for addr in (0x40_0000..0x40_4000).step_by(0x1000) {
+        let pte = space.read_pte(addr).unwrap();
+        assert_eq!(pte & !PAGE_FORMAT_4K_L4.physical_mask, PAGE_PRESENT | PAGE_WRITE);
+
+        pages.push(space.translate(addr).unwrap().unwrap());
+    }
+
+    pages.sort_unstable();
+    pages.dedup();
+    assert_eq!(pages.len(), 4);
+    assert!(!pages.contains(&root));
+
+    assert!(!space.is_mapped(0x3f_f000).unwrap());
+    assert!(!space.is_mapped(0x40_4000).unwrap());
+}
+
+#[test]
+fn map_range_to_translates_to_physical_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x7fff_0000_0000..0x7fff_0000_3000, 0x8_0000, PAGE_WRITE | PAGE_NX).unwrap();
+
+    assert_eq!(space.translate(0x7fff_0000_0000).unwrap(), Some(0x8_0000));
+    assert_eq!(space.translate(0x7fff_0000_2abc).unwrap(), Some(0x8_2abc));
+    assert_eq!(space.read_pte(0x7fff_0000_1000).unwrap(), 0x8_1000 | PAGE_PRESENT | PAGE_WRITE | PAGE_NX);
+    assert_eq!(space.translate(0x7fff_0000_3000).unwrap(), None);
+}
+
+#[test]
+fn copy_round_trip() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x4000, PAGE_WRITE).unwrap();
+
+    let data: Vec<u8> = (0..0x2000).map(|i| i as u8).collect();
+    space.copy_to(0x1800, &data).unwrap();
+
+    let mut read = vec![0u8; data.len()];
+    space.copy_from(&mut read, 0x1800).unwrap();
+    assert_eq!(read, data);
+
+    // The bytes land in the page that backs the virtual address.
+    let phys = space.translate(0x2000).unwrap().unwrap() as usize;
+    assert_eq!(mapper.memory[phys..phys + 0x10], data[0x800..0x810]);
+}
+
+#[test]
+fn protect_range_changes_flags() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.protect_range(0x2000..0x3000, (PAGE_WRITE, PAGE_NX)).unwrap();
+
+    assert_eq!(space.read_pte(0x1000).unwrap() & PAGE_FLAGS_MASK, PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.read_pte(0x2000).unwrap() & PAGE_FLAGS_MASK, PAGE_PRESENT | PAGE_NX);
+}
+
+#[test]
+fn free_range_reuses_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.free_range(0x1000..0x3000).unwrap();
+
+    assert!(!space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+
+    // The pages and page tables that were freed are reused, such that allocating the same range
+    // again does not allocate any new pages from the physical memory.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+
+    assert_eq!(mapper.alloc_page().unwrap(), 0x6000);
+}
@@ -0,0 +1,164 @@
+//! Tests for mapping, splitting and merging huge pages.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, MemoryMapper};
+
+#[test]
+fn map_range_huge_uses_a_huge_page_when_aligned() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+
+    let (pte, level) = space.read_pte_at(0x20_0000).unwrap();
+    assert_eq!(level, 1);
+    assert_eq!(pte, 0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE);
+
+    // The single huge PTE covers the whole range.
+    assert_eq!(space.read_pte_at(0x3f_f000).unwrap(), (pte, 1));
+    assert_eq!(space.translate(0x3f_f123).unwrap(), Some(0x401f_f123));
+}
+
+#[test]
+fn map_range_huge_falls_back_to_small_pages_when_misaligned() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The virtual range is aligned, but the physical base is not.
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_1000, PAGE_WRITE).unwrap();
+
+    for virt_addr in (0x20_0000..0x40_0000).step_by(0x1000) {
+        let (pte, level) = space.read_pte_at(virt_addr).unwrap();
+
+        assert_eq!(level, 0);
+        assert_eq!(pte & PAGE_HUGE, 0);
+        assert_eq!(space.translate(virt_addr).unwrap(), Some(0x4000_1000 + (virt_addr - 0x20_0000) as u64));
+    }
+}
+
+#[test]
+fn split_huge_page_into_small_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let flags = PAGE_WRITE | PAGE_USER | PAGE_NX;
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, flags).unwrap();
+    space.split_huge_page(0x30_0000).unwrap();
+
+    // The 512 pages cover the same physical range with the same flags.
+    for i in 0..512 {
+        let virt_addr = 0x20_0000 + i * 0x1000;
+        let (pte, level) = space.read_pte_at(virt_addr).unwrap();
+
+        assert_eq!(level, 0);
+        assert_eq!(pte, (0x4000_0000 + i as u64 * 0x1000) | PAGE_PRESENT | flags);
+    }
+
+    // The translation now passes through a leaf page table rather than ending at the directory.
+    let (path, count) = space.translate_path(0x20_0000).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(path[4], 0x4000_0000);
+}
+
+#[test]
+fn split_huge_page_leaves_small_pages_intact() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    let pte = space.read_pte(0x1000).unwrap();
+
+    space.split_huge_page(0x1000).unwrap();
+    assert_eq!(space.read_pte_at(0x1000).unwrap(), (pte, 0));
+
+    assert_eq!(space.split_huge_page(0x4000_0000), Err(MemoryError::PageNotPresent));
+}
+
+/// Maps 512 small pages at `0x20_0000` to the physical range starting at the given physical base.
+fn map_small_pages(space: &mut AddressSpace<MemoryMapper, MemoryError>, phys_base: u64) {
+    space.map_range_to(0x20_0000..0x40_0000, phys_base, PAGE_WRITE).unwrap();
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 0);
+}
+
+#[test]
+fn merge_small_pages_into_huge_page() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    map_small_pages(&mut space, 0x4000_0000);
+
+    assert!(space.try_merge_huge_page(0x30_0000).unwrap());
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap(), (0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE, 1));
+    assert_eq!(space.translate(0x3f_f123).unwrap(), Some(0x401f_f123));
+}
+
+#[test]
+fn merge_rejects_pages_that_are_not_present() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    map_small_pages(&mut space, 0x4000_0000);
+    space.unmap_range(0x30_0000..0x30_1000).unwrap();
+
+    assert!(!space.try_merge_huge_page(0x20_0000).unwrap());
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 0);
+}
+
+#[test]
+fn merge_rejects_misaligned_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    map_small_pages(&mut space, 0x4000_1000);
+
+    assert!(!space.try_merge_huge_page(0x20_0000).unwrap());
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 0);
+}
+
+#[test]
+fn merge_rejects_pages_that_are_not_contiguous() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    map_small_pages(&mut space, 0x4000_0000);
+    space.remap_range(0x30_0000..0x30_1000, 0x5000_0000).unwrap();
+
+    assert!(!space.try_merge_huge_page(0x20_0000).unwrap());
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 0);
+}
+
+#[test]
+fn merge_rejects_pages_with_different_flags() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    map_small_pages(&mut space, 0x4000_0000);
+    space.protect_range(0x30_0000..0x30_1000, (PAGE_WRITE, 0)).unwrap();
+
+    assert!(!space.try_merge_huge_page(0x20_0000).unwrap());
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 0);
+}
+
+#[test]
+fn page_size_histogram_counts_huge_and_small_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x6000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+
+    let histogram = space.page_size_histogram(0..0x4000_0000).unwrap();
+
+    assert_eq!(&histogram[..4], [5, 1, 0, 0]);
+
+    // The byte coverage follows from the page size of each page level.
+    let bytes: usize = histogram.iter()
+        .zip(PAGE_FORMAT_4K_L4.levels)
+        .map(|(count, level)| count * level.page_size())
+        .sum();
+
+    assert_eq!(bytes, 5 * 0x1000 + 0x20_0000);
+}
@@ -0,0 +1,121 @@
+//! Tests for mappers that support mapping whole page tables using
+//! [`PageTableMapper::map_table`].
+
+mod common;
+
+use common::TestMapper;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, PageTableMapper, PteType};
+
+/// Sets up an address space with two pages mapped in the same page table, and returns the mapper
+/// and the root.
+fn setup(map_tables: bool) -> (TestMapper, u64) {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+
+    mapper.map_tables = map_tables;
+    mapper.reset();
+
+    (mapper, root)
+}
+
+#[test]
+fn emptiness_check_uses_mapped_page_table() {
+    let (mut mapper, root) = setup(true);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Free one of the pages, such that the page table is only partially cleared and has to be
+    // scanned.
+    space.free_range(0x1000..0x2000).unwrap();
+    assert!(space.is_mapped(0x2000).unwrap());
+
+    mapper.reset();
+
+    // Free the other page, such that the page tables become empty.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.free_range(0x2000..0x3000).unwrap();
+
+    // The page tables have been scanned through the mapped page tables rather than being read in
+    // batches, and the three page tables below the root have been freed along with the page.
+    assert!(mapper.table_maps.get() > 0);
+    assert_eq!(mapper.batch_reads.get(), 0);
+    assert_eq!(mapper.freed.len(), 4);
+
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    assert!(!space.is_mapped(0x2000).unwrap());
+}
+
+#[test]
+fn emptiness_check_without_mapped_page_table() {
+    let (mut mapper, root) = setup(false);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.free_range(0x1000..0x2000).unwrap();
+    space.free_range(0x2000..0x3000).unwrap();
+    assert!(!space.is_mapped(0x2000).unwrap());
+
+    assert_eq!(mapper.table_maps.get(), 0);
+    assert!(mapper.batch_reads.get() > 0);
+    assert_eq!(mapper.freed.len(), 5);
+}
+
+/// Collects the PTEs of the given range using an immutable walk.
+fn collect(mapper: &mut TestMapper, root: u64, range: core::ops::Range<usize>) -> Vec<(PteType, usize, u64)> {
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, mapper, root);
+    let mut ptes = Vec::new();
+
+    space.for_each_pte(range, |pte_type, range, pte| ptes.push((pte_type, range.start, pte))).unwrap();
+
+    ptes
+}
+
+#[test]
+fn walk_results_are_identical_with_fewer_round_trips() {
+    let (mut mapper, root) = setup(false);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0x20_0000..0x20_4000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    mapper.reset();
+    let batched = collect(&mut mapper, root, 0..0x40_0000);
+    let round_trips = mapper.reads.get() + mapper.batch_reads.get();
+
+    mapper.map_tables = true;
+    mapper.reset();
+    let mapped = collect(&mut mapper, root, 0..0x40_0000);
+
+    assert_eq!(batched, mapped);
+    assert!(round_trips > 0);
+
+    // Every page table is mapped exactly once, i.e. the root page table, the page directory
+    // pointer table, the page directory and the two page tables.
+    assert_eq!(mapper.reads.get() + mapper.batch_reads.get(), 0);
+    assert_eq!(mapper.table_maps.get(), 5);
+}
+
+#[test]
+fn short_mapped_page_table_is_rejected() {
+    let (mut mapper, root) = setup(true);
+    mapper.short_tables = Some(16);
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    let result = space.for_each_pte(0..0x40_0000, |_, _, _| ());
+    assert_eq!(result, Err(MemoryError::PteNotFound));
+
+    // The emptiness check rejects the mapped page table as well, rather than treating the missing
+    // PTEs as empty.
+    assert_eq!(space.free_range(0x1000..0x2000), Err(MemoryError::PteNotFound));
+}
+
+#[test]
+fn map_table_errors_are_propagated() {
+    let (mut mapper, root) = setup(true);
+    mapper.table_error = Some(MemoryError::OutOfBounds);
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    let result = space.for_each_pte(0..0x40_0000, |_, _, _| ());
+    assert_eq!(result, Err(MemoryError::OutOfBounds));
+    assert_eq!(space.free_range(0x1000..0x2000), Err(MemoryError::OutOfBounds));
+}
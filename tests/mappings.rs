@@ -0,0 +1,84 @@
+//! Tests for iterating over the mappings of an [`AddressSpace`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::walkers::Mapping;
+use page_walker::AddressSpace;
+
+#[test]
+fn iter_mappings_in_ascending_order() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Map the pages out of order.
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x5000..0x6000, 0x9_0000, PAGE_USER).unwrap();
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    let mappings: Vec<Mapping> = space.iter_mappings(0..0x4000_0000).unwrap().collect();
+
+    assert_eq!(mappings, [
+        Mapping { virt: 0x1000..0x2000, phys: 0x8_0000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE },
+        Mapping { virt: 0x2000..0x3000, phys: 0x8_1000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE },
+        Mapping { virt: 0x5000..0x6000, phys: 0x9_0000, level: 0, flags: PAGE_PRESENT | PAGE_USER },
+        Mapping { virt: 0x20_0000..0x40_0000, phys: 0x4000_0000, level: 1, flags: PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE },
+    ]);
+}
+
+#[test]
+fn iter_mappings_coalesced_merges_contiguous_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Three physically contiguous pages with the same flags, followed by a contiguous page with
+    // different flags.
+    space.map_range_to(0x1000..0x4000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x4000..0x5000, 0x8_3000, PAGE_WRITE | PAGE_NX).unwrap();
+
+    let mappings: Vec<Mapping> = space.iter_mappings_coalesced(0..0x4000_0000).unwrap().collect();
+
+    assert_eq!(mappings, [
+        Mapping { virt: 0x1000..0x4000, phys: 0x8_0000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE },
+        Mapping { virt: 0x4000..0x5000, phys: 0x8_3000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE | PAGE_NX },
+    ]);
+}
+
+#[test]
+fn iter_non_global_skips_global_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x3000..0x5000, 0x8_2000, PAGE_WRITE | PAGE_GLOBAL).unwrap();
+    space.map_range_to(0x5000..0x6000, 0x8_4000, PAGE_USER).unwrap();
+
+    let ranges: Vec<_> = space.iter_non_global(0..0x4000_0000, PAGE_GLOBAL).unwrap().collect();
+
+    assert_eq!(ranges, [0x1000..0x2000, 0x2000..0x3000, 0x5000..0x6000]);
+}
+
+#[test]
+fn map_range_with_gives_each_page_its_own_mask() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Map the middle page as a read-only guard page.
+    space.map_range_with(0x1000..0x4000, 0x8_0000, |virt_addr, level| {
+        assert_eq!(level, 0);
+
+        match virt_addr {
+            0x2000 => PAGE_NX,
+            _ => PAGE_WRITE,
+        }
+    }).unwrap();
+
+    let mappings: Vec<Mapping> = space.iter_mappings(0..0x4000_0000).unwrap().collect();
+
+    assert_eq!(mappings, [
+        Mapping { virt: 0x1000..0x2000, phys: 0x8_0000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE },
+        Mapping { virt: 0x2000..0x3000, phys: 0x8_1000, level: 0, flags: PAGE_PRESENT | PAGE_NX },
+        Mapping { virt: 0x3000..0x4000, phys: 0x8_2000, level: 0, flags: PAGE_PRESENT | PAGE_WRITE },
+    ]);
+}
@@ -0,0 +1,55 @@
+//! Tests for [`AddressSpace::identity_map`] and [`AddressSpace::offset_map`].
+
+mod common;
+
+use common::setup;
+use core::ops::Range;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError};
+
+#[test]
+fn identity_map_uses_huge_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The range starts with 4K pages up to the first 2M boundary, followed by a 2M page.
+    space.identity_map(0x1f_f000..0x40_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.translate(0x1f_f123).unwrap(), Some(0x1f_f123));
+    assert_eq!(space.translate(0x30_0000).unwrap(), Some(0x30_0000));
+    assert_eq!(space.read_pte_at(0x1f_f000).unwrap().1, 0);
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 1);
+    assert!(!space.is_mapped(0x40_0000).unwrap());
+}
+
+#[test]
+fn offset_map_uses_huge_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let virt_base = 0xffff_8880_0000_0000;
+    space.offset_map(virt_base, 0x4000_0000..0x4040_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.translate(virt_base).unwrap(), Some(0x4000_0000));
+    assert_eq!(space.translate(virt_base + 0x23_4567).unwrap(), Some(0x4023_4567));
+    assert_eq!(space.read_pte_at(virt_base).unwrap().1, 1);
+    assert_eq!(space.read_pte_at(virt_base + 0x20_0000).unwrap().1, 1);
+    assert!(!space.is_mapped(virt_base + 0x40_0000).unwrap());
+}
+
+#[test]
+fn offset_map_rejects_inverted_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let phys_range = Range { start: 0x2000, end: 0x1000 };
+    assert_eq!(space.offset_map(0x1000, phys_range, PAGE_WRITE), Err(MemoryError::PteNotFound));
+}
+
+#[test]
+fn offset_map_rejects_overflow() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    assert_eq!(space.offset_map(usize::MAX - 0xfff, 0..0x2000, PAGE_WRITE), Err(MemoryError::PteNotFound));
+}
@@ -0,0 +1,48 @@
+//! Tests for walking the 2-bit root page table of the x86 PAE page format.
+
+use page_walker::arch::x86::*;
+use page_walker::{AddressSpace, MemoryMapper, PteType};
+
+/// The size of the physical memory.
+const MEMORY_SIZE: usize = 1 << 20;
+
+/// Sets up an address space with the PAE root page table, which only has four entries, placed at
+/// the very end of the physical memory, such that reading a fifth entry fails. Maps a page in the
+/// range of the first and the second entry of the root.
+fn setup() -> (MemoryMapper, u64) {
+    let mut mapper = MemoryMapper::new(MEMORY_SIZE, 4096).unwrap();
+    let root = (MEMORY_SIZE - 4 * 8) as u64;
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_PAE, &mut mapper, root);
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    space.allocate_range(0x4000_0000..0x4000_1000, PAGE_WRITE).unwrap();
+
+    (mapper, root)
+}
+
+#[test]
+fn walk_does_not_read_past_the_root() {
+    let (mut mapper, root) = setup();
+    let space = AddressSpace::new(PAGE_FORMAT_4K_PAE, &mut mapper, root);
+
+    // The range extends far beyond the 4G covered by the root.
+    let mut roots = 0;
+    space.for_each_pte(0..1 << 40, |pte_type, _, _| {
+        if pte_type == PteType::PageTable(2) {
+            roots += 1;
+        }
+    }).unwrap();
+
+    assert_eq!(roots, 4);
+}
+
+#[test]
+fn walk_mut_does_not_read_past_the_root() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_PAE, &mut mapper, root);
+
+    space.protect_range(0..1 << 40, (PAGE_WRITE, 0)).unwrap();
+
+    assert_eq!(space.read_pte(0x1000).unwrap() & PAGE_WRITE, 0);
+    assert_eq!(space.read_pte(0x4000_0000).unwrap() & PAGE_WRITE, 0);
+}
@@ -0,0 +1,92 @@
+//! Tests for decoding and encoding the protection flags of the supported architectures.
+
+use page_walker::arch::aarch64::AArch64PteFlags;
+use page_walker::arch::x86_64::X86PteFlags;
+use page_walker::arch::{aarch64, x86_64};
+use page_walker::{AddressSpace, MemoryMapper, PageTableMapper};
+
+#[test]
+fn aarch64_ap_xn_round_trip() {
+    let bits = [aarch64::PAGE_AP_USER, aarch64::PAGE_AP_READ_ONLY, aarch64::PAGE_UXN, aarch64::PAGE_PXN];
+
+    for combination in 0..1 << bits.len() {
+        let ap = bits.iter()
+            .enumerate()
+            .filter(|(i, _)| combination & (1 << i) != 0)
+            .fold(0, |ap, (_, bit)| ap | bit);
+
+        let permissions = aarch64::decode_ap(ap);
+        assert_eq!(aarch64::encode_ap(permissions), ap);
+
+        // The bits outside of the access permissions are ignored.
+        let pte = 0x8_0000 | aarch64::AArch64PteFlags::VALID.bits() | aarch64::AArch64PteFlags::ACCESS.bits() | ap;
+        assert_eq!(aarch64::decode_ap(pte), permissions);
+        assert_eq!(aarch64::encode_ap(permissions), pte & aarch64::PAGE_AP_MASK);
+
+        assert_eq!(permissions.readable, ap & aarch64::PAGE_AP_USER != 0);
+        assert_eq!(permissions.writable, ap & aarch64::PAGE_AP_READ_ONLY == 0);
+        assert_eq!(permissions.user_exec, ap & aarch64::PAGE_UXN == 0);
+        assert_eq!(permissions.kernel_exec, ap & aarch64::PAGE_PXN == 0);
+    }
+}
+
+#[test]
+fn x86_64_flags_round_trip() {
+    let bits = [
+        x86_64::PAGE_PRESENT,
+        x86_64::PAGE_WRITE,
+        x86_64::PAGE_USER,
+        x86_64::PAGE_NX,
+        x86_64::PAGE_GLOBAL,
+        x86_64::PAGE_ACCESSED,
+        x86_64::PAGE_DIRTY,
+    ];
+
+    for combination in 0..1 << bits.len() {
+        let flags = bits.iter()
+            .enumerate()
+            .filter(|(i, _)| combination & (1 << i) != 0)
+            .fold(0, |flags, (_, bit)| flags | bit);
+
+        let permissions = x86_64::decode_flags(flags);
+        assert_eq!(x86_64::encode_flags(permissions), flags);
+
+        // The physical address bits are never part of the encoded flags.
+        let pte = 0x000f_ffff_ffff_f000 | flags;
+        assert_eq!(x86_64::decode_flags(pte), permissions);
+        assert_eq!(x86_64::encode_flags(permissions), pte & x86_64::PAGE_FLAGS_MASK);
+    }
+
+    // NX is bit 63.
+    let permissions = x86_64::decode_flags(1 << 63 | x86_64::PAGE_PRESENT);
+    assert!(permissions.nx && permissions.present && !permissions.write);
+    assert_eq!(x86_64::encode_flags(permissions), 1 << 63 | 1);
+}
+
+#[test]
+fn typed_flags_map_to_the_raw_bits() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(x86_64::PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let flags = X86PteFlags::WRITE | X86PteFlags::USER | X86PteFlags::NX;
+    assert_eq!(flags.bits(), x86_64::PAGE_WRITE | x86_64::PAGE_USER | x86_64::PAGE_NX);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, flags.bits()).unwrap();
+
+    let pte = space.read_pte(0x1000).unwrap();
+    assert_eq!(pte, 0x8_0000 | x86_64::PAGE_PRESENT | flags.bits());
+    assert_eq!(X86PteFlags::from_bits_truncate(pte), X86PteFlags::PRESENT | flags);
+
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(aarch64::PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let flags = AArch64PteFlags::ACCESS | AArch64PteFlags::AP_USER | AArch64PteFlags::UXN;
+    assert_eq!(flags.bits(), 1 << 10 | aarch64::PAGE_AP_USER | aarch64::PAGE_UXN);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, flags.bits()).unwrap();
+
+    // Page descriptors at the leaf page level have both the valid bit and the table bit set.
+    let pte = space.read_pte(0x1000).unwrap();
+    assert_eq!(pte, 0x8_0000 | (AArch64PteFlags::VALID | AArch64PteFlags::TABLE | flags).bits());
+}
@@ -0,0 +1,112 @@
+//! Tests for page formats with an inverted present bit polarity, i.e. where an invalid bit has to
+//! be cleared to mark the PTE as present.
+
+use page_walker::{AddressSpace, Endian, MemoryMapper, PageFormat, PageLevel, PageTableMapper};
+
+/// The bit that marks the PTE as invalid, i.e. not present, when set.
+const INVALID: u64 = 1 << 0;
+/// A protection bit.
+const WRITE: u64 = 1 << 1;
+
+const LEVELS: &[PageLevel] = &[
+    PageLevel::new(12, 9).with_present(INVALID, 0),
+    PageLevel::new(21, 9).with_present(INVALID, 0),
+    PageLevel::new(30, 9).with_present(INVALID, 0),
+];
+
+const FORMAT: PageFormat<'static> = PageFormat {
+    levels: LEVELS,
+    physical_mask: 0x000f_ffff_ffff_f000,
+    pte_size: 8,
+    physical_high: None,
+    names: None,
+    endian: Endian::Native,
+};
+
+/// Creates a [`MemoryMapper`] with the given amount of physical memory and allocates the root page
+/// table, of which all the entries are empty.
+fn setup(size: usize) -> (MemoryMapper, u64) {
+    let mut mapper = MemoryMapper::new(size, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+
+    FORMAT.clear_page_table(&mut mapper, FORMAT.levels.len(), root).unwrap();
+
+    (mapper, root)
+}
+
+/// Checks that every entry of the page table at the given physical address is empty.
+fn assert_empty(mapper: &MemoryMapper, table: u64) {
+    for i in 0..512 {
+        assert_eq!(FORMAT.read_pte(mapper, table + i * 8).unwrap(), INVALID, "entry {}", i);
+    }
+}
+
+#[test]
+fn empty_pte_is_not_present() {
+    let level = &FORMAT.levels[0];
+
+    assert_eq!(level.empty_pte(), INVALID);
+    assert!(!level.is_present(level.empty_pte()));
+    assert!(level.is_present(0));
+}
+
+#[test]
+fn new_page_tables_are_empty() {
+    let (mut mapper, root) = setup(1 << 20);
+    let mut space = AddressSpace::new(FORMAT, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x2000, WRITE).unwrap();
+
+    assert!(space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x0000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+    assert!(!space.is_mapped(0x20_0000).unwrap());
+    assert!(!space.is_mapped(0x4000_0000).unwrap());
+
+    let pte = space.read_pte(0x1000).unwrap();
+    assert_eq!(pte & INVALID, 0);
+    assert_eq!(pte & WRITE, WRITE);
+}
+
+#[test]
+fn unmap_range_clears_to_empty() {
+    let (mut mapper, root) = setup(1 << 20);
+    let mut space = AddressSpace::new(FORMAT, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, WRITE).unwrap();
+    assert_eq!(space.translate(0x2000).unwrap(), Some(0x8_1000));
+
+    space.unmap_range(0x1000..0x3000).unwrap();
+
+    assert_eq!(space.translate(0x1000).unwrap(), None);
+    assert_eq!(space.translate(0x2000).unwrap(), None);
+}
+
+#[test]
+fn free_range_frees_page_tables() {
+    let (mut mapper, root) = setup(1 << 20);
+    let mut space = AddressSpace::new(FORMAT, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, WRITE).unwrap();
+    space.free_range(0x1000..0x3000).unwrap();
+
+    assert!(!space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+
+    // The page tables have been freed, and the root page table is empty again.
+    assert_empty(&mapper, root);
+}
+
+#[test]
+fn failed_allocation_is_rolled_back() {
+    // The root page table, two page tables and a single page fit, such that the allocation of the
+    // second page fails.
+    let (mut mapper, root) = setup(4 * 4096);
+    let mut space = AddressSpace::new(FORMAT, &mut mapper, root);
+
+    assert!(space.allocate_range(0x1000..0x3000, WRITE).is_err());
+
+    assert!(!space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+    assert_empty(&mapper, root);
+}
@@ -0,0 +1,89 @@
+//! Tests for changing the protection flags of a range in the virtual address space.
+
+mod common;
+
+use common::{setup, table_ptes, PRESENT_TABLES};
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError};
+
+#[test]
+fn protect_range_strict_on_a_fully_mapped_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x4000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.protect_range_strict(0x1000..0x4000, (PAGE_WRITE, PAGE_NX)).unwrap();
+
+    for (i, virt_addr) in (0x1000..0x4000).step_by(0x1000).enumerate() {
+        assert_eq!(space.read_pte(virt_addr).unwrap(), (0x8_0000 + i as u64 * 0x1000) | PAGE_PRESENT | PAGE_NX);
+    }
+
+    // The pages are left untouched if the range extends past the mapped pages.
+    assert_eq!(space.protect_range_strict(0x1000..0x5000, (PAGE_NX, PAGE_WRITE)), Err(MemoryError::PageNotPresent));
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_NX);
+}
+
+#[test]
+fn protect_range_with_tables_grants_write_on_the_tables() {
+    let (mut mapper, root) = setup();
+
+    // Map a read-only page using page tables that do not grant write access.
+    let mut space = AddressSpace::new(PRESENT_TABLES, &mut mapper, root);
+    space.map_range_to(0x1000..0x2000, 0x8_0000, 0).unwrap();
+
+    // The x86-64 page format shares the layout, but allows granting write access on the page
+    // tables. A plain protect_range only changes the page.
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.protect_range(0x1000..0x2000, (0, PAGE_WRITE)).unwrap();
+    assert!(table_ptes(&PAGE_FORMAT_4K_L4, &mapper, root, 0x1000).iter().all(|pte| pte & PAGE_WRITE == 0));
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.protect_range_with_tables(0x1000..0x2000, (0, PAGE_WRITE | PAGE_NX)).unwrap();
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_WRITE | PAGE_NX);
+
+    // The page tables gain the write bit, but not the bits outside of the page table mask.
+    let ptes = table_ptes(&PAGE_FORMAT_4K_L4, &mapper, root, 0x1000);
+    assert_eq!(ptes.len(), 3);
+    assert!(ptes.iter().all(|pte| pte & (PAGE_WRITE | PAGE_NX) == PAGE_WRITE));
+}
+
+#[test]
+fn protect_range_report_only_reports_changed_pages() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Every other page already has the NX bit set.
+    space.map_range_with(0x1000..0x5000, 0x8_0000, |virt_addr, _| {
+        match virt_addr & 0x1000 {
+            0 => PAGE_WRITE,
+            _ => PAGE_WRITE | PAGE_NX,
+        }
+    }).unwrap();
+
+    let changed = space.protect_range_report(0x1000..0x5000, (0, PAGE_NX)).unwrap();
+    assert_eq!(changed, [0x2000..0x3000, 0x4000..0x5000]);
+
+    for virt_addr in (0x1000..0x5000).step_by(0x1000) {
+        assert_eq!(space.read_pte(virt_addr).unwrap() & PAGE_NX, PAGE_NX);
+    }
+
+    // Nothing is reported once all the pages have the NX bit set.
+    assert!(space.protect_range_report(0x1000..0x5000, (0, PAGE_NX)).unwrap().is_empty());
+}
+
+#[test]
+fn protect_range_sticky_preserves_accessed_and_dirty() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE | PAGE_ACCESSED | PAGE_DIRTY).unwrap();
+
+    // Clear a wide mask while keeping the accessed and dirty bits of the first page.
+    let wide = PAGE_WRITE | PAGE_USER | PAGE_ACCESSED | PAGE_DIRTY;
+    space.protect_range_sticky(0x1000..0x2000, (wide, PAGE_NX), PAGE_ACCESSED | PAGE_DIRTY).unwrap();
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_ACCESSED | PAGE_DIRTY | PAGE_NX);
+
+    // Without sticky bits, the same mask clears them.
+    space.protect_range(0x2000..0x3000, (wide, PAGE_NX)).unwrap();
+    assert_eq!(space.read_pte(0x2000).unwrap(), 0x8_1000 | PAGE_PRESENT | PAGE_NX);
+}
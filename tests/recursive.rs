@@ -0,0 +1,58 @@
+//! Tests for walking page table hierarchies with a recursive page table mapping.
+
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryMapper, PageTableMapper, PteType};
+
+/// The index of the root entry that refers back to the root page table.
+const RECURSIVE_INDEX: u64 = 1;
+
+/// Sets up an address space with a page mapped at `0x1000`, where the second entry of the root
+/// page table refers back to the root page table itself, and a page table of the next level
+/// refers back to itself as well.
+fn setup() -> (MemoryMapper, u64) {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    let (path, _) = space.translate_path(0x1000).unwrap();
+
+    mapper.write_pte(8, root + RECURSIVE_INDEX * 8, root | PAGE_PRESENT | PAGE_WRITE).unwrap();
+    mapper.write_pte(8, path[1] + 511 * 8, path[1] | PAGE_PRESENT | PAGE_WRITE).unwrap();
+
+    (mapper, root)
+}
+
+/// Counts the PTEs visited per [`PteType`] level, such that the count of the root level is at
+/// index 3.
+fn count(counts: &mut [usize; 4], pte_type: PteType) {
+    counts[pte_type.level()] += 1;
+}
+
+#[test]
+fn walk_terminates_on_recursive_mapping() {
+    let (mut mapper, root) = setup();
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let mut counts = [0; 4];
+    space.for_each_pte(0..1 << 40, |pte_type, _, _| count(&mut counts, pte_type)).unwrap();
+
+    // The recursive PTEs are visited, but are not descended into again, such that only a single
+    // page table is visited per level.
+    assert_eq!(counts, [512, 512, 512, 2]);
+}
+
+#[test]
+fn walk_mut_terminates_on_recursive_mapping() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let mut counts = [0; 4];
+    space.for_each_pte_mut(0..1 << 40, |pte_type, _, _| count(&mut counts, pte_type)).unwrap();
+
+    // Only the PTEs of pages are visited.
+    assert_eq!(counts, [512, 0, 0, 0]);
+
+    space.protect_range(0..1 << 40, (PAGE_WRITE, 0)).unwrap();
+    assert_eq!(space.read_pte(0x1000).unwrap() & PAGE_WRITE, 0);
+}
@@ -0,0 +1,54 @@
+//! Tests for [`AddressSpace::remap_range`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError};
+
+#[test]
+fn remap_range_changes_frames_but_not_flags() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x2000..0x3000, 0x8_1000, PAGE_USER | PAGE_NX).unwrap();
+
+    let flags = [
+        space.read_pte(0x1000).unwrap() & !PAGE_FORMAT_4K_L4.address_mask(),
+        space.read_pte(0x2000).unwrap() & !PAGE_FORMAT_4K_L4.address_mask(),
+    ];
+
+    space.remap_range(0x1000..0x3000, 0x9_0000).unwrap();
+
+    assert_eq!(space.translate(0x1123).unwrap(), Some(0x9_0123));
+    assert_eq!(space.translate(0x2123).unwrap(), Some(0x9_1123));
+    assert_eq!(space.read_pte(0x1000).unwrap() & !PAGE_FORMAT_4K_L4.address_mask(), flags[0]);
+    assert_eq!(space.read_pte(0x2000).unwrap() & !PAGE_FORMAT_4K_L4.address_mask(), flags[1]);
+}
+
+#[test]
+fn remap_range_advances_by_huge_page_size() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_huge(0x20_0000..0x40_0000, 0x20_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x40_0000..0x40_1000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    space.remap_range(0x20_0000..0x40_1000, 0x4000_0000).unwrap();
+
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap().1, 1);
+    assert_eq!(space.translate(0x30_0000).unwrap(), Some(0x4010_0000));
+    assert_eq!(space.translate(0x40_0000).unwrap(), Some(0x4020_0000));
+}
+
+#[test]
+fn remap_range_leaves_range_intact_on_hole() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.remap_range(0x1000..0x3000, 0x9_0000), Err(MemoryError::PageNotPresent));
+    assert_eq!(space.translate(0x1000).unwrap(), Some(0x8_0000));
+}
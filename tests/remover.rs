@@ -0,0 +1,154 @@
+//! Tests for the combinations of [`PteRemovalFlags`] used by the [`PteRemover`].
+
+mod common;
+
+use common::TestMapper;
+use core::marker::PhantomData;
+use page_walker::arch::x86_64::*;
+use page_walker::format::MAX_LEVELS;
+use page_walker::walkers::remover::{PteRemovalFlags, PteRemover};
+use page_walker::{AddressSpace, PageTableMapper};
+
+/// Sets up an address space with two pages mapped in the same page table. Returns the mapper, the
+/// root, the physical addresses of the pages and the physical addresses of the page tables below
+/// the root.
+fn setup() -> (TestMapper, u64, Vec<u64>, Vec<u64>) {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+
+    let pages = vec![
+        space.translate(0x1000).unwrap().unwrap(),
+        space.translate(0x2000).unwrap().unwrap(),
+    ];
+
+    // The page tables are allocated before the pages they map.
+    let tables = vec![0x1000, 0x2000, 0x3000];
+    assert!(pages.iter().all(|page| !tables.contains(page)));
+
+    mapper.reset();
+
+    (mapper, root, pages, tables)
+}
+
+/// Removes the pages in the range using the given flags. Returns the sorted physical addresses of
+/// the pages and page tables that have been freed, and those of the pages and page tables that
+/// were mapped.
+fn remove(flags: PteRemovalFlags) -> (Vec<u64>, Vec<u64>, Vec<u64>) {
+    let (mut mapper, root, pages, tables) = setup();
+
+    let mut walker = PteRemover {
+        flags,
+        cleared: [0; MAX_LEVELS],
+        format: &PAGE_FORMAT_4K_L4,
+        error: PhantomData,
+        mapper: PhantomData,
+    };
+
+    PAGE_FORMAT_4K_L4.walk_mut(root, 0x1000..0x3000, &mut walker, &mut mapper).unwrap();
+
+    let space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    assert!(!space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+
+    let mut freed = mapper.freed.clone();
+    freed.sort_unstable();
+
+    (freed, pages, tables)
+}
+
+#[test]
+fn empty_flags_only_unmap_pages() {
+    let (freed, _, _) = remove(PteRemovalFlags::empty());
+
+    assert!(freed.is_empty());
+}
+
+#[test]
+fn free_pages_keeps_page_tables() {
+    let (freed, pages, _) = remove(PteRemovalFlags::FREE_PAGES);
+
+    assert_eq!(freed, pages);
+}
+
+#[test]
+fn free_page_tables_keeps_pages() {
+    let (freed, _, tables) = remove(PteRemovalFlags::FREE_PAGE_TABLES);
+
+    assert_eq!(freed, tables);
+}
+
+#[test]
+fn free_pages_and_page_tables() {
+    let (freed, pages, tables) = remove(PteRemovalFlags::FREE_PAGES | PteRemovalFlags::FREE_PAGE_TABLES);
+
+    let mut expected = [pages, tables].concat();
+    expected.sort_unstable();
+
+    assert_eq!(freed, expected);
+}
+
+#[test]
+fn address_space_methods_match_flags() {
+    let (mut mapper, root, pages, tables) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.unmap_range(0x1000..0x3000).unwrap();
+    assert!(mapper.freed.is_empty());
+
+    let (mut mapper, root, _, _) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.free_range_keep_tables(0x1000..0x3000).unwrap();
+    mapper.freed.sort_unstable();
+    assert_eq!(mapper.freed, pages);
+
+    let (mut mapper, root, _, _) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.free_range(0x1000..0x3000).unwrap();
+    mapper.freed.sort_unstable();
+
+    let mut expected = [pages, tables].concat();
+    expected.sort_unstable();
+
+    assert_eq!(mapper.freed, expected);
+}
+
+#[test]
+fn free_range_zeroed_zeroes_pages_before_freeing() {
+    let (mut mapper, root, pages, tables) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.fill(0x1000..0x3000, 0xaa).unwrap();
+    space.free_range(0x1000..0x2000).unwrap();
+
+    // Without zeroing, the contents are still there when the page is freed.
+    assert_eq!(mapper.freed, [pages[0]]);
+    assert_eq!(mapper.freed_zeroed, [false]);
+
+    mapper.reset();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.free_range_zeroed(0x2000..0x3000).unwrap();
+
+    let mut expected = [&pages[1..], &tables[..]].concat();
+    expected.sort_unstable();
+
+    mapper.freed.sort_unstable();
+    assert_eq!(mapper.freed, expected);
+    assert!(mapper.freed_zeroed.iter().all(|&zeroed| zeroed));
+}
+
+#[test]
+fn unmap_range_report_returns_the_mapped_frames() {
+    let (mut mapper, root, pages, _) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The range includes a hole before and after the mapped pages.
+    assert_eq!(space.unmap_range_report(0..0x4000).unwrap(), pages);
+    assert!(!space.is_mapped(0x1000).unwrap());
+    assert!(!space.is_mapped(0x2000).unwrap());
+
+    // Nothing is left to report, and none of the pages have been freed.
+    assert!(space.unmap_range_report(0..0x4000).unwrap().is_empty());
+    assert!(mapper.freed.is_empty());
+}
@@ -0,0 +1,63 @@
+//! Tests that a failed allocation or mapping leaves the address space unchanged.
+
+mod common;
+
+use common::TestMapper;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, PageTableMapper};
+
+/// Sets up an address space with a single page mapped at `0x1000`, and returns the mapper and
+/// the root.
+fn setup() -> (TestMapper, u64) {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+
+    mapper.reset();
+
+    (mapper, root)
+}
+
+#[test]
+fn allocate_range_rolls_back_after_n_allocations() {
+    for n in 0..7 {
+        let (mut mapper, root) = setup();
+        let before = mapper.memory().to_vec();
+
+        mapper.allocs_left = Some(n);
+
+        // The range reuses the existing page table for the first 2M, and needs a new page table
+        // for the part beyond, such that seven pages and page tables get allocated in total.
+        let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+        assert!(space.allocate_range(0x1f_e000..0x20_4000, PAGE_WRITE).is_err(), "n = {}", n);
+
+        assert!(space.is_mapped(0x1000).unwrap());
+        assert!(!space.is_mapped(0x1f_e000).unwrap());
+        assert!(!space.is_mapped(0x20_0000).unwrap());
+
+        // Every page and page table that was allocated has been freed again, and the page tables
+        // are byte-for-byte identical.
+        assert_eq!(mapper.freed.len(), n, "n = {}", n);
+        assert!(mapper.memory() == &before[..], "n = {}", n);
+    }
+}
+
+#[test]
+fn map_range_rolls_back_after_n_allocations() {
+    for n in 0..2 {
+        let (mut mapper, root) = setup();
+        let before = mapper.memory().to_vec();
+
+        mapper.allocs_left = Some(n);
+
+        // Mapping the range needs a new page directory and page table.
+        let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+        assert!(space.map_range_to(0x8000_0000..0x8000_2000, 0x8_0000, PAGE_WRITE).is_err());
+
+        assert!(!space.is_mapped(0x8000_0000).unwrap());
+        assert_eq!(mapper.freed.len(), n, "n = {}", n);
+        assert!(mapper.memory() == &before[..], "n = {}", n);
+    }
+}
@@ -0,0 +1,37 @@
+//! Tests for swapping pages out and back in.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::AddressSpace;
+
+#[test]
+fn swap_out_leaves_the_cookie_in_a_non_present_pte() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    // Encode the swap slot in the upper bits, and keep the frame to swap the page back in later.
+    // The present bit of the cookie is cleared by swap_out.
+    let mut frames = vec![];
+
+    space.swap_out(0x1000..0x4000, |virt_addr, pte| {
+        frames.push(PAGE_FORMAT_4K_L4.pte_to_phys(pte));
+        (virt_addr as u64 >> 12) << 32 | PAGE_PRESENT
+    }).unwrap();
+
+    assert_eq!(frames, [0x8_0000, 0x8_1000]);
+
+    for (slot, virt_addr) in [(1, 0x1000), (2, 0x2000)] {
+        assert!(!space.is_mapped(virt_addr).unwrap());
+        assert_eq!(space.read_pte(virt_addr).unwrap(), slot << 32);
+    }
+
+    // The page is swapped back in by reading the cookie.
+    let cookie = space.read_pte(0x2000).unwrap();
+    let pte = space.populate(0x2000, || Ok((0x9_0000 + (cookie >> 32 << 12), PAGE_WRITE))).unwrap();
+    assert_eq!(pte, 0x9_2000 | PAGE_PRESENT | PAGE_WRITE);
+    assert_eq!(space.translate(0x2123).unwrap(), Some(0x9_2123));
+}
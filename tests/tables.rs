@@ -0,0 +1,43 @@
+//! Tests for counting the page tables backing a virtual address range.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::AddressSpace;
+
+#[test]
+fn count_required_tables_for_two_leaf_tables_under_one_directory() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The range crosses the 2M boundary and thus spans two leaf page tables, which share the same
+    // page directory and page directory pointer table.
+    let range = 0x1f_f000..0x20_1000;
+    assert_eq!(space.count_required_tables(range.clone()).unwrap(), 4);
+
+    // Only the second leaf page table is missing once the first page has been mapped.
+    space.allocate_range(0x1f_f000..0x20_0000, PAGE_WRITE).unwrap();
+    assert_eq!(space.count_required_tables(range.clone()).unwrap(), 1);
+
+    space.allocate_range(range.clone(), PAGE_WRITE).unwrap();
+    assert_eq!(space.count_required_tables(range).unwrap(), 0);
+}
+
+#[test]
+fn table_overhead_bytes_for_two_leaf_tables_and_one_directory() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // Only the root page table backs an empty address space.
+    assert_eq!(space.table_overhead_bytes(0..0x40_0000).unwrap(), 0x1000);
+
+    // The range is backed by two leaf page tables that share the page directory and the page
+    // directory pointer table.
+    space.allocate_range(0x1f_f000..0x20_1000, PAGE_WRITE).unwrap();
+    assert_eq!(space.table_overhead_bytes(0..0x40_0000).unwrap(), 5 * 0x1000);
+
+    // Every page table is counted once, no matter how many pages share it.
+    space.allocate_range(0x1000..0x8000, PAGE_WRITE).unwrap();
+    assert_eq!(space.table_overhead_bytes(0..0x40_0000).unwrap(), 5 * 0x1000);
+}
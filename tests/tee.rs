@@ -0,0 +1,56 @@
+//! Tests for composing page table walkers with [`Tee`].
+
+mod common;
+
+use common::setup;
+use core::marker::PhantomData;
+use core::ops::Range;
+use page_walker::arch::x86_64::*;
+use page_walker::walkers::{ExtentWalker, Tee};
+use page_walker::{AddressSpace, MemoryError, MemoryMapper, PageWalker, PteLocation, PteType};
+
+/// Counts the present pages.
+#[derive(Default)]
+struct PageCounter {
+    count: usize,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for PageCounter {
+    fn handle_pte(&mut self, _mapper: &MemoryMapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), MemoryError> {
+        if matches!(pte_type, PteType::Page(..)) && pte & PAGE_PRESENT != 0 {
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn tee_feeds_a_counter_and_an_extent_walker_in_one_walk() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x3000..0x6000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+
+    let range = 0..0x4000_0000;
+    let mut walker = Tee::new(
+        PageCounter::default(),
+        ExtentWalker {
+            extent: None,
+            format: &PAGE_FORMAT_4K_L4,
+            error: PhantomData,
+            mapper: PhantomData,
+        },
+    );
+
+    space.walk(range.clone(), &mut walker).unwrap();
+
+    assert_eq!(walker.first.count, 4);
+    assert_eq!(walker.second.extent, Some(0x3000..0x40_0000));
+
+    // Both results match those of the separate walks.
+    let histogram = space.page_size_histogram(range.clone()).unwrap();
+    assert_eq!(walker.first.count, histogram.iter().sum());
+    assert_eq!(walker.second.extent, space.mapped_extent(range).unwrap());
+}
@@ -0,0 +1,25 @@
+//! Tests for the [`TrackingMapper`].
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, TrackingMapper};
+
+#[test]
+fn protect_range_dirties_the_ptes_of_the_pages() {
+    let (mapper, root) = setup();
+    let mut mapper = TrackingMapper::new(mapper);
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x4000, 0x8_0000, PAGE_WRITE).unwrap();
+    let (path, _) = space.translate_path(0x1000).unwrap();
+    mapper.clear();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.protect_range(0x1000..0x4000, (PAGE_WRITE, PAGE_NX)).unwrap();
+
+    // Only the PTEs of the three pages within the leaf page table have been written to.
+    let leaf = path[3];
+    assert_eq!(mapper.dirtied(), [leaf + 8, leaf + 16, leaf + 24]);
+}
@@ -0,0 +1,67 @@
+//! Tests for [`AddressSpace::for_each_pte`] and [`AddressSpace::for_each_pte_mut`].
+
+mod common;
+
+use common::{setup, table_ptes};
+use page_walker::arch::x86;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, PteType};
+
+/// A bit that is available for software use on x86-64.
+const PAGE_SOFTWARE: u64 = 1 << 9;
+
+#[test]
+fn for_each_pte_mut_flips_a_software_bit_on_present_leaves() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x4000..0x5000, 0x8_3000, PAGE_WRITE | PAGE_SOFTWARE).unwrap();
+
+    space.for_each_pte_mut(0..0x20_0000, |pte_type, _, pte| {
+        assert!(pte_type.is_page());
+
+        if *pte & PAGE_PRESENT != 0 {
+            *pte ^= PAGE_SOFTWARE;
+        }
+    }).unwrap();
+
+    assert_eq!(space.read_pte(0x1000).unwrap(), 0x8_0000 | PAGE_PRESENT | PAGE_WRITE | PAGE_SOFTWARE);
+    assert_eq!(space.read_pte(0x2000).unwrap(), 0x8_1000 | PAGE_PRESENT | PAGE_WRITE | PAGE_SOFTWARE);
+    assert_eq!(space.read_pte(0x4000).unwrap(), 0x8_3000 | PAGE_PRESENT | PAGE_WRITE);
+
+    // The PTEs that are not present and the PTEs of the page tables are left untouched.
+    let (path, _) = space.translate_path(0x1000).unwrap();
+    assert_eq!(PAGE_FORMAT_4K_L4.read_pte(&mapper, path[3] + 3 * 8).unwrap(), 0);
+    assert!(table_ptes(&PAGE_FORMAT_4K_L4, &mapper, root, 0x1000).iter().all(|pte| pte & PAGE_SOFTWARE == 0));
+}
+
+#[test]
+fn for_each_pte_visits_tables_and_pages_in_order() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(x86::PAGE_FORMAT_4K, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, x86::PAGE_WRITE).unwrap();
+    space.map_range_to(0x80_0000..0x80_1000, 0x9_0000, x86::PAGE_WRITE).unwrap();
+
+    // The empty PTEs are visited as well, i.e. the remaining 1021 PTEs of the first page table and
+    // the PTE of the root page table in between the two page tables.
+    let mut ptes = vec![];
+    let mut empty = 0;
+
+    space.for_each_pte(0x1000..0x80_1000, |pte_type, range, pte| {
+        match pte {
+            0 => empty += 1,
+            _ => ptes.push((pte_type, range)),
+        }
+    }).unwrap();
+
+    assert_eq!(empty, 1022);
+    assert_eq!(ptes, [
+        (PteType::PageTable(1), 0x1000..0x40_0000),
+        (PteType::Page(0, false), 0x1000..0x2000),
+        (PteType::Page(0, false), 0x2000..0x3000),
+        (PteType::PageTable(1), 0x80_0000..0x80_1000),
+        (PteType::Page(0, false), 0x80_0000..0x80_1000),
+    ]);
+}
@@ -0,0 +1,78 @@
+//! Tests that the walkers capture the faulting virtual address using
+//! [`PageTableMapper::page_not_present`].
+
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, MemoryError, MemoryMapper, PageTableMapper, WalkError};
+
+/// A [`PageTableMapper`] that wraps a [`MemoryMapper`] and uses [`WalkError`] as its error type.
+struct WalkMapper {
+    inner: MemoryMapper,
+}
+
+impl PageTableMapper<WalkError<MemoryError>> for WalkMapper {
+    const PTE_NOT_FOUND: WalkError<MemoryError> = WalkError::PteNotFound;
+    const PAGE_NOT_PRESENT: WalkError<MemoryError> = WalkError::PageNotPresent { virt: 0 };
+    const NOT_IMPLEMENTED: WalkError<MemoryError> = WalkError::NotImplemented;
+
+    fn page_not_present(virt: usize) -> WalkError<MemoryError> {
+        WalkError::page_not_present(virt)
+    }
+
+    fn read_bytes(&self, bytes: &mut [u8], phys_addr: u64) -> Result<usize, WalkError<MemoryError>> {
+        self.inner.read_bytes(bytes, phys_addr).map_err(WalkError::Mapper)
+    }
+
+    fn write_bytes(&mut self, phys_addr: u64, bytes: &[u8]) -> Result<usize, WalkError<MemoryError>> {
+        self.inner.write_bytes(phys_addr, bytes).map_err(WalkError::Mapper)
+    }
+
+    fn alloc_page(&mut self) -> Result<u64, WalkError<MemoryError>> {
+        self.inner.alloc_page().map_err(WalkError::Mapper)
+    }
+
+    fn free_page(&mut self, phys_addr: u64) {
+        self.inner.free_page(phys_addr)
+    }
+}
+
+/// Sets up an address space with the pages at `0x1000` and `0x3000` mapped, such that the page at
+/// `0x2000` is a PTE hole.
+fn setup() -> (WalkMapper, u64) {
+    let mut mapper = WalkMapper {
+        inner: MemoryMapper::new(1 << 20, 4096).unwrap(),
+    };
+    let root = mapper.alloc_page().unwrap();
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+    space.allocate_range(0x3000..0x4000, PAGE_WRITE).unwrap();
+
+    (mapper, root)
+}
+
+#[test]
+fn protect_range_strict_reports_the_hole() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    assert_eq!(
+        space.protect_range_strict(0x1000..0x4000, (PAGE_WRITE, 0)),
+        Err(WalkError::PageNotPresent { virt: 0x2000 }),
+    );
+}
+
+#[test]
+fn remap_range_reports_the_hole() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    assert_eq!(space.remap_range(0x1000..0x4000, 0x8_0000), Err(WalkError::PageNotPresent { virt: 0x2000 }));
+}
+
+#[test]
+fn split_huge_page_reports_the_hole() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    assert_eq!(space.split_huge_page(0x2000), Err(WalkError::PageNotPresent { virt: 0x2000 }));
+}
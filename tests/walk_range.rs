@@ -0,0 +1,61 @@
+//! Tests that the end of the range given to the page table walkers is exclusive.
+
+mod common;
+
+use common::setup;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, PteType};
+
+#[test]
+fn walk_does_not_visit_the_end_of_the_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x4000, PAGE_WRITE).unwrap();
+
+    let mut pages = vec![];
+    space.for_each_pte(0x1000..0x3000, |pte_type, range, _| {
+        if let PteType::Page(..) = pte_type {
+            pages.push(range);
+        }
+    }).unwrap();
+
+    assert_eq!(pages, [0x1000..0x2000, 0x2000..0x3000]);
+}
+
+#[test]
+fn map_range_to_does_not_map_the_end_of_the_range() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.translate(0x2fff).unwrap(), Some(0x8_1fff));
+    assert!(!space.is_mapped(0x3000).unwrap());
+}
+
+#[test]
+fn empty_range_visits_nothing() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x2000, PAGE_WRITE).unwrap();
+
+    let mut count = 0;
+    space.for_each_pte(0x1000..0x1000, |_, _, _| count += 1).unwrap();
+
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn range_ending_at_the_top_of_the_address_space() {
+    let (mut mapper, root) = setup();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The page range of the last page wraps around rather than overflowing.
+    space.allocate_range(usize::MAX - 0x1fff..usize::MAX, PAGE_WRITE).unwrap();
+
+    assert!(space.is_mapped(usize::MAX - 0x1fff).unwrap());
+    assert!(space.is_mapped(usize::MAX - 0xfff).unwrap());
+    assert!(!space.is_mapped(usize::MAX - 0x2fff).unwrap());
+}
@@ -0,0 +1,472 @@
+//! Tests for the arguments passed to the callbacks of the page table walkers.
+
+use core::ops::Range;
+use page_walker::arch::x86_64::*;
+use page_walker::{
+    AddressSpace, Descend, HoleKind, MemoryError, MemoryMapper, PageTableMapper, PageWalker,
+    PageWalkerMut, PteLocation, PteType, ReadOnlyAddressSpace, WalkStats,
+};
+
+/// Records the PTE type, the virtual address range, the location and the value of every PTE.
+#[derive(Default)]
+struct LocationRecorder {
+    ptes: Vec<(PteType, Range<usize>, PteLocation, u64)>,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for LocationRecorder {
+    fn handle_pte(&mut self, _mapper: &MemoryMapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &u64) -> Result<(), MemoryError> {
+        self.ptes.push((pte_type, range, location, *pte));
+
+        Ok(())
+    }
+}
+
+impl PageWalkerMut<MemoryMapper, MemoryError> for LocationRecorder {
+    fn handle_pte(&mut self, _mapper: &mut MemoryMapper, pte_type: PteType, range: Range<usize>, location: PteLocation, pte: &mut u64) -> Result<(), MemoryError> {
+        self.ptes.push((pte_type, range, location, *pte));
+
+        Ok(())
+    }
+}
+
+/// Checks that every recorded location is the physical address of the page table holding the PTE
+/// plus the offset of the PTE, and that the PTE is stored at that location.
+fn check_locations(mapper: &MemoryMapper, root: u64, recorder: &LocationRecorder) {
+    let format = &PAGE_FORMAT_4K_L4;
+    let space = ReadOnlyAddressSpace::new(PAGE_FORMAT_4K_L4, mapper, root);
+
+    assert!(!recorder.ptes.is_empty());
+
+    for (pte_type, range, location, pte) in &recorder.ptes {
+        let index = pte_type.level();
+        let level = &format.levels[index];
+        let (path, _) = space.translate_path(range.start).unwrap();
+        let table = path[format.levels.len() - 1 - index];
+
+        assert_eq!(location.index, level.pte_index(range.start));
+        assert_eq!(location.phys_addr, table + (location.index * format.pte_size) as u64);
+        assert_eq!(format.read_pte(mapper, location.phys_addr).unwrap(), *pte);
+    }
+}
+
+#[test]
+fn locations_point_to_the_ptes() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.allocate_range(0x4000_0000..0x4000_1000, PAGE_WRITE).unwrap();
+
+    let mut recorder = LocationRecorder::default();
+    space.walk(0..0x8000_0000, &mut recorder).unwrap();
+
+    let mut recorder_mut = LocationRecorder::default();
+    space.walk_mut(0..0x8000_0000, &mut recorder_mut).unwrap();
+
+    check_locations(&mapper, root, &recorder);
+    check_locations(&mapper, root, &recorder_mut);
+
+    assert_eq!(recorder.ptes, recorder_mut.ptes);
+}
+
+/// Records the page level, the kind, the virtual address range and the value of every PTE hole.
+#[derive(Default)]
+struct HoleRecorder {
+    holes: Vec<(usize, HoleKind, Range<usize>, u64)>,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for HoleRecorder {
+    fn handle_pte_hole(&mut self, _mapper: &MemoryMapper, index: usize, kind: HoleKind, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), MemoryError> {
+        self.holes.push((index, kind, range, *pte));
+
+        Ok(())
+    }
+}
+
+#[test]
+fn hole_kind_tells_empty_and_non_zero_holes_apart() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The page at 0x2000 is swapped out, whereas the page at 0x3000 was never mapped.
+    space.allocate_range(0x1000..0x3000, PAGE_WRITE).unwrap();
+    space.swap_out(0x2000..0x3000, |_, _| 0x42 << 12).unwrap();
+
+    let mut recorder = HoleRecorder::default();
+    space.walk(0x1000..0x4000, &mut recorder).unwrap();
+
+    assert_eq!(recorder.holes, [
+        (0, HoleKind::NonZero, 0x2000..0x3000, 0x42 << 12),
+        (0, HoleKind::Empty, 0x3000..0x4000, 0),
+    ]);
+}
+
+#[test]
+fn location_index_matches_pte_index() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    let virt_addr = 0x80_4020_3000;
+    space.allocate_range(virt_addr..virt_addr + 0x1000, PAGE_WRITE).unwrap();
+
+    let mut recorder = LocationRecorder::default();
+    space.walk(virt_addr..virt_addr + 0x1000, &mut recorder).unwrap();
+
+    let indices: Vec<(usize, usize)> = recorder.ptes.iter()
+        .map(|(pte_type, _, location, _)| (pte_type.level(), location.index))
+        .collect();
+
+    assert_eq!(indices, [(3, 1), (2, 1), (1, 1), (0, 3)]);
+
+    for (level, index) in indices {
+        assert_eq!(index, PAGE_FORMAT_4K_L4.levels[level].pte_index(virt_addr));
+    }
+}
+
+/// Fills the PTE holes with page tables down to the level of 2M pages, where it fills the PTE
+/// hole with a huge page instead. Records the PTE type of every PTE that is visited.
+#[derive(Default)]
+struct HugePageFiller {
+    visited: Vec<PteType>,
+}
+
+impl PageWalkerMut<MemoryMapper, MemoryError> for HugePageFiller {
+    fn handle_pte(&mut self, _mapper: &mut MemoryMapper, pte_type: PteType, _range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), MemoryError> {
+        self.visited.push(pte_type);
+
+        Ok(())
+    }
+
+    fn handle_pte_hole(&mut self, mapper: &mut MemoryMapper, index: usize, _kind: HoleKind, _range: Range<usize>, _location: PteLocation, pte: &mut u64) -> Result<(), MemoryError> {
+        let format = &PAGE_FORMAT_4K_L4;
+
+        *pte = match index {
+            1 => format.make_huge_pte(1, 0x4000_0000, PAGE_WRITE),
+            _ => {
+                let page_table = mapper.alloc_page()?;
+                format.clear_page_table(mapper, index, page_table)?;
+                format.make_pte(index, page_table, format.levels[index].page_table_mask)
+            }
+        };
+
+        Ok(())
+    }
+}
+
+#[test]
+fn filling_a_hole_with_a_huge_page_does_not_descend() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+
+    let mut walker = HugePageFiller::default();
+    PAGE_FORMAT_4K_L4.walk_mut(root, 0x20_0000..0x40_0000, &mut walker, &mut mapper).unwrap();
+
+    // The leaf page level is never visited, as the huge page is not treated as a page table.
+    assert_eq!(walker.visited, [PteType::PageTable(3), PteType::PageTable(2), PteType::PageTable(1)]);
+
+    let space = ReadOnlyAddressSpace::new(PAGE_FORMAT_4K_L4, &mapper, root);
+    assert_eq!(space.read_pte_at(0x20_0000).unwrap(), (0x4000_0000 | PAGE_PRESENT | PAGE_HUGE | PAGE_WRITE, 1));
+    assert_eq!(space.translate(0x23_4567).unwrap(), Some(0x4003_4567));
+}
+
+#[test]
+fn walk_from_matches_the_leaves_of_the_full_walk() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_huge(0x4000_0000..0x4020_0000, 0x20_0000, PAGE_WRITE).unwrap();
+
+    // The second table on the path is the page table at page level 2 that covers the first 512G.
+    let (path, _) = space.translate_path(0x1000).unwrap();
+    let range = 0..0x8000_0000;
+
+    let mut full = LocationRecorder::default();
+    space.walk(range.clone(), &mut full).unwrap();
+
+    let mut partial = LocationRecorder::default();
+    PAGE_FORMAT_4K_L4.walk_from(path[1], 2, range, &mut partial, &mapper).unwrap();
+
+    let leaves = |recorder: LocationRecorder| -> Vec<_> {
+        recorder.ptes
+            .into_iter()
+            .filter(|(pte_type, _, _, pte)| matches!(pte_type, PteType::Page(..)) && pte & PAGE_PRESENT != 0)
+            .collect()
+    };
+
+    let leaves_full = leaves(full);
+    assert_eq!(leaves_full.len(), 3);
+    assert_eq!(leaves(partial), leaves_full);
+}
+
+/// Skips the page tables covering the given virtual address range, and records the present pages
+/// as well as the page tables for which the post-PTE callback is invoked.
+struct TableSkipper {
+    skip: Range<usize>,
+    pages: Vec<Range<usize>>,
+    post: Vec<(usize, Range<usize>)>,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for TableSkipper {
+    fn handle_pte(&mut self, _mapper: &MemoryMapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, pte: &u64) -> Result<(), MemoryError> {
+        if matches!(pte_type, PteType::Page(..)) && pte & PAGE_PRESENT != 0 {
+            self.pages.push(range);
+        }
+
+        Ok(())
+    }
+
+    fn handle_pre_table(&mut self, _mapper: &MemoryMapper, _level: usize, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<Descend, MemoryError> {
+        if range == self.skip {
+            Ok(Descend::Skip)
+        } else {
+            Ok(Descend::Into)
+        }
+    }
+
+    fn handle_post_pte(&mut self, _mapper: &MemoryMapper, level: usize, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), MemoryError> {
+        self.post.push((level, range));
+
+        Ok(())
+    }
+}
+
+#[test]
+fn descend_skip_prunes_a_directory() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x4000_1000..0x4000_3000, 0x9_0000, PAGE_WRITE).unwrap();
+
+    // Skip the page directory covering the second gigabyte, which holds the leaf page table of the
+    // second mapping.
+    let mut walker = TableSkipper {
+        skip: 0x4000_0000..0x8000_0000,
+        pages: vec![],
+        post: vec![],
+    };
+
+    space.walk(0..0x8000_0000, &mut walker).unwrap();
+
+    assert_eq!(walker.pages.len(), 1);
+    assert_eq!(walker.pages[0], 0x1000..0x2000);
+    assert_eq!(walker.post, [
+        (1, 0..0x20_0000),
+        (2, 0..0x4000_0000),
+        (3, 0..0x8000_0000),
+    ]);
+}
+
+/// A minimal walker that only implements [`PageWalker::handle_pte`] and
+/// [`PageWalkerMut::handle_pte`], relying on the default implementations of the other callbacks.
+#[derive(Default)]
+struct MinimalWalker {
+    visited: usize,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for MinimalWalker {
+    fn handle_pte(&mut self, _mapper: &MemoryMapper, _pte_type: PteType, _range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), MemoryError> {
+        self.visited += 1;
+
+        Ok(())
+    }
+}
+
+impl PageWalkerMut<MemoryMapper, MemoryError> for MinimalWalker {
+    fn handle_pte(&mut self, _mapper: &mut MemoryMapper, _pte_type: PteType, _range: Range<usize>, _location: PteLocation, _pte: &mut u64) -> Result<(), MemoryError> {
+        self.visited += 1;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn minimal_walker_only_implements_handle_pte() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    // One PTE at each of the three upper page levels and two PTEs at the leaf page level.
+    let mut walker = MinimalWalker::default();
+    space.walk(0x1000..0x3000, &mut walker).unwrap();
+    assert_eq!(walker.visited, 5);
+
+    let mut walker = MinimalWalker::default();
+    space.walk_mut(0x1000..0x3000, &mut walker).unwrap();
+    assert_eq!(walker.visited, 5);
+}
+
+#[test]
+fn walk_checked_rejects_a_range_straddling_the_canonical_hole() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+
+    // The range starts at the top of the lower half and ends at the bottom of the upper half.
+    let range = 0x0000_7fff_ffff_f000..0xffff_8000_0000_1000;
+
+    let mut walker = MinimalWalker::default();
+    assert_eq!(PAGE_FORMAT_4K_L4.walk_checked(root, range.clone(), &mut walker, &mapper), Err(MemoryError::PteNotFound));
+    assert_eq!(walker.visited, 0);
+
+    let mut walker = MinimalWalker::default();
+    PAGE_FORMAT_4K_L4.walk(root, range, &mut walker, &mapper).unwrap();
+    assert!(walker.visited > 0);
+
+    // A range within one half passes the check.
+    let mut walker = MinimalWalker::default();
+    PAGE_FORMAT_4K_L4.walk_checked(root, 0xffff_8000_0000_0000..0xffff_8000_0000_1000, &mut walker, &mapper).unwrap();
+    assert_eq!(walker.visited, 1);
+}
+
+#[test]
+fn address_space_walk_runs_a_user_walker() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x5000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_to(0x20_0000..0x20_1000, 0x9_0000, PAGE_WRITE).unwrap();
+
+    // The walk visits every PTE within the range, including the ones that are not present.
+    let mut walker = MinimalWalker::default();
+    space.walk(0..0x40_0000, &mut walker).unwrap();
+    assert_eq!(walker.visited, 1 + 1 + 2 + 2 * 512);
+
+    let mut walker_mut = MinimalWalker::default();
+    space.walk_mut(0..0x40_0000, &mut walker_mut).unwrap();
+    assert_eq!(walker_mut.visited, walker.visited);
+
+    // The walk is the same as walking the page format with the root and the mapper.
+    let mut direct = MinimalWalker::default();
+    PAGE_FORMAT_4K_L4.walk(root, 0..0x40_0000, &mut direct, &mapper).unwrap();
+    assert_eq!(direct.visited, walker.visited);
+}
+
+/// A callback invoked by the page table walker.
+#[derive(Debug, PartialEq)]
+enum Event {
+    Pte(PteType, Range<usize>),
+    PreTable(usize, Range<usize>),
+    PostPte(usize, Range<usize>),
+}
+
+/// Records the sequence of callbacks invoked by the page table walker.
+#[derive(Default)]
+struct EventRecorder {
+    events: Vec<Event>,
+}
+
+impl PageWalker<MemoryMapper, MemoryError> for EventRecorder {
+    fn handle_pte(&mut self, _mapper: &MemoryMapper, pte_type: PteType, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), MemoryError> {
+        self.events.push(Event::Pte(pte_type, range));
+
+        Ok(())
+    }
+
+    fn handle_pre_table(&mut self, _mapper: &MemoryMapper, level: usize, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<Descend, MemoryError> {
+        self.events.push(Event::PreTable(level, range));
+
+        Ok(Descend::Into)
+    }
+
+    fn handle_post_pte(&mut self, _mapper: &MemoryMapper, level: usize, range: Range<usize>, _location: PteLocation, _pte: &u64) -> Result<(), MemoryError> {
+        self.events.push(Event::PostPte(level, range));
+
+        Ok(())
+    }
+}
+
+#[test]
+fn callbacks_fire_in_pre_order_and_post_order() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    // The two pages are mapped by two leaf page tables under the same page directory.
+    space.map_range_to(0x1f_f000..0x20_1000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    let mut walker = EventRecorder::default();
+    space.walk(0x1f_f000..0x20_1000, &mut walker).unwrap();
+
+    assert_eq!(walker.events, [
+        Event::Pte(PteType::PageTable(3), 0x1f_f000..0x20_1000),
+        Event::PreTable(3, 0x1f_f000..0x20_1000),
+        Event::Pte(PteType::PageTable(2), 0x1f_f000..0x20_1000),
+        Event::PreTable(2, 0x1f_f000..0x20_1000),
+        Event::Pte(PteType::PageTable(1), 0x1f_f000..0x20_0000),
+        Event::PreTable(1, 0x1f_f000..0x20_0000),
+        Event::Pte(PteType::Page(0, false), 0x1f_f000..0x20_0000),
+        Event::PostPte(1, 0x1f_f000..0x20_0000),
+        Event::Pte(PteType::PageTable(1), 0x20_0000..0x20_1000),
+        Event::PreTable(1, 0x20_0000..0x20_1000),
+        Event::Pte(PteType::Page(0, false), 0x20_0000..0x20_1000),
+        Event::PostPte(1, 0x20_0000..0x20_1000),
+        Event::PostPte(2, 0x1f_f000..0x20_1000),
+        Event::PostPte(3, 0x1f_f000..0x20_1000),
+    ]);
+}
+
+#[test]
+fn walk_with_stats_counts_every_field() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    // One PTE in each of the three upper page tables and four PTEs in the leaf page table, of
+    // which the first and the last are holes.
+    let mut stats = WalkStats::default();
+    PAGE_FORMAT_4K_L4.walk_with_stats(root, 0..0x4000, &mut MinimalWalker::default(), &mapper, &mut stats).unwrap();
+
+    assert_eq!(stats, WalkStats {
+        ptes_read: 7,
+        tables_visited: 4,
+        holes: 2,
+        max_depth: 4,
+    });
+
+    // The walk stops at the hole in the page directory pointer table, and the statistics are
+    // added to the existing ones.
+    PAGE_FORMAT_4K_L4.walk_with_stats(root, 0x4000_0000..0x4000_1000, &mut MinimalWalker::default(), &mapper, &mut stats).unwrap();
+
+    assert_eq!(stats, WalkStats {
+        ptes_read: 9,
+        tables_visited: 6,
+        holes: 3,
+        max_depth: 4,
+    });
+}
+
+#[test]
+fn page_type_tells_leaves_and_huge_pages_apart() {
+    let mut mapper = MemoryMapper::new(1 << 20, 4096).unwrap();
+    let root = mapper.alloc_page().unwrap();
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+
+    space.map_range_to(0x1000..0x2000, 0x8_0000, PAGE_WRITE).unwrap();
+    space.map_range_huge(0x20_0000..0x40_0000, 0x4000_0000, PAGE_WRITE).unwrap();
+
+    let mut walker = LocationRecorder::default();
+    space.walk(0..0x40_0000, &mut walker).unwrap();
+
+    let pages: Vec<(PteType, Range<usize>)> = walker.ptes
+        .into_iter()
+        .filter(|(pte_type, _, _, pte)| matches!(pte_type, PteType::Page(..)) && pte & PAGE_PRESENT != 0)
+        .map(|(pte_type, range, _, _)| (pte_type, range))
+        .collect();
+
+    // A page at the leaf page level is never huge, whereas a page at a higher page level is.
+    assert_eq!(pages, [
+        (PteType::Page(0, false), 0x1000..0x2000),
+        (PteType::Page(1, true), 0x20_0000..0x40_0000),
+    ]);
+}
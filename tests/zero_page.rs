@@ -0,0 +1,44 @@
+//! Tests for zeroing newly allocated page tables using [`PageTableMapper::zero_page`].
+
+mod common;
+
+use common::TestMapper;
+use page_walker::arch::x86_64::*;
+use page_walker::{AddressSpace, PageTableMapper};
+
+#[test]
+fn page_tables_are_zeroed_before_use() {
+    let mut mapper = TestMapper::new();
+    let root = mapper.alloc_page().unwrap();
+    mapper.inner.zero_page(8, root, 4096).unwrap();
+
+    // Every page handed out from now on is filled with garbage that looks like present PTEs.
+    mapper.dirty_pages = true;
+
+    let mut space = AddressSpace::new(PAGE_FORMAT_4K_L4, &mut mapper, root);
+    space.map_range_to(0x1000..0x3000, 0x8_0000, PAGE_WRITE).unwrap();
+
+    assert_eq!(space.translate(0x1123).unwrap(), Some(0x8_0123));
+    assert_eq!(space.translate(0x2123).unwrap(), Some(0x8_1123));
+
+    // None of the stale bits of the page tables show up as mappings.
+    assert!(!space.is_mapped(0x0).unwrap());
+    assert!(!space.is_mapped(0x3000).unwrap());
+    assert!(!space.is_mapped(0x20_0000).unwrap());
+    assert!(!space.is_mapped(0x4000_0000).unwrap());
+
+    // The page tables only contain the PTEs that have been set up.
+    let (path, count) = space.translate_path(0x1000).unwrap();
+    assert_eq!(count, 4);
+
+    for (i, table) in path[1..4].iter().enumerate() {
+        let start = *table as usize;
+        let nonzero = mapper.memory()[start..start + 4096]
+            .chunks(8)
+            .filter(|pte| pte.iter().any(|&byte| byte != 0))
+            .count();
+
+        // The leaf page table maps two pages, the other page tables map a single page table.
+        assert_eq!(nonzero, if i == 2 { 2 } else { 1 });
+    }
+}